@@ -0,0 +1,71 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- Stable company identity that survives ticker symbol changes
+CREATE TABLE IF NOT EXISTS companies (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Every ticker a company has traded under; valid_until is NULL for the current one
+CREATE TABLE IF NOT EXISTS ticker_aliases (
+    ticker TEXT PRIMARY KEY,
+    company_id INTEGER NOT NULL REFERENCES companies(id),
+    valid_from TEXT,
+    valid_until TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_ticker_aliases_company_id ON ticker_aliases(company_id);
+
+-- Register one company per known ticker
+INSERT INTO companies (id, name)
+SELECT ROW_NUMBER() OVER (ORDER BY t.ticker),
+    (SELECT m.name FROM market_caps m WHERE m.ticker = t.ticker ORDER BY m.timestamp DESC LIMIT 1)
+FROM (
+    SELECT ticker FROM market_caps
+    UNION
+    SELECT ticker FROM ticker_details
+) t;
+
+INSERT INTO ticker_aliases (ticker, company_id)
+SELECT ticker, ROW_NUMBER() OVER (ORDER BY ticker)
+FROM (
+    SELECT ticker FROM market_caps
+    UNION
+    SELECT ticker FROM ticker_details
+);
+
+-- Link symbol changes that were applied before companies existed: a new ticker
+-- without history of its own joins the company of the old one, then the old ticker
+-- joins the company of the new ticker and is retired on the change date
+INSERT OR IGNORE INTO ticker_aliases (ticker, company_id, valid_from)
+SELECT s.new_symbol, o.company_id, s.change_date
+FROM symbol_changes s
+JOIN ticker_aliases o ON o.ticker = s.old_symbol
+WHERE s.applied = 1;
+
+UPDATE ticker_aliases
+SET company_id = (
+        SELECT n.company_id
+        FROM symbol_changes s
+        JOIN ticker_aliases n ON n.ticker = s.new_symbol
+        WHERE s.applied = 1 AND s.old_symbol = ticker_aliases.ticker
+        ORDER BY s.change_date DESC
+        LIMIT 1
+    ),
+    valid_until = (
+        SELECT s.change_date
+        FROM symbol_changes s
+        WHERE s.applied = 1 AND s.old_symbol = ticker_aliases.ticker
+        ORDER BY s.change_date DESC
+        LIMIT 1
+    )
+WHERE ticker IN (SELECT old_symbol FROM symbol_changes WHERE applied = 1);
+
+DELETE FROM companies WHERE id NOT IN (SELECT company_id FROM ticker_aliases);
+
+-- History stays under the ticker it was stored with; readers map retired tickers to
+-- the current one through ticker_aliases
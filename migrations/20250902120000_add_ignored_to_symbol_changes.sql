@@ -0,0 +1,6 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- Symbol changes rejected during review are kept but no longer reported as pending
+ALTER TABLE symbol_changes ADD COLUMN ignored INTEGER DEFAULT 0;
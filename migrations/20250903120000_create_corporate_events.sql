@@ -0,0 +1,25 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- Delistings, acquisitions and other events that take a ticker out of the rankings
+CREATE TABLE IF NOT EXISTS corporate_events (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticker TEXT NOT NULL,
+    event_type TEXT NOT NULL,
+    event_date TEXT NOT NULL,
+    reason TEXT,
+    source TEXT NOT NULL,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP,
+    UNIQUE(ticker, event_type)
+);
+
+CREATE INDEX IF NOT EXISTS idx_corporate_events_ticker ON corporate_events(ticker);
+
+-- Consecutive fetch failures per ticker, reset on the next successful fetch
+CREATE TABLE IF NOT EXISTS ticker_fetch_failures (
+    ticker TEXT PRIMARY KEY,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_failed_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
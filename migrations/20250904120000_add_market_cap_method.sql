@@ -0,0 +1,6 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- How each market cap was obtained: profile, historical, close_x_shares or quote_fallback
+ALTER TABLE market_caps ADD COLUMN market_cap_method TEXT;
@@ -0,0 +1,11 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- Where each market cap came from and which exchange rates converted it
+ALTER TABLE market_caps ADD COLUMN source_provider TEXT;
+ALTER TABLE market_caps ADD COLUMN source_endpoint TEXT;
+ALTER TABLE market_caps ADD COLUMN fetched_at INTEGER;
+ALTER TABLE market_caps ADD COLUMN fx_rate_eur REAL;
+ALTER TABLE market_caps ADD COLUMN fx_rate_usd REAL;
+ALTER TABLE market_caps ADD COLUMN fx_rate_timestamp INTEGER;
@@ -0,0 +1,22 @@
+-- SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+--
+-- SPDX-License-Identifier: AGPL-3.0-only
+
+-- Hand-corrected market caps, currencies and names, or exclusions, for a ticker over a date range
+CREATE TABLE IF NOT EXISTS manual_overrides (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    ticker TEXT NOT NULL,
+    market_cap REAL,
+    currency TEXT,
+    name TEXT,
+    exclude BOOLEAN NOT NULL DEFAULT 0,
+    start_date TEXT NOT NULL,
+    end_date TEXT,
+    reason TEXT,
+    created_at DATETIME DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_manual_overrides_ticker ON manual_overrides(ticker);
+
+-- Fields of a stored market cap that were overridden, e.g. "market_cap, name"
+ALTER TABLE market_caps ADD COLUMN overridden TEXT;
@@ -8,6 +8,7 @@ use reqwest::Client;
 use serde::Deserialize;
 use serde_json::{self, Value};
 use std::collections::HashMap;
+use std::fmt;
 use std::sync::Arc;
 use std::{env, time::Duration};
 use tokio::sync::Semaphore;
@@ -26,6 +27,54 @@ pub struct SymbolChange {
     pub name: Option<String>,
 }
 
+/// Root of FMP's API, before the version segment
+const FMP_BASE_URL: &str = "https://financialmodelingprep.com/api";
+
+/// Upper bound on the pages requested from the delisted companies endpoint
+const MAX_DELISTED_PAGES: usize = 100;
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DelistedCompany {
+    pub symbol: String,
+    #[serde(rename = "companyName")]
+    pub company_name: Option<String>,
+    pub exchange: Option<String>,
+    #[serde(rename = "delistedDate")]
+    pub delisted_date: Option<String>,
+}
+
+/// A company returned by FMP's stock screener
+#[derive(Debug, Deserialize, Clone)]
+pub struct ScreenerResult {
+    pub symbol: String,
+    #[serde(rename = "companyName")]
+    pub company_name: Option<String>,
+    #[serde(rename = "marketCap")]
+    pub market_cap: Option<f64>,
+    pub industry: Option<String>,
+    #[serde(rename = "exchangeShortName")]
+    pub exchange: Option<String>,
+    pub country: Option<String>,
+}
+
+/// The provider has no data for a ticker, as opposed to a request that failed
+#[derive(Debug)]
+pub struct NoDataFound;
+
+impl fmt::Display for NoDataFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("No data found for ticker")
+    }
+}
+
+impl std::error::Error for NoDataFound {}
+
+/// Whether a fetch failed because the provider does not know the ticker (an empty
+/// profile or a 404), rather than because of a timeout, rate limit or API key problem
+pub fn is_no_data_found(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<NoDataFound>().is_some()
+}
+
 pub struct PolygonClient {
     client: Client,
     api_key: String,
@@ -35,6 +84,7 @@ pub struct PolygonClient {
 pub struct FMPClient {
     client: Client,
     api_key: String,
+    base_url: String,
     rate_limiter: Arc<Semaphore>,
 }
 
@@ -46,10 +96,20 @@ impl FMPClient {
         Self {
             client: Client::new(),
             api_key,
+            base_url: FMP_BASE_URL.to_string(),
             rate_limiter,
         }
     }
 
+    /// Send requests to a stand-in for the FMP API
+    #[cfg(test)]
+    fn with_base_url(self, base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_string(),
+            ..self
+        }
+    }
+
     async fn make_request<T: for<'de> Deserialize<'de>>(&self, url: String) -> Result<T> {
         let mut retries = 0;
         let max_retries = 3;
@@ -65,6 +125,9 @@ impl FMPClient {
                 .send()
                 .await
                 .context("Failed to send request")?;
+            if response.status() == reqwest::StatusCode::NOT_FOUND {
+                return Err(NoDataFound.into());
+            }
 
             // Get the response text first to log in case of error
             let text = response
@@ -111,10 +174,7 @@ impl FMPClient {
     }
 
     pub async fn fetch_symbol_changes(&self) -> Result<Vec<SymbolChange>> {
-        let url = format!(
-            "https://financialmodelingprep.com/api/v4/symbol_change?apikey={}",
-            self.api_key
-        );
+        let url = format!("{}/v4/symbol_change?apikey={}", self.base_url, self.api_key);
 
         let response: Vec<SymbolChange> = self
             .make_request(url)
@@ -124,6 +184,51 @@ impl FMPClient {
         Ok(response)
     }
 
+    /// Fetch FMP's list of delisted companies, following pages until an empty one
+    pub async fn fetch_delisted_companies(&self) -> Result<Vec<DelistedCompany>> {
+        let mut companies = Vec::new();
+        for page in 0..MAX_DELISTED_PAGES {
+            let url = format!(
+                "{}/v3/delisted-companies?page={}&apikey={}",
+                self.base_url, page, self.api_key
+            );
+            let batch: Vec<DelistedCompany> = self
+                .make_request(url)
+                .await
+                .context("Failed to fetch delisted companies from FMP API")?;
+            if batch.is_empty() {
+                break;
+            }
+            companies.extend(batch);
+        }
+
+        Ok(companies)
+    }
+
+    /// Screen actively trading companies in an industry above a market cap
+    pub async fn screen_industry(
+        &self,
+        industry: &str,
+        min_market_cap: u64,
+    ) -> Result<Vec<ScreenerResult>> {
+        let url = reqwest::Url::parse_with_params(
+            &format!("{}/v3/stock-screener", self.base_url),
+            &[
+                ("industry", industry),
+                ("marketCapMoreThan", &min_market_cap.to_string()),
+                ("isActivelyTrading", "true"),
+                ("isEtf", "false"),
+                ("isFund", "false"),
+                ("limit", "1000"),
+                ("apikey", &self.api_key),
+            ],
+        )?;
+
+        self.make_request(url.to_string())
+            .await
+            .with_context(|| format!("Failed to screen industry '{}' on FMP API", industry))
+    }
+
     pub async fn get_details(
         &self,
         ticker: &str,
@@ -135,16 +240,16 @@ impl FMPClient {
 
         // Prepare URLs for all three requests
         let profile_url = format!(
-            "https://financialmodelingprep.com/api/v3/profile/{}?apikey={}",
-            ticker, self.api_key
+            "{}/v3/profile/{}?apikey={}",
+            self.base_url, ticker, self.api_key
         );
         let ratios_url = format!(
-            "https://financialmodelingprep.com/api/v3/ratios/{}?apikey={}",
-            ticker, self.api_key
+            "{}/v3/ratios/{}?apikey={}",
+            self.base_url, ticker, self.api_key
         );
         let income_url = format!(
-            "https://financialmodelingprep.com/api/v3/income-statement/{}?limit=1&apikey={}",
-            ticker, self.api_key
+            "{}/v3/income-statement/{}?limit=1&apikey={}",
+            self.base_url, ticker, self.api_key
         );
 
         // Make all three requests in parallel
@@ -155,7 +260,7 @@ impl FMPClient {
         )?;
 
         if profiles.is_empty() {
-            anyhow::bail!("No data found for ticker");
+            return Err(NoDataFound.into());
         }
 
         let profile = &profiles[0];
@@ -172,7 +277,7 @@ impl FMPClient {
             name: Some(profile.company_name.clone()),
             currency_name: Some(currency.to_string()),
             currency_symbol: Some(currency.to_string()),
-            active: Some(profile.is_active),
+            active: profile.is_active,
             description: Some(profile.description.clone()),
             homepage_url: Some(profile.website.clone()),
             weighted_shares_outstanding: None,
@@ -216,9 +321,12 @@ impl FMPClient {
         ticker: &str,
         date: &DateTime<Utc>,
     ) -> Result<HistoricalMarketCap> {
+        let day = date.date_naive();
+
         // First try historical market cap endpoint
         let url = format!(
-            "https://financialmodelingprep.com/api/v3/historical-market-capitalization/{}?from={}&to={}&apikey={}",
+            "{}/v3/historical-market-capitalization/{}?from={}&to={}&apikey={}",
+            self.base_url,
             ticker,
             date.format("%Y-%m-%d"),
             date.format("%Y-%m-%d"),
@@ -226,69 +334,121 @@ impl FMPClient {
         );
 
         let response: Vec<Value> = self.make_request(url).await?;
-
-        if let Some(data) = response.first() {
-            let market_cap = data["marketCap"].as_f64().unwrap_or(0.0);
-            let price = data["price"].as_f64().unwrap_or(0.0);
-
-            // Get company profile for additional info
-            let profile_url = format!(
-                "https://financialmodelingprep.com/api/v3/profile/{}?apikey={}",
-                ticker, self.api_key
-            );
-            let profiles: Vec<FMPCompanyProfile> = self.make_request(profile_url).await?;
-
-            if let Some(profile) = profiles.first() {
-                return Ok(HistoricalMarketCap {
-                    ticker: ticker.to_string(),
-                    name: profile.company_name.clone(),
-                    market_cap_original: market_cap,
-                    original_currency: profile.currency.clone(), // Use actual currency from profile
-                    exchange: profile.exchange.clone(),
-                    price,
-                });
+        let historical = response.first().and_then(|data| {
+            let market_cap = data["marketCap"].as_f64().filter(|m| *m > 0.0)?;
+            Some((market_cap, data["price"].as_f64().unwrap_or(0.0)))
+        });
+
+        let (market_cap, price, method) = match historical {
+            Some((market_cap, price)) => (market_cap, price, MarketCapMethod::Historical),
+            None => {
+                // Close price on or before the date times the shares outstanding then;
+                // a failed lookup moves on to the quote
+                let computed = self
+                    .compute_historical_market_cap(ticker, day)
+                    .await
+                    .unwrap_or_else(|e| {
+                        eprintln!(
+                            "⚠️  Could not compute the market cap of {} on {}: {:#}",
+                            ticker, day, e
+                        );
+                        None
+                    });
+                match computed {
+                    Some((market_cap, close)) => {
+                        (market_cap, close, MarketCapMethod::CloseTimesShares)
+                    }
+                    None => {
+                        // Last resort: today's quote, stored under the historical date
+                        let quote_url = format!(
+                            "{}/v3/quote/{}?apikey={}",
+                            self.base_url, ticker, self.api_key
+                        );
+                        let quotes: Vec<Value> = self.make_request(quote_url).await?;
+                        let quote = quotes.first().with_context(|| {
+                            format!("No market cap data found for ticker {}", ticker)
+                        })?;
+                        eprintln!(
+                            "⚠️  No historical data for {} on {}, using the current quote",
+                            ticker, day
+                        );
+                        (
+                            quote["marketCap"].as_f64().unwrap_or(0.0),
+                            quote["price"].as_f64().unwrap_or(0.0),
+                            MarketCapMethod::QuoteFallback,
+                        )
+                    }
+                }
             }
-        }
+        };
 
-        // If historical data not found, try the quote endpoint
-        let quote_url = format!(
-            "https://financialmodelingprep.com/api/v3/quote/{}?apikey={}",
-            ticker, self.api_key
+        // Get company profile for additional info
+        let profile_url = format!(
+            "{}/v3/profile/{}?apikey={}",
+            self.base_url, ticker, self.api_key
         );
+        let profiles: Vec<FMPCompanyProfile> = self.make_request(profile_url).await?;
+        let profile = profiles
+            .first()
+            .with_context(|| format!("No market cap data found for ticker {}", ticker))?;
+
+        Ok(HistoricalMarketCap {
+            ticker: ticker.to_string(),
+            name: profile.company_name.clone(),
+            market_cap_original: market_cap,
+            original_currency: profile.currency.clone(), // Use actual currency from profile
+            exchange: profile.exchange.clone(),
+            price,
+            method,
+        })
+    }
 
-        let quotes: Vec<Value> = self.make_request(quote_url).await?;
-
-        if let Some(quote) = quotes.first() {
-            let market_cap = quote["marketCap"].as_f64().unwrap_or(0.0);
-            let price = quote["price"].as_f64().unwrap_or(0.0);
+    /// Market cap from the last close on or before `date` times the shares outstanding
+    /// reported for the last quarter ending on or before it; returns (market cap, close)
+    async fn compute_historical_market_cap(
+        &self,
+        ticker: &str,
+        date: NaiveDate,
+    ) -> Result<Option<(f64, f64)>> {
+        // Look back a few days so weekends and holidays still find a close
+        let prices_url = format!(
+            "{}/v3/historical-price-full/{}?from={}&to={}&apikey={}",
+            self.base_url,
+            ticker,
+            date - chrono::Duration::days(7),
+            date,
+            self.api_key
+        );
+        let prices: Value = self.make_request(prices_url).await?;
+        let closes = dated_values(&prices["historical"], "close");
+        let Some((_, close)) = latest_on_or_before(closes, date) else {
+            return Ok(None);
+        };
 
-            // Get company profile for additional info
-            let profile_url = format!(
-                "https://financialmodelingprep.com/api/v3/profile/{}?apikey={}",
-                ticker, self.api_key
-            );
-            let profiles: Vec<FMPCompanyProfile> = self.make_request(profile_url).await?;
-
-            if let Some(profile) = profiles.first() {
-                return Ok(HistoricalMarketCap {
-                    ticker: ticker.to_string(),
-                    name: profile.company_name.clone(),
-                    market_cap_original: market_cap,
-                    original_currency: profile.currency.clone(), // Use actual currency from profile
-                    exchange: profile.exchange.clone(),
-                    price,
-                });
-            }
-        }
+        let Some(shares) = self.get_shares_outstanding_at(ticker, date).await? else {
+            return Ok(None);
+        };
 
-        anyhow::bail!("No market cap data found for ticker {}", ticker)
+        Ok(Some((close * shares, close)))
     }
 
-    pub async fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>> {
+    /// Shares outstanding reported for the last quarter ending on or before `date`
+    pub async fn get_shares_outstanding_at(
+        &self,
+        ticker: &str,
+        date: NaiveDate,
+    ) -> Result<Option<f64>> {
         let url = format!(
-            "https://financialmodelingprep.com/api/v3/quotes/forex?apikey={}",
-            self.api_key
+            "{}/v3/enterprise-values/{}?period=quarter&limit=120&apikey={}",
+            self.base_url, ticker, self.api_key
         );
+        let enterprise_values: Value = self.make_request(url).await?;
+        let shares = dated_values(&enterprise_values, "numberOfShares");
+        Ok(latest_on_or_before(shares, date).map(|(_, shares)| shares))
+    }
+
+    pub async fn get_exchange_rates(&self) -> Result<Vec<ExchangeRate>> {
+        let url = format!("{}/v3/quotes/forex?apikey={}", self.base_url, self.api_key);
 
         let response = self
             .client
@@ -397,7 +557,77 @@ pub struct ExchangeRate {
     pub timestamp: i64,
 }
 
-#[derive(Debug, Deserialize)]
+/// How a stored market cap figure was obtained
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MarketCapMethod {
+    /// Market cap from the company profile at fetch time
+    Profile,
+    /// FMP's historical market capitalization endpoint
+    Historical,
+    /// Historical close price times shares outstanding
+    CloseTimesShares,
+    /// Today's quote stored under a historical date because nothing else was found
+    QuoteFallback,
+}
+
+impl MarketCapMethod {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MarketCapMethod::Profile => "profile",
+            MarketCapMethod::Historical => "historical",
+            MarketCapMethod::CloseTimesShares => "close_x_shares",
+            MarketCapMethod::QuoteFallback => "quote_fallback",
+        }
+    }
+
+    /// The FMP endpoint(s) the figure was read from
+    pub fn endpoint(self) -> &'static str {
+        match self {
+            MarketCapMethod::Profile => "/api/v3/profile",
+            MarketCapMethod::Historical => "/api/v3/historical-market-capitalization",
+            MarketCapMethod::CloseTimesShares => {
+                "/api/v3/historical-price-full + /api/v3/enterprise-values"
+            }
+            MarketCapMethod::QuoteFallback => "/api/v3/quote",
+        }
+    }
+
+    /// Whether the figure describes the requested date rather than the fetch time
+    pub fn is_point_in_time(self) -> bool {
+        matches!(
+            self,
+            MarketCapMethod::Historical | MarketCapMethod::CloseTimesShares
+        )
+    }
+}
+
+/// `(date, value)` pairs of a numeric field in a list of `{"date": ..}` objects
+fn dated_values(entries: &Value, field: &str) -> Vec<(NaiveDate, f64)> {
+    entries
+        .as_array()
+        .map(|entries| {
+            entries
+                .iter()
+                .filter_map(|entry| {
+                    let date =
+                        NaiveDate::parse_from_str(entry["date"].as_str()?, "%Y-%m-%d").ok()?;
+                    let value = entry[field].as_f64().filter(|v| *v > 0.0)?;
+                    Some((date, value))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The most recent value dated on or before `date`
+fn latest_on_or_before(values: Vec<(NaiveDate, f64)>, date: NaiveDate) -> Option<(NaiveDate, f64)> {
+    values
+        .into_iter()
+        .filter(|(d, _)| *d <= date)
+        .max_by_key(|(d, _)| *d)
+}
+
+#[derive(Debug)]
 pub struct HistoricalMarketCap {
     #[allow(dead_code)]
     pub ticker: String,
@@ -406,11 +636,13 @@ pub struct HistoricalMarketCap {
     pub original_currency: String,
     pub exchange: String,
     pub price: f64,
+    pub method: MarketCapMethod,
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use axum::{extract::Path, http::StatusCode, routing::get, Json, Router};
     use std::collections::HashMap;
 
     #[tokio::test]
@@ -429,4 +661,127 @@ mod tests {
         assert!(result.is_err());
         assert!(result.unwrap_err().to_string().contains("ticker empty"));
     }
+
+    #[test]
+    fn test_latest_value_on_or_before_date() {
+        let prices = serde_json::json!([
+            { "date": "2025-01-02", "close": 12.0 },
+            { "date": "2024-12-31", "close": 11.0 },
+            { "date": "2024-12-30", "close": 10.0 },
+            { "date": "2024-12-27", "close": 0.0 },
+        ]);
+        let closes = dated_values(&prices, "close");
+        assert_eq!(closes.len(), 3);
+
+        let date = NaiveDate::from_ymd_opt(2024, 12, 31).unwrap();
+        assert_eq!(
+            latest_on_or_before(closes.clone(), date),
+            Some((date, 11.0))
+        );
+        let before = NaiveDate::from_ymd_opt(2024, 12, 29).unwrap();
+        assert_eq!(latest_on_or_before(closes, before), None);
+        assert!(dated_values(&Value::Null, "close").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_historical_market_cap_methods_against_stand_in() {
+        use serde_json::json;
+
+        let app = Router::new()
+            .route(
+                "/v3/historical-market-capitalization/:ticker",
+                get(|Path(ticker): Path<String>| async move {
+                    match ticker.as_str() {
+                        "HIST" => Json(json!([
+                            { "date": "2025-03-31", "marketCap": 500.0, "price": 5.0 }
+                        ])),
+                        _ => Json(json!([])),
+                    }
+                }),
+            )
+            .route(
+                "/v3/historical-price-full/:ticker",
+                get(|| async {
+                    // The stand-in ignores the date range, so a later close is returned too
+                    Json(json!({ "historical": [
+                        { "date": "2025-04-01", "close": 99.0 },
+                        { "date": "2025-03-28", "close": 10.0 },
+                    ] }))
+                }),
+            )
+            .route(
+                "/v3/enterprise-values/:ticker",
+                get(|Path(ticker): Path<String>| async move {
+                    match ticker.as_str() {
+                        "CLOSE" => Ok(Json(json!([
+                            { "date": "2025-06-30", "numberOfShares": 9999.0 },
+                            { "date": "2024-12-31", "numberOfShares": 1000.0 },
+                            { "date": "2024-09-30", "numberOfShares": 900.0 },
+                        ]))),
+                        "QUOTE" => Ok(Json(json!([]))),
+                        _ => Err(StatusCode::NOT_FOUND),
+                    }
+                }),
+            )
+            .route(
+                "/v3/quote/:ticker",
+                get(|| async { Json(json!([{ "marketCap": 777.0, "price": 7.0 }])) }),
+            )
+            .route(
+                "/v3/profile/:ticker",
+                get(|Path(ticker): Path<String>| async move {
+                    Json(json!([{
+                        "symbol": ticker,
+                        "companyName": "Test AG",
+                        "currency": "EUR",
+                        "exchangeShortName": "XETRA",
+                    }]))
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client =
+            FMPClient::new("test_key".to_string()).with_base_url(&format!("http://{}/", addr));
+        let date = NaiveDate::from_ymd_opt(2025, 3, 31).unwrap();
+        let datetime = date.and_hms_opt(23, 59, 0).unwrap().and_utc();
+
+        // The shares of the last quarter ending on or before the date
+        assert_eq!(
+            client
+                .get_shares_outstanding_at("CLOSE", date)
+                .await
+                .unwrap(),
+            Some(1000.0)
+        );
+        assert_eq!(
+            client
+                .get_shares_outstanding_at("QUOTE", date)
+                .await
+                .unwrap(),
+            None
+        );
+
+        let fetch = |ticker: &'static str| client.get_historical_market_cap(ticker, &datetime);
+
+        let historical = fetch("HIST").await.unwrap();
+        assert_eq!(historical.method, MarketCapMethod::Historical);
+        assert_eq!(historical.market_cap_original, 500.0);
+        assert_eq!(historical.original_currency, "EUR");
+
+        // No historical figure: the close on or before the date times those shares
+        let computed = fetch("CLOSE").await.unwrap();
+        assert_eq!(computed.method, MarketCapMethod::CloseTimesShares);
+        assert_eq!(computed.market_cap_original, 10_000.0);
+        assert_eq!(computed.price, 10.0);
+
+        // No shares reported, or the shares lookup failing, falls back to the quote
+        for ticker in ["QUOTE", "MISSING"] {
+            let quoted = fetch(ticker).await.unwrap();
+            assert_eq!(quoted.method, MarketCapMethod::QuoteFallback, "{}", ticker);
+            assert_eq!(quoted.market_cap_original, 777.0);
+            assert_eq!(quoted.price, 7.0);
+        }
+    }
 }
@@ -0,0 +1,410 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::currencies::get_rate_map_from_db;
+use crate::monthly_historical_marketcaps::month_end_datetime;
+use crate::output::status;
+use crate::overrides::{self, Figures};
+use crate::theme::{Magnitude, Presentation};
+use crate::visualizations::draw_watermark;
+use anyhow::Result;
+use chrono::{Datelike, NaiveDate, Utc};
+use indicatif::{ProgressBar, ProgressStyle};
+use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+
+/// Market caps of all companies at one month-end
+#[derive(Debug, Clone)]
+struct Snapshot {
+    date: NaiveDate,
+    /// (ticker, name, market cap in USD)
+    companies: Vec<(String, String, f64)>,
+}
+
+/// One bar in a rendered frame
+#[derive(Debug, Clone, PartialEq)]
+struct Bar {
+    ticker: String,
+    name: String,
+    value: f64,
+    /// Interpolated 0-based rank; bars at or beyond `top_n` are off screen
+    position: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Frame {
+    date: NaiveDate,
+    bars: Vec<Bar>,
+}
+
+/// Load the month-end snapshots stored by `fetch-monthly-historical-market-caps`
+async fn load_monthly_snapshots(
+    pool: &SqlitePool,
+    start_year: i32,
+    end_year: i32,
+) -> Result<Vec<Snapshot>> {
+    let mut snapshots = Vec::new();
+    let now = Utc::now();
+    let rate_map = get_rate_map_from_db(pool).await?;
+
+    for year in start_year..=end_year {
+        for month in 1..=12 {
+            if year == now.year() && month > now.month() {
+                break;
+            }
+
+            // Monthly snapshots are stored on the last day of the month at 23:59
+            let month_end = month_end_datetime(year, month);
+            let date = month_end.date();
+            let timestamp = month_end.and_utc().timestamp();
+
+            let rows = sqlx::query_as::<_, (String, String, f64, String, f64)>(
+                r#"
+                SELECT ticker, name, CAST(market_cap_original AS REAL), original_currency,
+                    CAST(market_cap_usd AS REAL)
+                FROM market_caps
+                WHERE timestamp = ? AND market_cap_usd > 0
+                "#,
+            )
+            .bind(timestamp)
+            .fetch_all(pool)
+            .await?;
+
+            // Drop excluded companies and pin overridden figures
+            let active_overrides = overrides::active_at(pool, timestamp).await?;
+            let companies: Vec<(String, String, f64)> = rows
+                .into_iter()
+                .filter(|(ticker, ..)| !active_overrides.get(ticker).is_some_and(|o| o.exclude))
+                .map(|(ticker, name, original, currency, usd)| {
+                    let mut figures = Figures {
+                        name,
+                        market_cap_original: original,
+                        original_currency: currency,
+                        market_cap_eur: 0.0,
+                        market_cap_usd: usd,
+                    };
+                    if let Some(o) = active_overrides.get(&ticker) {
+                        o.apply(&mut figures, &rate_map);
+                    }
+                    (ticker, figures.name, figures.market_cap_usd)
+                })
+                .collect();
+
+            if !companies.is_empty() {
+                snapshots.push(Snapshot { date, companies });
+            }
+        }
+    }
+
+    Ok(snapshots)
+}
+
+/// Rank position of every ticker in a snapshot, largest first
+fn rank_positions(snapshot: &Snapshot) -> HashMap<String, usize> {
+    let mut sorted: Vec<_> = snapshot.companies.iter().collect();
+    sorted.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap_or(std::cmp::Ordering::Equal));
+    sorted
+        .into_iter()
+        .enumerate()
+        .map(|(rank, (ticker, _, _))| (ticker.clone(), rank))
+        .collect()
+}
+
+/// Interpolate between consecutive snapshots, producing `frames_per_step` frames per transition.
+///
+/// Companies missing from a snapshot are treated as having a zero market cap, ranked just below
+/// the visible `top_n`, so they slide in and out of view.
+fn interpolate_frames(snapshots: &[Snapshot], top_n: usize, frames_per_step: usize) -> Vec<Frame> {
+    let frames_per_step = frames_per_step.max(1);
+    let ranks: Vec<_> = snapshots.iter().map(rank_positions).collect();
+    let values: Vec<HashMap<&str, (&str, f64)>> = snapshots
+        .iter()
+        .map(|s| {
+            s.companies
+                .iter()
+                .map(|(ticker, name, value)| (ticker.as_str(), (name.as_str(), *value)))
+                .collect()
+        })
+        .collect();
+
+    let bars_at = |index: usize, next: usize, t: f64| -> Vec<Bar> {
+        let mut tickers: Vec<&str> = values[index].keys().copied().collect();
+        for ticker in values[next].keys() {
+            if !values[index].contains_key(ticker) {
+                tickers.push(ticker);
+            }
+        }
+
+        let hidden = top_n as f64;
+        let mut bars: Vec<Bar> = tickers
+            .into_iter()
+            .filter_map(|ticker| {
+                let from = values[index].get(ticker);
+                let to = values[next].get(ticker);
+                let from_pos = ranks[index].get(ticker).map_or(hidden, |r| *r as f64);
+                let to_pos = ranks[next].get(ticker).map_or(hidden, |r| *r as f64);
+                let position =
+                    from_pos.min(hidden) + (to_pos.min(hidden) - from_pos.min(hidden)) * t;
+                if position >= hidden {
+                    return None;
+                }
+
+                let from_value = from.map_or(0.0, |v| v.1);
+                let to_value = to.map_or(0.0, |v| v.1);
+                let name = to.or(from).map(|v| v.0).unwrap_or(ticker);
+                Some(Bar {
+                    ticker: ticker.to_string(),
+                    name: name.to_string(),
+                    value: from_value + (to_value - from_value) * t,
+                    position,
+                })
+            })
+            .collect();
+        bars.sort_by(|a, b| {
+            a.position
+                .partial_cmp(&b.position)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        bars
+    };
+
+    let mut frames = Vec::new();
+    for index in 0..snapshots.len() {
+        if index + 1 == snapshots.len() {
+            frames.push(Frame {
+                date: snapshots[index].date,
+                bars: bars_at(index, index, 0.0),
+            });
+            break;
+        }
+
+        for step in 0..frames_per_step {
+            let t = step as f64 / frames_per_step as f64;
+            frames.push(Frame {
+                // Show the target date once the transition is halfway
+                date: if t < 0.5 {
+                    snapshots[index].date
+                } else {
+                    snapshots[index + 1].date
+                },
+                bars: bars_at(index, index + 1, t),
+            });
+        }
+    }
+
+    frames
+}
+
+/// Render frames into an animated GIF
+fn render_gif(
+    frames: &[Frame],
+    filename: &str,
+    top_n: usize,
+    frame_delay_ms: u32,
+    presentation: &Presentation,
+) -> Result<()> {
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
+    let (width, height) = (1200u32, 800u32);
+    let root = BitMapBackend::gif(filename, (width, height), frame_delay_ms)?.into_drawing_area();
+
+    // Stable colour per company across frames
+    let mut colors: HashMap<String, RGBColor> = HashMap::new();
+
+    let chart_left = 260;
+    let chart_right = width as i32 - 140;
+    let chart_top = 100;
+    let row_height = (height as i32 - chart_top - 60) / top_n.max(1) as i32;
+
+    let progress = ProgressBar::new(frames.len() as u64);
+    progress.set_style(
+        ProgressStyle::default_bar()
+            .template("[{elapsed_precise}] {bar:40.cyan/blue} {pos:>7}/{len:7} {msg}")
+            .unwrap()
+            .progress_chars("=>-"),
+    );
+
+    for frame in frames {
+        root.fill(&theme.background.0)?;
+
+        root.draw_text(
+            &format!("{} – top {}", labels.bar_race_title, top_n),
+            &TextStyle::from(theme.title_font()).color(&theme.text.0),
+            (40, 30),
+        )?;
+
+        let max_value = frame
+            .bars
+            .iter()
+            .map(|b| b.value)
+            .fold(0.0, f64::max)
+            .max(1.0);
+
+        for bar in &frame.bars {
+            let next_color = colors.len();
+            let color = *colors
+                .entry(bar.ticker.clone())
+                .or_insert_with(|| theme.series_color(next_color));
+
+            let y = chart_top + (bar.position * row_height as f64).round() as i32;
+            let bar_width = ((bar.value / max_value) * (chart_right - chart_left) as f64) as i32;
+
+            root.draw(&Rectangle::new(
+                [
+                    (chart_left, y + 4),
+                    (chart_left + bar_width, y + row_height - 4),
+                ],
+                color.filled(),
+            ))?;
+
+            let display_name = if bar.name.chars().count() > 28 {
+                format!("{}...", bar.name.chars().take(25).collect::<String>())
+            } else {
+                bar.name.clone()
+            };
+            root.draw_text(
+                &display_name,
+                &TextStyle::from(theme.font(14))
+                    .color(&theme.text.0)
+                    .pos(Pos::new(HPos::Right, VPos::Center)),
+                (chart_left - 10, y + row_height / 2),
+            )?;
+
+            root.draw_text(
+                &locale.format_money(bar.value, "USD", Magnitude::Billions, 1),
+                &TextStyle::from(theme.font(13))
+                    .color(&theme.muted.0)
+                    .pos(Pos::new(HPos::Left, VPos::Center)),
+                (chart_left + bar_width + 8, y + row_height / 2),
+            )?;
+        }
+
+        // Current date in the bottom right corner
+        root.draw_text(
+            &frame.date.format("%Y-%m").to_string(),
+            &TextStyle::from(theme.font(48))
+                .color(&theme.muted.0)
+                .pos(Pos::new(HPos::Right, VPos::Bottom)),
+            (width as i32 - 40, height as i32 - 40),
+        )?;
+
+        draw_watermark(&root, theme)?;
+        root.present()?;
+        progress.inc(1);
+    }
+    progress.finish();
+
+    Ok(())
+}
+
+/// Render an animated bar chart race of the top N companies over the monthly snapshots
+pub async fn generate_bar_chart_race(
+    pool: &SqlitePool,
+    start_year: i32,
+    end_year: i32,
+    top_n: usize,
+    duration_secs: u32,
+    frames_per_step: usize,
+    presentation: &Presentation,
+) -> Result<()> {
+    status!(
+        "Generating bar chart race for {} to {} (top {})",
+        start_year,
+        end_year,
+        top_n
+    );
+
+    let snapshots = load_monthly_snapshots(pool, start_year, end_year).await?;
+    if snapshots.len() < 2 {
+        anyhow::bail!(
+            "Found {} monthly snapshots between {} and {}. Please run 'fetch-monthly-historical-market-caps {} {}' first.",
+            snapshots.len(),
+            start_year,
+            end_year,
+            start_year,
+            end_year
+        );
+    }
+    status!("Loaded {} monthly snapshots", snapshots.len());
+
+    let frames = interpolate_frames(&snapshots, top_n, frames_per_step);
+    let frame_delay_ms = ((duration_secs as f64 * 1000.0) / frames.len() as f64).max(10.0) as u32;
+
+    std::fs::create_dir_all("output")?;
+    let filename = format!("output/bar_chart_race_{}_{}.gif", start_year, end_year);
+    render_gif(&frames, &filename, top_n, frame_delay_ms, presentation)?;
+
+    status!(
+        "✅ Generated bar chart race with {} frames: {}",
+        frames.len(),
+        filename
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(date: (i32, u32, u32), companies: &[(&str, f64)]) -> Snapshot {
+        Snapshot {
+            date: NaiveDate::from_ymd_opt(date.0, date.1, date.2).unwrap(),
+            companies: companies
+                .iter()
+                .map(|(ticker, value)| (ticker.to_string(), ticker.to_string(), *value))
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_interpolate_frames() {
+        let snapshots = vec![
+            snapshot((2024, 1, 31), &[("A", 100.0), ("B", 50.0), ("C", 10.0)]),
+            snapshot((2024, 2, 29), &[("A", 60.0), ("B", 80.0), ("C", 10.0)]),
+        ];
+
+        let frames = interpolate_frames(&snapshots, 2, 4);
+        // 4 transition frames plus the final snapshot
+        assert_eq!(frames.len(), 5);
+
+        let first = &frames[0];
+        assert_eq!(first.bars.len(), 2);
+        assert_eq!(first.bars[0].ticker, "A");
+        assert_eq!(first.bars[0].position, 0.0);
+
+        // Halfway through, A and B are crossing
+        let middle = &frames[2];
+        let a = middle.bars.iter().find(|b| b.ticker == "A").unwrap();
+        let b = middle.bars.iter().find(|b| b.ticker == "B").unwrap();
+        assert_eq!(a.position, 0.5);
+        assert_eq!(b.position, 0.5);
+        assert_eq!(a.value, 80.0);
+        assert_eq!(b.value, 65.0);
+        assert_eq!(middle.date, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap());
+
+        let last = frames.last().unwrap();
+        assert_eq!(last.bars[0].ticker, "B");
+        assert_eq!(last.bars[1].ticker, "A");
+    }
+
+    #[test]
+    fn test_interpolate_frames_entering_company() {
+        let snapshots = vec![
+            snapshot((2024, 1, 31), &[("A", 100.0)]),
+            snapshot((2024, 2, 29), &[("A", 100.0), ("N", 200.0)]),
+        ];
+
+        let frames = interpolate_frames(&snapshots, 2, 2);
+        // N is off screen in the first frame and slides in
+        assert!(frames[0].bars.iter().all(|b| b.ticker != "N"));
+        let n = frames[1].bars.iter().find(|b| b.ticker == "N").unwrap();
+        assert_eq!(n.position, 1.0);
+        assert_eq!(n.value, 100.0);
+    }
+}
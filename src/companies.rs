@@ -0,0 +1,197 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use sqlx::sqlite::{SqliteConnection, SqlitePool};
+use std::collections::HashMap;
+
+async fn company_id_for(conn: &mut SqliteConnection, ticker: &str) -> Result<Option<i64>> {
+    let record =
+        sqlx::query_as::<_, (i64,)>("SELECT company_id FROM ticker_aliases WHERE ticker = ?")
+            .bind(ticker)
+            .fetch_optional(&mut *conn)
+            .await?;
+
+    Ok(record.map(|(id,)| id))
+}
+
+async fn ensure_company_conn(
+    conn: &mut SqliteConnection,
+    ticker: &str,
+    name: Option<&str>,
+) -> Result<i64> {
+    if let Some(id) = company_id_for(conn, ticker).await? {
+        return Ok(id);
+    }
+
+    let id = sqlx::query("INSERT INTO companies (name) VALUES (?)")
+        .bind(name)
+        .execute(&mut *conn)
+        .await?
+        .last_insert_rowid();
+    sqlx::query("INSERT INTO ticker_aliases (ticker, company_id) VALUES (?, ?)")
+        .bind(ticker)
+        .bind(id)
+        .execute(&mut *conn)
+        .await?;
+
+    Ok(id)
+}
+
+/// Company id for a ticker, registering a new company if the ticker is unknown
+pub async fn ensure_company(pool: &SqlitePool, ticker: &str, name: Option<&str>) -> Result<i64> {
+    let mut conn = pool.acquire().await?;
+    ensure_company_conn(&mut conn, ticker, name).await
+}
+
+/// Current ticker of the company a (possibly retired) ticker belongs to
+pub async fn resolve_ticker(pool: &SqlitePool, ticker: &str) -> Result<String> {
+    let record = sqlx::query_as::<_, (String,)>(
+        r#"
+        SELECT current.ticker
+        FROM ticker_aliases alias
+        JOIN ticker_aliases current
+            ON current.company_id = alias.company_id AND current.valid_until IS NULL
+        WHERE alias.ticker = ?
+        ORDER BY current.valid_from DESC
+        LIMIT 1
+        "#,
+    )
+    .bind(ticker)
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(record.map(|(t,)| t).unwrap_or_else(|| ticker.to_string()))
+}
+
+/// Map of every retired ticker to the current ticker of its company
+pub async fn retired_ticker_map(pool: &SqlitePool) -> Result<HashMap<String, String>> {
+    let records = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT retired.ticker, current.ticker
+        FROM ticker_aliases retired
+        JOIN ticker_aliases current
+            ON current.company_id = retired.company_id AND current.valid_until IS NULL
+        WHERE retired.valid_until IS NOT NULL
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records.into_iter().collect())
+}
+
+/// Link the old and new symbol to one company and retire the old one.
+///
+/// Stored rows keep the ticker they were fetched under; readers map retired tickers
+/// to the current one through `ticker_aliases`. Takes a connection so callers can
+/// record the change in the same transaction.
+pub async fn apply_symbol_change(
+    conn: &mut SqliteConnection,
+    old_symbol: &str,
+    new_symbol: &str,
+    change_date: Option<&str>,
+) -> Result<()> {
+    let company_id = ensure_company_conn(conn, old_symbol, None).await?;
+
+    match company_id_for(conn, new_symbol).await? {
+        // The new symbol was already tracked as a separate company: merge it
+        Some(other_id) if other_id != company_id => {
+            sqlx::query("UPDATE ticker_aliases SET company_id = ? WHERE company_id = ?")
+                .bind(company_id)
+                .bind(other_id)
+                .execute(&mut *conn)
+                .await?;
+            sqlx::query("DELETE FROM companies WHERE id = ?")
+                .bind(other_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+        Some(_) => {}
+        None => {
+            sqlx::query("INSERT INTO ticker_aliases (ticker, company_id) VALUES (?, ?)")
+                .bind(new_symbol)
+                .bind(company_id)
+                .execute(&mut *conn)
+                .await?;
+        }
+    }
+
+    sqlx::query("UPDATE ticker_aliases SET valid_from = ?, valid_until = NULL WHERE ticker = ?")
+        .bind(change_date)
+        .bind(new_symbol)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "UPDATE ticker_aliases SET valid_until = COALESCE(?, date('now')) WHERE ticker = ?",
+    )
+    .bind(change_date)
+    .bind(old_symbol)
+    .execute(&mut *conn)
+    .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_apply_symbol_change_links_without_moving_history() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        // SPD.L has two snapshots, FRAS.L only the second one
+        for (ticker, timestamp) in [("SPD.L", 1), ("SPD.L", 2), ("FRAS.L", 2)] {
+            sqlx::query(
+                "INSERT INTO market_caps (ticker, name, market_cap_usd, timestamp) VALUES (?, 'Frasers', 1.0, ?)",
+            )
+            .bind(ticker)
+            .bind(timestamp)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        let old_id = ensure_company(&pool, "SPD.L", Some("Sports Direct"))
+            .await
+            .unwrap();
+        ensure_company(&pool, "FRAS.L", None).await.unwrap();
+
+        let mut conn = pool.acquire().await.unwrap();
+        apply_symbol_change(&mut conn, "SPD.L", "FRAS.L", Some("2019-12-01"))
+            .await
+            .unwrap();
+        drop(conn);
+
+        // Every stored row is kept under the ticker it was fetched with
+        let rows = sqlx::query_as::<_, (String, i64)>(
+            "SELECT ticker, timestamp FROM market_caps ORDER BY timestamp, ticker",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            rows,
+            vec![
+                ("SPD.L".to_string(), 1),
+                ("FRAS.L".to_string(), 2),
+                ("SPD.L".to_string(), 2)
+            ]
+        );
+
+        assert_eq!(resolve_ticker(&pool, "SPD.L").await.unwrap(), "FRAS.L");
+        assert_eq!(resolve_ticker(&pool, "FRAS.L").await.unwrap(), "FRAS.L");
+        assert_eq!(ensure_company(&pool, "FRAS.L", None).await.unwrap(), old_id);
+        assert_eq!(
+            retired_ticker_map(&pool).await.unwrap().get("SPD.L"),
+            Some(&"FRAS.L".to_string())
+        );
+    }
+}
@@ -2,47 +2,118 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use anyhow::{Context, Result};
-use chrono::Local;
-use csv::{Reader, Writer};
+use anyhow::Result;
+use chrono::{Local, NaiveDate};
+use csv::Writer;
 use indicatif::{ProgressBar, ProgressStyle};
-use serde::Deserialize;
+use rust_xlsxwriter::Workbook;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::Write as IoWrite;
 use std::path::Path;
 
-#[derive(Debug, Deserialize)]
-struct MarketCapRecord {
-    #[serde(rename = "Rank")]
-    rank: Option<usize>,
-    #[serde(rename = "Ticker")]
-    ticker: String,
-    #[serde(rename = "Name")]
-    name: String,
-    #[serde(rename = "Market Cap (Original)")]
-    market_cap_original: Option<f64>,
-    #[serde(rename = "Original Currency")]
-    original_currency: Option<String>,
-    #[serde(rename = "Market Cap (EUR)")]
-    market_cap_eur: Option<f64>,
-    #[serde(rename = "Market Cap (USD)")]
-    market_cap_usd: Option<f64>,
+use crate::companies;
+use crate::config::{self, ConsolidationConfig, SortCurrency};
+use crate::consolidation::{self, Listing};
+use crate::currencies::get_rate_map_from_db;
+use crate::export_schema::{self, MarketCapRecord};
+use crate::output::{status, ExportFormat};
+use crate::overrides::{self, Figures, ManualOverride};
+use crate::parquet_export;
+use crate::provenance;
+use crate::rankings::{self, Rankable};
+use crate::theme::{Locale, Magnitude};
+use crate::xlsx_report::{self, RankedCompany};
+
+impl MarketCapRecord {
+    /// Short provenance description such as `fmp/historical`
+    fn provenance(&self) -> Option<String> {
+        provenance::summary(self.source.as_deref(), self.method.as_deref())
+    }
+}
+
+impl Rankable for MarketCapRecord {
+    fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    fn exchange(&self) -> Option<&str> {
+        self.exchange.as_deref()
+    }
+
+    /// Files exported before the `Active` column count every company as active
+    fn active(&self) -> bool {
+        self.active.unwrap_or(true)
+    }
+
+    fn market_cap(&self, currency: SortCurrency) -> f64 {
+        match currency {
+            SortCurrency::Eur => self.market_cap_eur.unwrap_or(0.0),
+            SortCurrency::Usd => self.market_cap_usd.unwrap_or(0.0),
+        }
+    }
+}
+
+impl Listing for MarketCapRecord {
+    fn ticker(&self) -> &str {
+        &self.ticker
+    }
+
+    fn set_ticker(&mut self, ticker: &str) {
+        self.ticker = ticker.to_string();
+    }
+
+    fn figures(&self) -> Figures {
+        Figures {
+            name: self.name.clone(),
+            market_cap_original: self.market_cap_original.unwrap_or(0.0),
+            original_currency: self.original_currency.clone().unwrap_or_default(),
+            market_cap_eur: self.market_cap_eur.unwrap_or(0.0),
+            market_cap_usd: self.market_cap_usd.unwrap_or(0.0),
+        }
+    }
+
+    fn set_figures(&mut self, figures: Figures) {
+        self.name = figures.name;
+        self.market_cap_original = Some(figures.market_cap_original);
+        self.original_currency = Some(figures.original_currency);
+        self.market_cap_eur = Some(figures.market_cap_eur);
+        self.market_cap_usd = Some(figures.market_cap_usd);
+    }
+}
+
+/// Currency of the compared market caps, which are read from the USD column
+pub const COMPARISON_CURRENCY: &str = "USD";
+
+#[derive(Debug, Serialize)]
+pub struct MarketCapComparison {
+    pub ticker: String,
+    pub name: String,
+    pub market_cap_from: Option<f64>,
+    pub market_cap_to: Option<f64>,
+    pub absolute_change: Option<f64>,
+    pub percentage_change: Option<f64>,
+    pub rank_from: Option<usize>,
+    pub rank_to: Option<usize>,
+    pub rank_change: Option<i32>,
+    pub market_share_from: Option<f64>,
+    pub market_share_to: Option<f64>,
+    pub source_from: Option<String>,
+    pub source_to: Option<String>,
+    pub overridden_from: Option<String>,
+    pub overridden_to: Option<String>,
 }
 
-#[derive(Debug)]
-struct MarketCapComparison {
-    ticker: String,
-    name: String,
-    market_cap_from: Option<f64>,
-    market_cap_to: Option<f64>,
-    absolute_change: Option<f64>,
-    percentage_change: Option<f64>,
-    rank_from: Option<usize>,
-    rank_to: Option<usize>,
-    rank_change: Option<i32>,
-    market_share_from: Option<f64>,
-    market_share_to: Option<f64>,
+/// The files a comparison read and wrote, with its rows
+#[derive(Debug, Serialize)]
+pub struct ComparisonRun {
+    pub from_file: String,
+    pub to_file: String,
+    pub comparison_file: String,
+    pub summary_file: String,
+    pub comparisons: Vec<MarketCapComparison>,
 }
 
 /// Find the most recent CSV file for a given date
@@ -76,22 +147,6 @@ fn find_csv_for_date(date: &str) -> Result<String> {
     Ok(format!("output/{}", selected_file))
 }
 
-/// Read market cap data from CSV file
-fn read_market_cap_csv(file_path: &str) -> Result<Vec<MarketCapRecord>> {
-    let file =
-        File::open(file_path).with_context(|| format!("Failed to open CSV file: {}", file_path))?;
-
-    let mut reader = Reader::from_reader(file);
-    let mut records = Vec::new();
-
-    for result in reader.deserialize() {
-        let record: MarketCapRecord = result?;
-        records.push(record);
-    }
-
-    Ok(records)
-}
-
 /// Calculate market share for each company
 fn calculate_market_shares(records: &[MarketCapRecord]) -> HashMap<String, f64> {
     let total_market_cap: f64 = records.iter().filter_map(|r| r.market_cap_usd).sum();
@@ -110,17 +165,113 @@ fn calculate_market_shares(records: &[MarketCapRecord]) -> HashMap<String, f64>
     shares
 }
 
+/// Rename retired tickers to the current ticker of their company.
+///
+/// If a file lists both the old and the new ticker, the row under the new ticker wins.
+pub fn canonicalize_tickers(
+    records: Vec<MarketCapRecord>,
+    retired: &HashMap<String, String>,
+) -> Vec<MarketCapRecord> {
+    let present: std::collections::HashSet<String> =
+        records.iter().map(|r| r.ticker.clone()).collect();
+
+    records
+        .into_iter()
+        .filter_map(|mut record| {
+            if let Some(current) = retired.get(&record.ticker) {
+                if present.contains(current) {
+                    return None;
+                }
+                record.ticker = current.clone();
+            }
+            Some(record)
+        })
+        .collect()
+}
+
+/// Apply the manual overrides in effect for a snapshot: drop excluded tickers and pin
+/// overridden figures, marking the rows they changed
+pub fn apply_overrides(
+    records: Vec<MarketCapRecord>,
+    active: &HashMap<String, ManualOverride>,
+    rate_map: &HashMap<String, f64>,
+) -> Vec<MarketCapRecord> {
+    records
+        .into_iter()
+        .filter(|r| !active.get(&r.ticker).is_some_and(|o| o.exclude))
+        .map(|mut record| {
+            let Some(manual_override) = active.get(&record.ticker) else {
+                return record;
+            };
+            let mut figures = Figures {
+                name: record.name.clone(),
+                market_cap_original: record.market_cap_original.unwrap_or(0.0),
+                original_currency: record.original_currency.clone().unwrap_or_default(),
+                market_cap_eur: record.market_cap_eur.unwrap_or(0.0),
+                market_cap_usd: record.market_cap_usd.unwrap_or(0.0),
+            };
+            if let Some(applied) = manual_override.apply(&mut figures, rate_map) {
+                record.name = figures.name;
+                if manual_override.market_cap.is_some() || manual_override.currency.is_some() {
+                    record.market_cap_original = Some(figures.market_cap_original);
+                    record.original_currency = Some(figures.original_currency);
+                    record.market_cap_eur = Some(figures.market_cap_eur);
+                    record.market_cap_usd = Some(figures.market_cap_usd);
+                }
+                record.overridden =
+                    overrides::merge_markers(record.overridden.as_deref(), Some(&applied));
+            }
+            record
+        })
+        .collect()
+}
+
+/// Merge the listings of consolidated companies and re-rank the snapshot if it was ranked
+pub fn consolidate_records(
+    records: Vec<MarketCapRecord>,
+    consolidations: &[ConsolidationConfig],
+) -> Result<Vec<MarketCapRecord>> {
+    if consolidations.is_empty() {
+        return Ok(records);
+    }
+
+    let ranked = records.iter().any(|r| r.rank.is_some());
+    let mut records: Vec<MarketCapRecord> = consolidation::consolidate(records, consolidations)?
+        .into_iter()
+        .map(|(record, _)| record)
+        .collect();
+    if ranked {
+        records.sort_by(|a, b| {
+            b.market_cap_eur
+                .unwrap_or(0.0)
+                .partial_cmp(&a.market_cap_eur.unwrap_or(0.0))
+                .unwrap_or(std::cmp::Ordering::Equal)
+        });
+        for (i, record) in records.iter_mut().enumerate() {
+            record.rank = Some(i + 1);
+        }
+    }
+
+    Ok(records)
+}
+
 /// Compare market caps between two dates
-pub async fn compare_market_caps(from_date: &str, to_date: &str) -> Result<()> {
-    println!("Comparing market caps from {} to {}", from_date, to_date);
+pub async fn compare_market_caps(
+    pool: &SqlitePool,
+    from_date: &str,
+    to_date: &str,
+    locale: &Locale,
+    format: ExportFormat,
+) -> Result<ComparisonRun> {
+    status!("Comparing market caps from {} to {}", from_date, to_date);
 
     // Find CSV files for both dates
     let from_file = find_csv_for_date(from_date)?;
     let to_file = find_csv_for_date(to_date)?;
 
-    println!("Using files:");
-    println!("  From: {}", from_file);
-    println!("  To:   {}", to_file);
+    status!("Using files:");
+    status!("  From: {}", from_file);
+    status!("  To:   {}", to_file);
 
     // Read data from both files
     let progress = ProgressBar::new(4);
@@ -132,69 +283,94 @@ pub async fn compare_market_caps(from_date: &str, to_date: &str) -> Result<()> {
     );
 
     progress.set_message("Reading from date CSV...");
-    let from_records = read_market_cap_csv(&from_file)?;
+    let from_records = export_schema::read_csv(&from_file)?;
     progress.inc(1);
 
     progress.set_message("Reading to date CSV...");
-    let to_records = read_market_cap_csv(&to_file)?;
+    let to_records = export_schema::read_csv(&to_file)?;
     progress.inc(1);
 
-    // Create lookup maps
-    let mut from_map: HashMap<String, MarketCapRecord> = HashMap::new();
-    let mut to_map: HashMap<String, MarketCapRecord> = HashMap::new();
-
-    for record in from_records.iter() {
-        from_map.insert(
-            record.ticker.clone(),
-            MarketCapRecord {
-                rank: record.rank,
-                ticker: record.ticker.clone(),
-                name: record.name.clone(),
-                market_cap_original: record.market_cap_original,
-                original_currency: record.original_currency.clone(),
-                market_cap_eur: record.market_cap_eur,
-                market_cap_usd: record.market_cap_usd,
-            },
-        );
-    }
+    // Match companies that changed ticker between the two files
+    let retired = companies::retired_ticker_map(pool).await?;
+    let from_records = canonicalize_tickers(from_records, &retired);
+    let to_records = canonicalize_tickers(to_records, &retired);
+
+    // Hand-corrected values, including ones set after the files were exported
+    let rate_map = get_rate_map_from_db(pool).await?;
+    let from_records = apply_overrides(
+        from_records,
+        &overrides::active_overrides(pool, from_date).await?,
+        &rate_map,
+    );
+    let to_records = apply_overrides(
+        to_records,
+        &overrides::active_overrides(pool, to_date).await?,
+        &rate_map,
+    );
 
-    for record in to_records.iter() {
-        to_map.insert(
-            record.ticker.clone(),
-            MarketCapRecord {
-                rank: record.rank,
-                ticker: record.ticker.clone(),
-                name: record.name.clone(),
-                market_cap_original: record.market_cap_original,
-                original_currency: record.original_currency.clone(),
-                market_cap_eur: record.market_cap_eur,
-                market_cap_usd: record.market_cap_usd,
-            },
-        );
-    }
+    // Compare companies rather than their individual listings
+    let consolidations = config::load_config()?.consolidations;
+    let from_records = consolidate_records(from_records, &consolidations)?;
+    let to_records = consolidate_records(to_records, &consolidations)?;
+
+    // Calculate market shares and build comparison data
+    progress.set_message("Analyzing changes...");
+    let comparisons = build_comparisons(&from_records, &to_records);
+    progress.inc(2);
+    progress.finish_with_message("Analysis complete");
+
+    // Export main comparison data
+    let comparison_file = export_comparison(
+        &comparisons,
+        &to_records,
+        from_date,
+        to_date,
+        locale,
+        format,
+    )?;
+
+    // Export summary report
+    let summary_file = export_summary_report(&comparisons, from_date, to_date, locale)?;
+
+    Ok(ComparisonRun {
+        from_file,
+        to_file,
+        comparison_file,
+        summary_file,
+        comparisons,
+    })
+}
+
+/// Build the comparison between two snapshots, sorted by percentage change (descending)
+pub fn build_comparisons(
+    from_records: &[MarketCapRecord],
+    to_records: &[MarketCapRecord],
+) -> Vec<MarketCapComparison> {
+    // Create lookup maps
+    let from_map: HashMap<&str, &MarketCapRecord> = from_records
+        .iter()
+        .map(|r| (r.ticker.as_str(), r))
+        .collect();
+    let to_map: HashMap<&str, &MarketCapRecord> =
+        to_records.iter().map(|r| (r.ticker.as_str(), r)).collect();
 
     // Calculate market shares
-    progress.set_message("Calculating market shares...");
-    let from_shares = calculate_market_shares(&from_records);
-    let to_shares = calculate_market_shares(&to_records);
-    progress.inc(1);
+    let from_shares = calculate_market_shares(from_records);
+    let to_shares = calculate_market_shares(to_records);
 
-    // Build comparison data
-    progress.set_message("Analyzing changes...");
     let mut comparisons = Vec::new();
     let mut all_tickers = std::collections::HashSet::new();
 
     for ticker in from_map.keys() {
-        all_tickers.insert(ticker.clone());
+        all_tickers.insert(ticker.to_string());
     }
     for ticker in to_map.keys() {
-        all_tickers.insert(ticker.clone());
+        all_tickers.insert(ticker.to_string());
     }
 
     for ticker in all_tickers {
-        let from_record = from_map.get(&ticker);
-        let to_record = to_map.get(&ticker);
-
+        let from_record = from_map.get(ticker.as_str()).copied();
+        let to_record = to_map.get(ticker.as_str()).copied();
         let name = from_record
             .map(|r| r.name.clone())
             .or_else(|| to_record.map(|r| r.name.clone()))
@@ -236,6 +412,10 @@ pub async fn compare_market_caps(from_date: &str, to_date: &str) -> Result<()> {
             rank_change,
             market_share_from: from_shares.get(&ticker).copied(),
             market_share_to: to_shares.get(&ticker).copied(),
+            source_from: from_record.and_then(|r| r.provenance()),
+            source_to: to_record.and_then(|r| r.provenance()),
+            overridden_from: from_record.and_then(|r| r.overridden.clone()),
+            overridden_to: to_record.and_then(|r| r.overridden.clone()),
         });
     }
 
@@ -246,35 +426,90 @@ pub async fn compare_market_caps(from_date: &str, to_date: &str) -> Result<()> {
         b_pct.partial_cmp(&a_pct).unwrap()
     });
 
-    progress.inc(1);
-    progress.finish_with_message("Analysis complete");
-
-    // Export main comparison CSV
-    export_comparison_csv(&comparisons, from_date, to_date)?;
-
-    // Export summary report
-    export_summary_report(&comparisons, from_date, to_date)?;
-
-    Ok(())
+    comparisons
 }
 
-/// Export comparison data to CSV
-fn export_comparison_csv(
+/// Export comparison data; returns the file written.
+///
+/// An xlsx export is a full report: the configured ranked lists of the `to` snapshot,
+/// the comparison and its summary.
+fn export_comparison(
     comparisons: &[MarketCapComparison],
+    to_records: &[MarketCapRecord],
     from_date: &str,
     to_date: &str,
-) -> Result<()> {
+    locale: &Locale,
+    format: ExportFormat,
+) -> Result<String> {
     let timestamp = Local::now().format("%Y%m%d_%H%M%S");
     let filename = format!(
-        "output/comparison_{}_to_{}_{}.csv",
-        from_date, to_date, timestamp
+        "output/comparison_{}_to_{}_{}.{}",
+        from_date,
+        to_date,
+        timestamp,
+        format.extension()
     );
 
-    let file = File::create(&filename)?;
-    let mut writer = Writer::from_writer(file);
+    match format {
+        ExportFormat::Csv => write_comparison_csv(File::create(&filename)?, comparisons)?,
+        ExportFormat::Parquet => {
+            write_comparison_parquet(&filename, comparisons, from_date, to_date)?
+        }
+        ExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            for list in rankings::configured_lists(&config::load_config()?.lists) {
+                let selected = list.select(to_records);
+                xlsx_report::add_ranking_sheet(
+                    &mut workbook,
+                    &list.name,
+                    &ranked_records(&selected),
+                )?;
+            }
+            xlsx_report::add_comparison_sheet(&mut workbook, comparisons)?;
+            xlsx_report::add_summary_sheet(
+                &mut workbook,
+                &ComparisonSummary::new(comparisons),
+                from_date,
+                to_date,
+                locale,
+            )?;
+            xlsx_report::save(&mut workbook, &filename)?;
+        }
+    }
+    status!("✅ Comparison data exported to {}", filename);
+
+    Ok(filename)
+}
+
+/// Records of a snapshot as a ranked list sheet
+fn ranked_records(records: &[&MarketCapRecord]) -> Vec<RankedCompany> {
+    records
+        .iter()
+        .map(|r| {
+            let figures = r.figures();
+            RankedCompany {
+                ticker: r.ticker.clone(),
+                name: figures.name,
+                market_cap_original: figures.market_cap_original,
+                original_currency: figures.original_currency,
+                market_cap_eur: figures.market_cap_eur,
+                market_cap_usd: figures.market_cap_usd,
+                exchange: r.exchange.clone(),
+                active: r.active,
+            }
+        })
+        .collect()
+}
+
+/// Write comparison rows as CSV to any writer
+pub fn write_comparison_csv<W: std::io::Write>(
+    out: W,
+    comparisons: &[MarketCapComparison],
+) -> Result<()> {
+    let mut writer = Writer::from_writer(out);
 
     // Write headers
-    writer.write_record(&[
+    writer.write_record([
         "Ticker",
         "Name",
         "Market Cap From (USD)",
@@ -286,6 +521,10 @@ fn export_comparison_csv(
         "Rank Change",
         "Market Share From (%)",
         "Market Share To (%)",
+        "Source From",
+        "Source To",
+        "Overridden From",
+        "Overridden To",
     ])?;
 
     // Write data
@@ -326,21 +565,247 @@ fn export_comparison_csv(
             comp.market_share_to
                 .map(|v| format!("{:.4}", v))
                 .unwrap_or_else(|| "NA".to_string()),
+            comp.source_from.clone().unwrap_or_else(|| "NA".to_string()),
+            comp.source_to.clone().unwrap_or_else(|| "NA".to_string()),
+            comp.overridden_from.clone().unwrap_or_default(),
+            comp.overridden_to.clone().unwrap_or_default(),
         ])?;
     }
 
     writer.flush()?;
-    println!("✅ Comparison data exported to {}", filename);
 
     Ok(())
 }
 
-/// Export summary report in Markdown format
-fn export_summary_report(
+/// Write comparison rows as Parquet, with the compared dates as typed columns
+fn write_comparison_parquet(
+    filename: &str,
     comparisons: &[MarketCapComparison],
     from_date: &str,
     to_date: &str,
 ) -> Result<()> {
+    let parse = |date: &str| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok();
+    let (from_date, to_date) = (parse(from_date), parse(to_date));
+    let rank = |rank: Option<usize>| rank.map(|r| r as i64);
+
+    let columns = vec![
+        (
+            "from_date",
+            parquet_export::dates(comparisons.iter().map(|_| from_date)),
+        ),
+        (
+            "to_date",
+            parquet_export::dates(comparisons.iter().map(|_| to_date)),
+        ),
+        (
+            "ticker",
+            parquet_export::strings(comparisons.iter().map(|c| Some(c.ticker.as_str()))),
+        ),
+        (
+            "name",
+            parquet_export::strings(comparisons.iter().map(|c| Some(c.name.as_str()))),
+        ),
+        (
+            "market_cap_from_usd",
+            parquet_export::decimals(
+                comparisons.iter().map(|c| c.market_cap_from),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "market_cap_to_usd",
+            parquet_export::decimals(
+                comparisons.iter().map(|c| c.market_cap_to),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "absolute_change_usd",
+            parquet_export::decimals(
+                comparisons.iter().map(|c| c.absolute_change),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "percentage_change",
+            parquet_export::floats(comparisons.iter().map(|c| c.percentage_change)),
+        ),
+        (
+            "rank_from",
+            parquet_export::integers(comparisons.iter().map(|c| rank(c.rank_from))),
+        ),
+        (
+            "rank_to",
+            parquet_export::integers(comparisons.iter().map(|c| rank(c.rank_to))),
+        ),
+        (
+            "rank_change",
+            parquet_export::integers(comparisons.iter().map(|c| c.rank_change.map(i64::from))),
+        ),
+        (
+            "market_share_from",
+            parquet_export::floats(comparisons.iter().map(|c| c.market_share_from)),
+        ),
+        (
+            "market_share_to",
+            parquet_export::floats(comparisons.iter().map(|c| c.market_share_to)),
+        ),
+        (
+            "source_from",
+            parquet_export::strings(comparisons.iter().map(|c| c.source_from.as_deref())),
+        ),
+        (
+            "source_to",
+            parquet_export::strings(comparisons.iter().map(|c| c.source_to.as_deref())),
+        ),
+        (
+            "overridden_from",
+            parquet_export::strings(comparisons.iter().map(|c| c.overridden_from.as_deref())),
+        ),
+        (
+            "overridden_to",
+            parquet_export::strings(comparisons.iter().map(|c| c.overridden_to.as_deref())),
+        ),
+    ];
+    parquet_export::write_parquet(filename, columns)
+}
+
+/// Overview figures and top-10 lists of a comparison, shared by the Markdown and
+/// Excel summaries
+pub struct ComparisonSummary<'a> {
+    pub total_from: f64,
+    pub total_to: f64,
+    pub total_change: f64,
+    pub total_pct_change: f64,
+    pub top_gainers: Vec<&'a MarketCapComparison>,
+    pub top_losers: Vec<&'a MarketCapComparison>,
+    pub top_absolute_gains: Vec<&'a MarketCapComparison>,
+    /// Only companies that lost value
+    pub top_absolute_losses: Vec<&'a MarketCapComparison>,
+    /// Only companies that moved up
+    pub rank_improvements: Vec<&'a MarketCapComparison>,
+    /// Only companies that moved down
+    pub rank_declines: Vec<&'a MarketCapComparison>,
+    pub companies_increased: usize,
+    pub companies_decreased: usize,
+    pub new_companies: usize,
+    pub companies_removed: usize,
+}
+
+impl<'a> ComparisonSummary<'a> {
+    pub fn new(comparisons: &'a [MarketCapComparison]) -> Self {
+        // Calculate overview statistics
+        let total_from: f64 = comparisons.iter().filter_map(|c| c.market_cap_from).sum();
+        let total_to: f64 = comparisons.iter().filter_map(|c| c.market_cap_to).sum();
+        let total_change = total_to - total_from;
+        let total_pct_change = if total_from > 0.0 {
+            (total_change / total_from) * 100.0
+        } else {
+            0.0
+        };
+
+        // Filter out comparisons with valid percentage changes
+        let mut valid_comparisons: Vec<_> = comparisons
+            .iter()
+            .filter(|c| c.percentage_change.is_some())
+            .collect();
+        let top = |list: &[&'a MarketCapComparison]| list.iter().take(10).copied().collect();
+
+        valid_comparisons.sort_by(|a, b| {
+            b.percentage_change
+                .unwrap()
+                .partial_cmp(&a.percentage_change.unwrap())
+                .unwrap()
+        });
+        let top_gainers = top(&valid_comparisons);
+
+        valid_comparisons.sort_by(|a, b| {
+            a.percentage_change
+                .unwrap()
+                .partial_cmp(&b.percentage_change.unwrap())
+                .unwrap()
+        });
+        let top_losers = top(&valid_comparisons);
+
+        valid_comparisons.sort_by(|a, b| {
+            b.absolute_change
+                .unwrap_or(0.0)
+                .partial_cmp(&a.absolute_change.unwrap_or(0.0))
+                .unwrap()
+        });
+        let top_absolute_gains = top(&valid_comparisons);
+
+        valid_comparisons.sort_by(|a, b| {
+            a.absolute_change
+                .unwrap_or(0.0)
+                .partial_cmp(&b.absolute_change.unwrap_or(0.0))
+                .unwrap()
+        });
+        let top_absolute_losses = valid_comparisons
+            .iter()
+            .take(10)
+            .filter(|c| c.absolute_change.unwrap_or(0.0) < 0.0)
+            .copied()
+            .collect();
+
+        let mut rank_comparisons: Vec<_> = comparisons
+            .iter()
+            .filter(|c| c.rank_change.is_some())
+            .collect();
+        rank_comparisons.sort_by_key(|c| std::cmp::Reverse(c.rank_change.unwrap()));
+        let rank_improvements = rank_comparisons
+            .iter()
+            .take(10)
+            .filter(|c| c.rank_change.unwrap() > 0)
+            .copied()
+            .collect();
+        rank_comparisons.sort_by_key(|a| a.rank_change.unwrap());
+        let rank_declines = rank_comparisons
+            .iter()
+            .take(10)
+            .filter(|c| c.rank_change.unwrap() < 0)
+            .copied()
+            .collect();
+
+        Self {
+            total_from,
+            total_to,
+            total_change,
+            total_pct_change,
+            top_gainers,
+            top_losers,
+            top_absolute_gains,
+            top_absolute_losses,
+            rank_improvements,
+            rank_declines,
+            companies_increased: comparisons
+                .iter()
+                .filter(|c| c.percentage_change.map(|v| v > 0.0).unwrap_or(false))
+                .count(),
+            companies_decreased: comparisons
+                .iter()
+                .filter(|c| c.percentage_change.map(|v| v < 0.0).unwrap_or(false))
+                .count(),
+            new_companies: comparisons
+                .iter()
+                .filter(|c| c.market_cap_from.is_none() && c.market_cap_to.is_some())
+                .count(),
+            companies_removed: comparisons
+                .iter()
+                .filter(|c| c.market_cap_from.is_some() && c.market_cap_to.is_none())
+                .count(),
+        }
+    }
+}
+
+/// Export summary report in Markdown format; returns the file written
+fn export_summary_report(
+    comparisons: &[MarketCapComparison],
+    from_date: &str,
+    to_date: &str,
+    locale: &Locale,
+) -> Result<String> {
+    let labels = locale.labels();
     let timestamp = Local::now().format("%Y%m%d_%H%M%S");
     let filename = format!(
         "output/comparison_{}_to_{}_summary_{}.md",
@@ -348,234 +813,205 @@ fn export_summary_report(
     );
 
     let mut file = File::create(&filename)?;
+    let summary = ComparisonSummary::new(comparisons);
 
     writeln!(
         file,
-        "# Market Cap Comparison: {} to {}",
-        from_date, to_date
+        "# {}: {} {} {}",
+        labels.comparison_title, from_date, labels.to, to_date
     )?;
     writeln!(file)?;
 
-    // Calculate overview statistics
-    let total_from: f64 = comparisons.iter().filter_map(|c| c.market_cap_from).sum();
-    let total_to: f64 = comparisons.iter().filter_map(|c| c.market_cap_to).sum();
-    let total_change = total_to - total_from;
-    let total_pct_change = if total_from > 0.0 {
-        (total_change / total_from) * 100.0
-    } else {
-        0.0
-    };
-
-    writeln!(file, "## Overview Statistics")?;
+    writeln!(file, "## {}", labels.overview_statistics)?;
     writeln!(
         file,
-        "- Total Market Cap on {}: ${:.2}B",
+        "- {} {}: {}",
+        labels.total_market_cap_on,
         from_date,
-        total_from / 1_000_000_000.0
+        locale.format_money(
+            summary.total_from,
+            COMPARISON_CURRENCY,
+            Magnitude::Billions,
+            2
+        )
     )?;
     writeln!(
         file,
-        "- Total Market Cap on {}: ${:.2}B",
+        "- {} {}: {}",
+        labels.total_market_cap_on,
         to_date,
-        total_to / 1_000_000_000.0
+        locale.format_money(
+            summary.total_to,
+            COMPARISON_CURRENCY,
+            Magnitude::Billions,
+            2
+        )
     )?;
     writeln!(
         file,
-        "- Total Change: ${:.2}B ({:.2}%)",
-        total_change / 1_000_000_000.0,
-        total_pct_change
+        "- {}: {} ({})",
+        labels.total_change,
+        locale.format_money(
+            summary.total_change,
+            COMPARISON_CURRENCY,
+            Magnitude::Billions,
+            2
+        ),
+        locale.format_percent(summary.total_pct_change, 2)
     )?;
     writeln!(file)?;
 
-    // Filter out comparisons with valid percentage changes
-    let mut valid_comparisons: Vec<_> = comparisons
-        .iter()
-        .filter(|c| c.percentage_change.is_some())
-        .collect();
-
     // Top 10 gainers
-    writeln!(file, "## Top 10 Gainers (by percentage)")?;
-    valid_comparisons.sort_by(|a, b| {
-        b.percentage_change
-            .unwrap()
-            .partial_cmp(&a.percentage_change.unwrap())
-            .unwrap()
-    });
-
-    for (i, comp) in valid_comparisons.iter().take(10).enumerate() {
+    writeln!(file, "## {}", labels.top_gainers_pct)?;
+    for (i, comp) in summary.top_gainers.iter().enumerate() {
         writeln!(
             file,
-            "{}. **{}** ({}): +{:.2}% (${:.2}M increase)",
+            "{}. **{}** ({}): +{} ({} {})",
             i + 1,
             comp.name,
             comp.ticker,
-            comp.percentage_change.unwrap(),
-            comp.absolute_change.unwrap_or(0.0) / 1_000_000.0
+            locale.format_percent(comp.percentage_change.unwrap(), 2),
+            locale.format_money(
+                comp.absolute_change.unwrap_or(0.0),
+                COMPARISON_CURRENCY,
+                Magnitude::Millions,
+                2
+            ),
+            labels.increase
         )?;
     }
     writeln!(file)?;
 
     // Top 10 losers
-    writeln!(file, "## Top 10 Losers (by percentage)")?;
-    valid_comparisons.sort_by(|a, b| {
-        a.percentage_change
-            .unwrap()
-            .partial_cmp(&b.percentage_change.unwrap())
-            .unwrap()
-    });
-
-    for (i, comp) in valid_comparisons.iter().take(10).enumerate() {
+    writeln!(file, "## {}", labels.top_losers_pct)?;
+    for (i, comp) in summary.top_losers.iter().enumerate() {
         writeln!(
             file,
-            "{}. **{}** ({}): {:.2}% (${:.2}M decrease)",
+            "{}. **{}** ({}): {} ({} {})",
             i + 1,
             comp.name,
             comp.ticker,
-            comp.percentage_change.unwrap(),
-            comp.absolute_change.unwrap_or(0.0) / 1_000_000.0
+            locale.format_percent(comp.percentage_change.unwrap(), 2),
+            locale.format_money(
+                comp.absolute_change.unwrap_or(0.0),
+                COMPARISON_CURRENCY,
+                Magnitude::Millions,
+                2
+            ),
+            labels.decrease
         )?;
     }
     writeln!(file)?;
 
     // Top 10 by absolute gain
-    writeln!(file, "## Top 10 by Absolute Gain")?;
-    valid_comparisons.sort_by(|a, b| {
-        b.absolute_change
-            .unwrap_or(0.0)
-            .partial_cmp(&a.absolute_change.unwrap_or(0.0))
-            .unwrap()
-    });
-
-    for (i, comp) in valid_comparisons.iter().take(10).enumerate() {
+    writeln!(file, "## {}", labels.top_absolute_gain)?;
+    for (i, comp) in summary.top_absolute_gains.iter().enumerate() {
         writeln!(
             file,
-            "{}. **{}** ({}): ${:.2}B gain ({:.2}%)",
+            "{}. **{}** ({}): {} {} ({})",
             i + 1,
             comp.name,
             comp.ticker,
-            comp.absolute_change.unwrap_or(0.0) / 1_000_000_000.0,
-            comp.percentage_change.unwrap_or(0.0)
+            locale.format_money(
+                comp.absolute_change.unwrap_or(0.0),
+                COMPARISON_CURRENCY,
+                Magnitude::Billions,
+                2
+            ),
+            labels.gain,
+            locale.format_percent(comp.percentage_change.unwrap_or(0.0), 2)
         )?;
     }
     writeln!(file)?;
 
     // Top 10 by absolute loss
-    writeln!(file, "## Top 10 by Absolute Loss")?;
-    valid_comparisons.sort_by(|a, b| {
-        a.absolute_change
-            .unwrap_or(0.0)
-            .partial_cmp(&b.absolute_change.unwrap_or(0.0))
-            .unwrap()
-    });
-
-    for (i, comp) in valid_comparisons.iter().take(10).enumerate() {
-        if comp.absolute_change.unwrap_or(0.0) < 0.0 {
-            writeln!(
-                file,
-                "{}. **{}** ({}): ${:.2}B loss ({:.2}%)",
-                i + 1,
-                comp.name,
-                comp.ticker,
-                comp.absolute_change.unwrap_or(0.0).abs() / 1_000_000_000.0,
-                comp.percentage_change.unwrap_or(0.0)
-            )?;
-        }
+    writeln!(file, "## {}", labels.top_absolute_loss)?;
+    for (i, comp) in summary.top_absolute_losses.iter().enumerate() {
+        writeln!(
+            file,
+            "{}. **{}** ({}): {} {} ({})",
+            i + 1,
+            comp.name,
+            comp.ticker,
+            locale.format_money(
+                comp.absolute_change.unwrap_or(0.0).abs(),
+                COMPARISON_CURRENCY,
+                Magnitude::Billions,
+                2
+            ),
+            labels.loss,
+            locale.format_percent(comp.percentage_change.unwrap_or(0.0), 2)
+        )?;
     }
     writeln!(file)?;
 
     // Biggest rank improvements
-    writeln!(file, "## Biggest Rank Improvements")?;
-    let mut rank_comparisons: Vec<_> = comparisons
-        .iter()
-        .filter(|c| c.rank_change.is_some())
-        .collect();
-    rank_comparisons.sort_by(|a, b| b.rank_change.unwrap().cmp(&a.rank_change.unwrap()));
-
-    for (i, comp) in rank_comparisons.iter().take(10).enumerate() {
-        if comp.rank_change.unwrap() > 0 {
-            writeln!(
-                file,
-                "{}. **{}** ({}): +{} positions (#{} → #{})",
-                i + 1,
-                comp.name,
-                comp.ticker,
-                comp.rank_change.unwrap(),
-                comp.rank_from.unwrap_or(0),
-                comp.rank_to.unwrap_or(0)
-            )?;
-        }
+    writeln!(file, "## {}", labels.biggest_rank_improvements)?;
+    for (i, comp) in summary.rank_improvements.iter().enumerate() {
+        writeln!(
+            file,
+            "{}. **{}** ({}): +{} {} (#{} → #{})",
+            i + 1,
+            comp.name,
+            comp.ticker,
+            comp.rank_change.unwrap(),
+            labels.positions,
+            comp.rank_from.unwrap_or(0),
+            comp.rank_to.unwrap_or(0)
+        )?;
     }
     writeln!(file)?;
 
     // Biggest rank declines
-    writeln!(file, "## Biggest Rank Declines")?;
-    rank_comparisons.sort_by(|a, b| a.rank_change.unwrap().cmp(&b.rank_change.unwrap()));
-
-    for (i, comp) in rank_comparisons.iter().take(10).enumerate() {
-        if comp.rank_change.unwrap() < 0 {
-            writeln!(
-                file,
-                "{}. **{}** ({}): {} positions (#{} → #{})",
-                i + 1,
-                comp.name,
-                comp.ticker,
-                comp.rank_change.unwrap(),
-                comp.rank_from.unwrap_or(0),
-                comp.rank_to.unwrap_or(0)
-            )?;
-        }
+    writeln!(file, "## {}", labels.biggest_rank_declines)?;
+    for (i, comp) in summary.rank_declines.iter().enumerate() {
+        writeln!(
+            file,
+            "{}. **{}** ({}): {} {} (#{} → #{})",
+            i + 1,
+            comp.name,
+            comp.ticker,
+            comp.rank_change.unwrap(),
+            labels.positions,
+            comp.rank_from.unwrap_or(0),
+            comp.rank_to.unwrap_or(0)
+        )?;
     }
     writeln!(file)?;
 
     // Market concentration analysis
-    writeln!(file, "## Market Concentration Analysis")?;
-
-    let companies_with_increase = comparisons
-        .iter()
-        .filter(|c| c.percentage_change.map(|v| v > 0.0).unwrap_or(false))
-        .count();
-
-    let companies_with_decrease = comparisons
-        .iter()
-        .filter(|c| c.percentage_change.map(|v| v < 0.0).unwrap_or(false))
-        .count();
-
-    let new_companies = comparisons
-        .iter()
-        .filter(|c| c.market_cap_from.is_none() && c.market_cap_to.is_some())
-        .count();
-
-    let delisted_companies = comparisons
-        .iter()
-        .filter(|c| c.market_cap_from.is_some() && c.market_cap_to.is_none())
-        .count();
-
+    writeln!(file, "## {}", labels.concentration_analysis)?;
     writeln!(
         file,
-        "- Companies with increased market cap: {}",
-        companies_with_increase
+        "- {}: {}",
+        labels.companies_increased, summary.companies_increased
     )?;
     writeln!(
         file,
-        "- Companies with decreased market cap: {}",
-        companies_with_decrease
+        "- {}: {}",
+        labels.companies_decreased, summary.companies_decreased
     )?;
-    writeln!(file, "- New companies in list: {}", new_companies)?;
     writeln!(
         file,
-        "- Companies no longer in list: {}",
-        delisted_companies
+        "- {}: {}",
+        labels.new_companies, summary.new_companies
+    )?;
+    writeln!(
+        file,
+        "- {}: {}",
+        labels.companies_removed, summary.companies_removed
     )?;
 
     writeln!(file)?;
     writeln!(file, "---")?;
     writeln!(
         file,
-        "*Generated on {}*",
+        "*{} {}*",
+        labels.generated_on,
         Local::now().format("%Y-%m-%d %H:%M:%S")
     )?;
 
-    println!("✅ Summary report exported to {}", filename);
+    status!("✅ Summary report exported to {}", filename);
 
-    Ok(())
+    Ok(filename)
 }
@@ -2,7 +2,11 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only
 
+use crate::rankings;
+use anyhow::Context;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
 use std::fs;
 use std::path::PathBuf;
 
@@ -10,6 +14,245 @@ use std::path::PathBuf;
 pub struct Config {
     pub non_us_tickers: Vec<String>,
     pub us_tickers: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub email: Option<EmailConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub notifications: Option<NotificationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub validation: Option<ValidationConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub consolidations: Vec<ConsolidationConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub calendar: Option<CalendarConfig>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<ScheduleConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub lists: Vec<RankedListConfig>,
+}
+
+/// Sender and recipients for emailed reports, the `[email]` table in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    pub sender_email: String,
+    #[serde(default = "default_sender_name")]
+    pub sender_name: String,
+    pub recipients: Vec<String>,
+}
+
+fn default_sender_name() -> String {
+    "Top 200 Market Cap Tracker".to_string()
+}
+
+/// Webhooks notified after each market cap run, the `[notifications]` table in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    /// Write payloads to `dry_run_dir` instead of sending them
+    #[serde(default)]
+    pub dry_run: bool,
+    #[serde(default = "default_dry_run_dir")]
+    pub dry_run_dir: String,
+    #[serde(default = "default_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled after each attempt
+    #[serde(default = "default_retry_delay_secs")]
+    pub retry_delay_secs: u64,
+    /// Number of biggest movers included in the payload
+    #[serde(default = "default_top_movers")]
+    pub top_movers: usize,
+}
+
+/// Data quality checks run after each fetch, the `[validation]` table in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ValidationConfig {
+    /// Flag market caps that moved by at least this factor since the previous snapshot
+    #[serde(default = "default_max_change_factor")]
+    pub max_change_factor: f64,
+    /// Refuse to export when a check reports an error
+    #[serde(default)]
+    pub block_export: bool,
+}
+
+impl Default for ValidationConfig {
+    fn default() -> Self {
+        Self {
+            max_change_factor: default_max_change_factor(),
+            block_export: false,
+        }
+    }
+}
+
+fn default_max_change_factor() -> f64 {
+    10.0
+}
+
+/// Listings of one company ranked as a single row, a `[[consolidations]]` entry in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConsolidationConfig {
+    /// Ticker the company is ranked under
+    pub primary: String,
+    /// Name shown for the company; defaults to the name of the primary listing
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Other share classes, whose market caps are added to the primary's
+    #[serde(default)]
+    pub share_classes: Vec<String>,
+    /// Other listings of the same shares, such as ADRs, left out to avoid double counting
+    #[serde(default)]
+    pub duplicate_listings: Vec<String>,
+}
+
+/// Currency a ranked list is sorted and filtered by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum SortCurrency {
+    #[default]
+    Eur,
+    Usd,
+}
+
+/// A ranked list exported after each run, a `[[lists]]` entry in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RankedListConfig {
+    /// Shown as the sheet name and, in snake_case, in the file name
+    pub name: String,
+    /// Number of companies in the list
+    pub size: usize,
+    #[serde(default)]
+    pub currency: SortCurrency,
+    /// Keep companies listed on these exchanges (e.g. "NASDAQ" or "EURONEXT")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exchanges: Vec<String>,
+    /// Keep companies listed in these countries, as ISO codes (e.g. "FR")
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub countries: Vec<String>,
+    /// Keep only active (`true`) or inactive (`false`) companies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub active: Option<bool>,
+    /// Smallest market cap in `currency`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_market_cap: Option<f64>,
+    /// Tickers ranked regardless of the filters
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include: Vec<String>,
+    /// Tickers left out of the list
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude: Vec<String>,
+}
+
+/// Exchange holidays, the `[calendar]` table in config.toml
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalendarConfig {
+    /// YYYY-MM-DD dates on which an exchange is closed, keyed by exchange (e.g. "NYSE")
+    #[serde(default)]
+    pub holidays: BTreeMap<String, Vec<String>>,
+}
+
+/// Work the `schedule` daemon can run
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum ScheduledTask {
+    /// Fetch today's market caps and export them
+    DailySnapshot,
+    /// Fetch symbol changes and report those affecting config.toml
+    SymbolChanges,
+    /// Fetch market caps for the last day of the previous month
+    MonthEndBackfill,
+}
+
+impl ScheduledTask {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ScheduledTask::DailySnapshot => "daily-snapshot",
+            ScheduledTask::SymbolChanges => "symbol-changes",
+            ScheduledTask::MonthEndBackfill => "month-end-backfill",
+        }
+    }
+}
+
+impl fmt::Display for ScheduledTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Jobs run by the `schedule` daemon, the `[schedule]` table in config.toml
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduleConfig {
+    /// File locked while a job runs, so runs never overlap
+    #[serde(default = "default_lock_file")]
+    pub lock_file: String,
+    #[serde(default = "default_jobs")]
+    pub jobs: Vec<JobConfig>,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            lock_file: default_lock_file(),
+            jobs: default_jobs(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobConfig {
+    pub task: ScheduledTask,
+    /// Cron expression in UTC with a seconds field, e.g. "0 30 22 * * Mon-Fri"
+    pub cron: String,
+    /// Skip the run on days all of these exchanges are closed
+    #[serde(default)]
+    pub exchanges: Vec<String>,
+}
+
+fn default_lock_file() -> String {
+    "output/top200.lock".to_string()
+}
+
+fn default_jobs() -> Vec<JobConfig> {
+    vec![
+        JobConfig {
+            task: ScheduledTask::DailySnapshot,
+            cron: "0 30 22 * * Mon-Fri".to_string(),
+            exchanges: vec!["NYSE".to_string(), "EURONEXT".to_string()],
+        },
+        JobConfig {
+            task: ScheduledTask::SymbolChanges,
+            cron: "0 0 6 * * Mon".to_string(),
+            exchanges: Vec::new(),
+        },
+        JobConfig {
+            task: ScheduledTask::MonthEndBackfill,
+            cron: "0 0 4 1 * *".to_string(),
+            exchanges: Vec::new(),
+        },
+    ]
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    pub name: String,
+    pub url: String,
+    /// Extra request headers, e.g. for authentication
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+}
+
+fn default_dry_run_dir() -> String {
+    "output/notifications".to_string()
+}
+
+fn default_max_retries() -> u32 {
+    3
+}
+
+fn default_retry_delay_secs() -> u64 {
+    2
+}
+
+fn default_top_movers() -> usize {
+    5
 }
 
 impl Default for Config {
@@ -27,11 +270,18 @@ impl Default for Config {
                 "ITX.MC".to_string(),
             ],
             us_tickers: vec!["NKE".to_string(), "TJX".to_string(), "VFC".to_string()],
+            email: None,
+            notifications: None,
+            validation: None,
+            consolidations: Vec::new(),
+            calendar: None,
+            schedule: None,
+            lists: Vec::new(),
         }
     }
 }
 
-fn get_config_path() -> PathBuf {
+pub fn get_config_path() -> PathBuf {
     let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     path.push("config.toml");
     path
@@ -41,8 +291,11 @@ pub fn load_config() -> anyhow::Result<Config> {
     let config_path = get_config_path();
     match fs::read_to_string(&config_path) {
         Ok(config_str) => {
-            match toml::from_str(&config_str) {
-                Ok(config) => Ok(config),
+            match toml::from_str::<Config>(&config_str) {
+                Ok(config) => {
+                    rankings::check_list_names(&config.lists)?;
+                    Ok(config)
+                }
                 Err(e) => {
                     eprintln!("Failed to parse config.toml: {}", e); // Log error
                     Err(e.into())
@@ -59,6 +312,16 @@ pub fn load_config() -> anyhow::Result<Config> {
     }
 }
 
+/// Load the configuration from an explicit path
+pub fn load_config_from(path: &str) -> anyhow::Result<Config> {
+    let config_str =
+        fs::read_to_string(path).with_context(|| format!("Failed to read {}", path))?;
+    let config: Config =
+        toml::from_str(&config_str).with_context(|| format!("Failed to parse {}", path))?;
+    rankings::check_list_names(&config.lists).with_context(|| format!("Invalid {}", path))?;
+    Ok(config)
+}
+
 #[allow(dead_code)]
 pub fn save_config(config: &Config) -> anyhow::Result<()> {
     let config_path = get_config_path();
@@ -0,0 +1,354 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use toml_edit::{Array, DocumentMut, Value};
+
+/// The ticker lists in config.toml
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum TickerList {
+    Us,
+    NonUs,
+}
+
+impl TickerList {
+    const ALL: [TickerList; 2] = [TickerList::NonUs, TickerList::Us];
+
+    fn key(self) -> &'static str {
+        match self {
+            TickerList::Us => "us_tickers",
+            TickerList::NonUs => "non_us_tickers",
+        }
+    }
+}
+
+impl fmt::Display for TickerList {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.key())
+    }
+}
+
+/// Edits the ticker lists of config.toml while keeping comments, ordering and layout.
+///
+/// Ticker arrays are written one value per line with an optional `# comment` after the
+/// comma. In the document model that comment belongs to the prefix of the *next* value
+/// (or to the array's trailing whitespace for the last value), which the helpers below
+/// take care of so comments stay on the line of the ticker they describe.
+pub struct ConfigEditor {
+    path: PathBuf,
+    doc: DocumentMut,
+}
+
+/// Check that a ticker looks like an exchange symbol, e.g. `AAPL`, `HM-B.ST` or `9983.T`
+pub fn validate_ticker(ticker: &str) -> Result<()> {
+    if ticker.is_empty() {
+        anyhow::bail!("Ticker must not be empty");
+    }
+    if let Some(c) = ticker
+        .chars()
+        .find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_' | '^' | '=')))
+    {
+        anyhow::bail!("Invalid character {:?} in ticker '{}'", c, ticker);
+    }
+    Ok(())
+}
+
+/// Split decor into the comment on the previous value's line and the remainder
+fn split_line_comment(raw: &str) -> (&str, &str) {
+    match raw.find('\n') {
+        Some(pos) => raw.split_at(pos),
+        None => (raw, ""),
+    }
+}
+
+fn prefix_of(value: &Value) -> String {
+    value
+        .decor()
+        .prefix()
+        .and_then(|p| p.as_str())
+        .unwrap_or("")
+        .to_string()
+}
+
+/// Comment on the line of the value at `index`, without the leading `#`
+fn line_comment(array: &Array, index: usize) -> Option<String> {
+    let raw = match array.get(index + 1) {
+        Some(next) => prefix_of(next),
+        None => array.trailing().as_str().unwrap_or("").to_string(),
+    };
+    let (comment, _) = split_line_comment(&raw);
+    let comment = comment.trim().strip_prefix('#')?.trim();
+    (!comment.is_empty()).then(|| comment.to_string())
+}
+
+/// Replace the comment on the line of the value at `index`
+fn set_line_comment(array: &mut Array, index: usize, comment: Option<&str>) {
+    let new_comment = comment.map(|c| format!(" # {}", c)).unwrap_or_default();
+    if index + 1 < array.len() {
+        let next = array.get_mut(index + 1).unwrap();
+        let raw = prefix_of(next);
+        let (_, rest) = split_line_comment(&raw);
+        let rest = if rest.is_empty() { "\n    " } else { rest };
+        next.decor_mut()
+            .set_prefix(format!("{}{}", new_comment, rest));
+    } else {
+        let raw = array.trailing().as_str().unwrap_or("").to_string();
+        let (_, rest) = split_line_comment(&raw);
+        let rest = if rest.is_empty() { "\n" } else { rest };
+        array.set_trailing(format!("{}{}", new_comment, rest));
+        array.set_trailing_comma(true);
+    }
+}
+
+impl ConfigEditor {
+    /// Load config.toml for editing
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut editor = Self::parse(&content)?;
+        editor.path = path;
+        Ok(editor)
+    }
+
+    /// Parse config.toml content
+    pub fn parse(content: &str) -> Result<Self> {
+        let doc: DocumentMut = content.parse().context("Failed to parse config.toml")?;
+        for list in TickerList::ALL {
+            if doc
+                .get(list.key())
+                .is_some_and(|item| item.as_array().is_none())
+            {
+                anyhow::bail!("{} must be an array of tickers", list);
+            }
+        }
+        Ok(Self {
+            path: PathBuf::new(),
+            doc,
+        })
+    }
+
+    fn array(&self, list: TickerList) -> Option<&Array> {
+        self.doc.get(list.key()).and_then(|item| item.as_array())
+    }
+
+    fn array_mut(&mut self, list: TickerList) -> &mut Array {
+        if self.doc.get(list.key()).is_none() {
+            self.doc[list.key()] = toml_edit::value(Array::new());
+        }
+        self.doc[list.key()].as_array_mut().unwrap()
+    }
+
+    fn position(&self, ticker: &str) -> Option<(TickerList, usize)> {
+        TickerList::ALL.into_iter().find_map(|list| {
+            self.array(list)?
+                .iter()
+                .position(|v| v.as_str() == Some(ticker))
+                .map(|index| (list, index))
+        })
+    }
+
+    /// The list a ticker is configured in, if any
+    pub fn find(&self, ticker: &str) -> Option<TickerList> {
+        self.position(ticker).map(|(list, _)| list)
+    }
+
+    /// The comment on a configured ticker's line, e.g. the company name
+    pub fn comment_for(&self, ticker: &str) -> Option<String> {
+        let (list, index) = self.position(ticker)?;
+        line_comment(self.array(list)?, index)
+    }
+
+    /// Append a ticker to a list, with an optional comment such as the company name
+    pub fn add_ticker(
+        &mut self,
+        list: TickerList,
+        ticker: &str,
+        comment: Option<&str>,
+    ) -> Result<()> {
+        validate_ticker(ticker)?;
+        if let Some(existing) = self.find(ticker) {
+            anyhow::bail!("{} is already configured in {}", ticker, existing);
+        }
+
+        let array = self.array_mut(list);
+        let mut value = Value::from(ticker);
+        match array.len() {
+            0 => value.decor_mut().set_prefix("\n    "),
+            len => {
+                // The comment of the current last line moves from the trailing decor
+                // into the prefix of the new value
+                let trailing = array.trailing().as_str().unwrap_or("").to_string();
+                let (last_comment, _) = split_line_comment(&trailing);
+                let indent = prefix_of(array.get(len - 1).unwrap());
+                let indent = indent.rsplit('\n').next().unwrap_or("    ").to_string();
+                value
+                    .decor_mut()
+                    .set_prefix(format!("{}\n{}", last_comment, indent));
+            }
+        }
+        value.decor_mut().set_suffix("");
+        array.push_formatted(value);
+        array.set_trailing("\n");
+        array.set_trailing_comma(true);
+        let index = array.len() - 1;
+        set_line_comment(array, index, comment);
+
+        Ok(())
+    }
+
+    /// Remove a ticker from whichever list contains it
+    pub fn remove_ticker(&mut self, ticker: &str) -> Result<TickerList> {
+        let (list, index) = self
+            .position(ticker)
+            .with_context(|| format!("{} is not configured", ticker))?;
+
+        let array = self.array_mut(list);
+        let removed_prefix = prefix_of(array.get(index).unwrap());
+        let (previous_comment, _) = split_line_comment(&removed_prefix);
+        let previous_comment = previous_comment.to_string();
+        array.remove(index);
+
+        // The removed value's prefix held the comment of the line before it
+        if index < array.len() {
+            let next = array.get_mut(index).unwrap();
+            let raw = prefix_of(next);
+            let (_, rest) = split_line_comment(&raw);
+            next.decor_mut()
+                .set_prefix(format!("{}{}", previous_comment, rest));
+        } else if index > 0 {
+            array.set_trailing(format!("{}\n", previous_comment));
+        } else {
+            array.set_trailing("");
+            array.set_trailing_comma(false);
+        }
+
+        Ok(list)
+    }
+
+    /// Replace a ticker in place, keeping its position; the note is added to the line comment
+    pub fn rename_ticker(
+        &mut self,
+        old: &str,
+        new: &str,
+        note: Option<&str>,
+    ) -> Result<TickerList> {
+        validate_ticker(new)?;
+        let (list, index) = self
+            .position(old)
+            .with_context(|| format!("{} is not configured", old))?;
+        if let Some(existing) = self.find(new) {
+            anyhow::bail!("{} is already configured in {}", new, existing);
+        }
+
+        let array = self.array_mut(list);
+        let existing_comment = line_comment(array, index);
+        let slot = array.get_mut(index).unwrap();
+        let decor = slot.decor().clone();
+        *slot = Value::from(new);
+        *slot.decor_mut() = decor;
+
+        if let Some(note) = note {
+            let comment = match existing_comment {
+                Some(existing) => format!("{} ({})", existing, note),
+                None => note.to_string(),
+            };
+            set_line_comment(array, index, Some(&comment));
+        }
+
+        Ok(list)
+    }
+
+    /// Write the document back to the file it was opened from
+    pub fn save(&self) -> Result<()> {
+        fs::write(&self.path, self.doc.to_string())
+            .with_context(|| format!("Failed to write {}", self.path.display()))
+    }
+}
+
+impl fmt::Display for ConfigEditor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.doc)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Tickers we track
+non_us_tickers = [
+    "MC.PA", # LVMH
+    "SPD.L", # Sports Direct
+    "ITX.MC", # Inditex
+]
+us_tickers = [
+    "NKE", # Nike
+    "TJX", # TJX
+]
+
+# [email]
+# recipients = ["SPD.L"]
+"#;
+
+    #[test]
+    fn test_rename_keeps_comments_and_order() {
+        let mut editor = ConfigEditor::parse(CONFIG).unwrap();
+        let list = editor
+            .rename_ticker("SPD.L", "FRAS.L", Some("changed from SPD.L on 2019-12-01"))
+            .unwrap();
+        assert_eq!(list, TickerList::NonUs);
+
+        let expected = CONFIG.replace(
+            "    \"SPD.L\", # Sports Direct\n",
+            "    \"FRAS.L\", # Sports Direct (changed from SPD.L on 2019-12-01)\n",
+        );
+        assert_eq!(editor.to_string(), expected);
+
+        // The last value's comment lives in the array's trailing decor
+        editor.rename_ticker("TJX", "TJX.N", None).unwrap();
+        assert!(editor.to_string().contains("    \"TJX.N\", # TJX\n]"));
+    }
+
+    #[test]
+    fn test_add_and_remove_keep_comments_on_their_lines() {
+        let mut editor = ConfigEditor::parse(CONFIG).unwrap();
+
+        editor
+            .add_ticker(TickerList::Us, "VFC", Some("VF Corporation"))
+            .unwrap();
+        assert!(editor
+            .to_string()
+            .contains("    \"TJX\", # TJX\n    \"VFC\", # VF Corporation\n]"));
+
+        assert_eq!(editor.remove_ticker("SPD.L").unwrap(), TickerList::NonUs);
+        assert!(editor
+            .to_string()
+            .contains("    \"MC.PA\", # LVMH\n    \"ITX.MC\", # Inditex\n]"));
+
+        editor.remove_ticker("VFC").unwrap();
+        assert!(editor.to_string().contains("    \"TJX\", # TJX\n]"));
+
+        // The result is still valid TOML with the expected lists
+        let parsed: crate::config::Config = toml::from_str(&editor.to_string()).unwrap();
+        assert_eq!(parsed.non_us_tickers, vec!["MC.PA", "ITX.MC"]);
+        assert_eq!(parsed.us_tickers, vec!["NKE", "TJX"]);
+    }
+
+    #[test]
+    fn test_validation() {
+        let mut editor = ConfigEditor::parse(CONFIG).unwrap();
+
+        assert!(editor.add_ticker(TickerList::Us, "MC.PA", None).is_err());
+        assert!(editor
+            .add_ticker(TickerList::Us, "BAD TICKER", None)
+            .is_err());
+        assert!(editor.remove_ticker("UNKNOWN").is_err());
+        assert!(editor.rename_ticker("NKE", "TJX", None).is_err());
+        assert!(ConfigEditor::parse("us_tickers = \"NKE\"").is_err());
+    }
+}
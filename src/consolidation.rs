@@ -0,0 +1,232 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use std::collections::HashMap;
+
+use crate::config::ConsolidationConfig;
+use crate::overrides::Figures;
+
+/// Export column listing the tickers merged into a company's row
+pub const CSV_HEADER: &str = "Listings";
+
+/// A ranked row that can be merged with the other listings of its company
+pub trait Listing {
+    fn ticker(&self) -> &str;
+    fn set_ticker(&mut self, ticker: &str);
+    fn figures(&self) -> Figures;
+    fn set_figures(&mut self, figures: Figures);
+}
+
+/// Add another share class's market cap to `base`, expressing it in the base currency
+fn add_share_class(base: &mut Figures, other: &Figures) {
+    if other.original_currency == base.original_currency {
+        base.market_cap_original += other.market_cap_original;
+    } else if base.market_cap_eur > 0.0 {
+        base.market_cap_original +=
+            other.market_cap_eur * base.market_cap_original / base.market_cap_eur;
+    }
+    base.market_cap_eur += other.market_cap_eur;
+    base.market_cap_usd += other.market_cap_usd;
+}
+
+/// Map each configured ticker to its group, failing if a ticker is listed twice
+fn check_groups(groups: &[ConsolidationConfig]) -> Result<HashMap<&str, usize>> {
+    let mut group_of = HashMap::new();
+    for (i, group) in groups.iter().enumerate() {
+        let tickers = std::iter::once(&group.primary)
+            .chain(&group.share_classes)
+            .chain(&group.duplicate_listings);
+        for ticker in tickers {
+            if let Some(other) = group_of.insert(ticker.as_str(), i) {
+                anyhow::bail!(
+                    "Ticker {} is listed twice in the consolidations (under {} and {})",
+                    ticker,
+                    groups[other].primary,
+                    group.primary
+                );
+            }
+        }
+    }
+    Ok(group_of)
+}
+
+/// Merge the listings of each configured company into one row under its primary ticker.
+///
+/// Share classes are summed; duplicate listings are only used when the primary listing
+/// is missing, and then keep their own ticker, since their figures are in their own
+/// currency (an ADR in USD standing in for a Hong Kong listing). Each row comes with
+/// the tickers merged into it (empty when the row was not consolidated).
+pub fn consolidate<T: Listing>(
+    rows: Vec<T>,
+    groups: &[ConsolidationConfig],
+) -> Result<Vec<(T, Vec<String>)>> {
+    let group_of = check_groups(groups)?;
+
+    let mut members: Vec<Vec<T>> = groups.iter().map(|_| Vec::new()).collect();
+    let mut consolidated = Vec::new();
+    for row in rows {
+        match group_of.get(row.ticker()) {
+            Some(&i) => members[i].push(row),
+            None => consolidated.push((row, Vec::new())),
+        }
+    }
+
+    for (group, mut rows) in groups.iter().zip(members) {
+        if rows.is_empty() {
+            continue;
+        }
+
+        // Prefer the primary listing, then a duplicate of it, as the company's row
+        let position = |ticker: &str| {
+            if ticker == group.primary {
+                0
+            } else if let Some(i) = group.duplicate_listings.iter().position(|t| t == ticker) {
+                1 + i
+            } else {
+                1 + group.duplicate_listings.len()
+                    + group
+                        .share_classes
+                        .iter()
+                        .position(|t| t == ticker)
+                        .unwrap_or(0)
+            }
+        };
+        rows.sort_by_key(|row| position(row.ticker()));
+
+        let mut rows = rows.into_iter();
+        let mut base = rows.next().expect("group has rows");
+        let mut figures = base.figures();
+        let mut listings = vec![base.ticker().to_string()];
+        for row in rows {
+            if group.duplicate_listings.iter().any(|t| t == row.ticker()) {
+                continue;
+            }
+            add_share_class(&mut figures, &row.figures());
+            listings.push(row.ticker().to_string());
+        }
+
+        if let Some(name) = &group.name {
+            figures.name = name.clone();
+        }
+        base.set_figures(figures);
+        if !group.duplicate_listings.iter().any(|t| t == base.ticker()) {
+            base.set_ticker(&group.primary);
+        }
+        consolidated.push((base, listings));
+    }
+
+    Ok(consolidated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Row {
+        ticker: String,
+        figures: Figures,
+    }
+
+    impl Listing for Row {
+        fn ticker(&self) -> &str {
+            &self.ticker
+        }
+        fn set_ticker(&mut self, ticker: &str) {
+            self.ticker = ticker.to_string();
+        }
+        fn figures(&self) -> Figures {
+            self.figures.clone()
+        }
+        fn set_figures(&mut self, figures: Figures) {
+            self.figures = figures;
+        }
+    }
+
+    fn row(ticker: &str, original: f64, currency: &str, eur: f64) -> Row {
+        Row {
+            ticker: ticker.to_string(),
+            figures: Figures {
+                name: ticker.to_string(),
+                market_cap_original: original,
+                original_currency: currency.to_string(),
+                market_cap_eur: eur,
+                market_cap_usd: eur * 1.1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_consolidate_share_classes_and_duplicates() {
+        let groups = vec![
+            ConsolidationConfig {
+                primary: "HM-B.ST".to_string(),
+                name: Some("H&M".to_string()),
+                share_classes: vec!["HM-A.ST".to_string()],
+                duplicate_listings: vec!["HNNMY".to_string()],
+            },
+            ConsolidationConfig {
+                primary: "9988.HK".to_string(),
+                name: None,
+                share_classes: vec![],
+                duplicate_listings: vec!["BABA".to_string()],
+            },
+        ];
+        let rows = vec![
+            row("HM-B.ST", 200e9, "SEK", 18e9),
+            row("HNNMY", 20e9, "USD", 18e9),
+            row("HM-A.ST", 20e9, "SEK", 1.8e9),
+            row("BABA", 250e9, "USD", 230e9),
+            row("NKE", 100e9, "USD", 92e9),
+        ];
+
+        let consolidated = consolidate(rows, &groups).unwrap();
+        assert_eq!(consolidated.len(), 3);
+        let (hm, listings) = &consolidated[1];
+        assert_eq!(hm.ticker, "HM-B.ST");
+        assert_eq!(hm.figures.name, "H&M");
+        assert_eq!(hm.figures.market_cap_original, 220e9);
+        assert!((hm.figures.market_cap_eur - 19.8e9).abs() < 1.0);
+        assert_eq!(
+            listings,
+            &vec!["HM-B.ST".to_string(), "HM-A.ST".to_string()]
+        );
+
+        // Without the primary listing the ADR stands in for it under its own ticker,
+        // so its USD figures are not passed off as a Hong Kong listing
+        let (alibaba, listings) = &consolidated[2];
+        assert_eq!(alibaba.ticker, "BABA");
+        assert_eq!(alibaba.figures.original_currency, "USD");
+        assert_eq!(alibaba.figures.market_cap_eur, 230e9);
+        assert_eq!(listings, &vec!["BABA".to_string()]);
+
+        let overlapping = vec![groups[0].clone(), groups[0].clone()];
+        assert!(consolidate(Vec::<Row>::new(), &overlapping).is_err());
+    }
+
+    #[test]
+    fn test_stand_in_keeps_its_ticker_and_currency() {
+        let groups = vec![ConsolidationConfig {
+            primary: "HM-B.ST".to_string(),
+            name: Some("H&M".to_string()),
+            share_classes: vec!["HM-A.ST".to_string()],
+            duplicate_listings: vec!["HNNMY".to_string()],
+        }];
+        // The primary listing is missing; the share class is added to the ADR in USD
+        let rows = vec![
+            row("HNNMY", 20e9, "USD", 18e9),
+            row("HM-A.ST", 20e9, "SEK", 1.8e9),
+        ];
+
+        let consolidated = consolidate(rows, &groups).unwrap();
+        let (hm, listings) = &consolidated[0];
+        assert_eq!(hm.ticker, "HNNMY");
+        assert_eq!(hm.figures.name, "H&M");
+        assert_eq!(hm.figures.original_currency, "USD");
+        assert!((hm.figures.market_cap_original - 22e9).abs() < 1.0);
+        assert!((hm.figures.market_cap_eur - 19.8e9).abs() < 1.0);
+        assert_eq!(listings, &vec!["HNNMY".to_string(), "HM-A.ST".to_string()]);
+    }
+}
@@ -0,0 +1,394 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::api::{self, FMPClient};
+use crate::config;
+use crate::output::status;
+
+/// Consecutive fetches without data after which a ticker is treated as inactive
+pub const FAILURE_THRESHOLD: i64 = 3;
+
+/// Source of the inactive events recorded after failed fetches
+const FETCH_FAILURES_SOURCE: &str = "fetch_failures";
+
+/// Kind of event that takes a ticker out of the rankings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum EventType {
+    /// Removed from its exchange, e.g. after bankruptcy
+    Delisted,
+    /// Taken over by another company or taken private
+    Acquired,
+    /// Merged into another company
+    Merged,
+    /// The provider no longer reports the ticker as trading
+    Inactive,
+}
+
+impl EventType {
+    fn as_str(self) -> &'static str {
+        match self {
+            EventType::Delisted => "delisted",
+            EventType::Acquired => "acquired",
+            EventType::Merged => "merged",
+            EventType::Inactive => "inactive",
+        }
+    }
+}
+
+impl fmt::Display for EventType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[derive(Debug, Serialize, Clone, sqlx::FromRow)]
+pub struct CorporateEvent {
+    pub ticker: String,
+    pub event_type: String,
+    pub event_date: String,
+    pub reason: Option<String>,
+    pub source: String,
+}
+
+fn today() -> String {
+    Utc::now().format("%Y-%m-%d").to_string()
+}
+
+/// Record an event for a ticker; returns false if that event was already recorded
+pub async fn record_event(
+    pool: &SqlitePool,
+    ticker: &str,
+    event_type: EventType,
+    event_date: &str,
+    reason: Option<&str>,
+    source: &str,
+) -> Result<bool> {
+    NaiveDate::parse_from_str(event_date, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Invalid date '{}', expected YYYY-MM-DD", event_date))?;
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO corporate_events (ticker, event_type, event_date, reason, source)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT(ticker, event_type) DO NOTHING
+        "#,
+    )
+    .bind(ticker)
+    .bind(event_type.as_str())
+    .bind(event_date)
+    .bind(reason)
+    .bind(source)
+    .execute(pool)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Remove all events of a ticker, bringing it back into the rankings
+pub async fn remove_events(pool: &SqlitePool, ticker: &str) -> Result<u64> {
+    let removed = sqlx::query("DELETE FROM corporate_events WHERE ticker = ?")
+        .bind(ticker)
+        .execute(pool)
+        .await?
+        .rows_affected();
+    sqlx::query("DELETE FROM ticker_fetch_failures WHERE ticker = ?")
+        .bind(ticker)
+        .execute(pool)
+        .await?;
+
+    Ok(removed)
+}
+
+/// All recorded events, most recent first
+pub async fn list_events(pool: &SqlitePool) -> Result<Vec<CorporateEvent>> {
+    let events = sqlx::query_as::<_, CorporateEvent>(
+        r#"
+        SELECT ticker, event_type, event_date, reason, source
+        FROM corporate_events
+        ORDER BY event_date DESC, ticker
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(events)
+}
+
+/// Date from which each ticker is excluded from the rankings (its earliest event)
+pub async fn exclusion_dates(pool: &SqlitePool) -> Result<HashMap<String, String>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        "SELECT ticker, MIN(event_date) FROM corporate_events GROUP BY ticker",
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows.into_iter().collect())
+}
+
+fn excluded_on(dates: impl IntoIterator<Item = (String, String)>, as_of: &str) -> HashSet<String> {
+    dates
+        .into_iter()
+        .filter(|(_, date)| date.as_str() <= as_of)
+        .map(|(ticker, _)| ticker)
+        .collect()
+}
+
+/// Tickers that are out of the rankings on the given YYYY-MM-DD date
+pub async fn excluded_tickers(pool: &SqlitePool, as_of: &str) -> Result<HashSet<String>> {
+    Ok(excluded_on(exclusion_dates(pool).await?, as_of))
+}
+
+/// Tickers no longer worth fetching on the given YYYY-MM-DD date. Tickers only
+/// excluded after failed fetches are still fetched, so a success can bring them back.
+pub async fn skipped_tickers(pool: &SqlitePool, as_of: &str) -> Result<HashSet<String>> {
+    let rows = sqlx::query_as::<_, (String, String)>(
+        r#"
+        SELECT ticker, MIN(event_date) FROM corporate_events
+        WHERE source != ?
+        GROUP BY ticker
+        "#,
+    )
+    .bind(FETCH_FAILURES_SOURCE)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(excluded_on(rows, as_of))
+}
+
+/// Tickers that are out of the rankings for the snapshot taken at a unix timestamp
+pub async fn excluded_at(pool: &SqlitePool, timestamp: i64) -> Result<HashSet<String>> {
+    let date = chrono::DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_else(today);
+    excluded_tickers(pool, &date).await
+}
+
+/// Reset the failure count of a ticker after a successful fetch, and bring it back
+/// into the rankings if failed fetches had marked it inactive
+pub async fn record_fetch_success(pool: &SqlitePool, ticker: &str) -> Result<()> {
+    sqlx::query("DELETE FROM ticker_fetch_failures WHERE ticker = ?")
+        .bind(ticker)
+        .execute(pool)
+        .await?;
+    sqlx::query("DELETE FROM corporate_events WHERE ticker = ? AND source = ?")
+        .bind(ticker)
+        .bind(FETCH_FAILURES_SOURCE)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+/// Count a fetch for which the provider had no data; after `FAILURE_THRESHOLD` of
+/// those in a row the ticker is recorded as inactive. Returns true when that happened.
+///
+/// Other errors, such as timeouts, rate limits or API key problems, say nothing about
+/// the ticker and are not counted.
+pub async fn record_fetch_failure(
+    pool: &SqlitePool,
+    ticker: &str,
+    error: &anyhow::Error,
+) -> Result<bool> {
+    if !api::is_no_data_found(error) {
+        return Ok(false);
+    }
+    let error = format!("{:#}", error);
+
+    let (failures,) = sqlx::query_as::<_, (i64,)>(
+        r#"
+        INSERT INTO ticker_fetch_failures (ticker, consecutive_failures, last_error)
+        VALUES (?, 1, ?)
+        ON CONFLICT(ticker) DO UPDATE SET
+            consecutive_failures = consecutive_failures + 1,
+            last_error = excluded.last_error,
+            last_failed_at = CURRENT_TIMESTAMP
+        RETURNING consecutive_failures
+        "#,
+    )
+    .bind(ticker)
+    .bind(&error)
+    .fetch_one(pool)
+    .await?;
+
+    if failures < FAILURE_THRESHOLD {
+        return Ok(false);
+    }
+
+    let reason = format!("{} consecutive fetches without data: {}", failures, error);
+    record_event(
+        pool,
+        ticker,
+        EventType::Inactive,
+        &today(),
+        Some(&reason),
+        FETCH_FAILURES_SOURCE,
+    )
+    .await
+}
+
+/// Record a ticker the provider reports as no longer actively trading
+pub async fn record_inactive_profile(pool: &SqlitePool, ticker: &str) -> Result<bool> {
+    record_event(
+        pool,
+        ticker,
+        EventType::Inactive,
+        &today(),
+        Some("Provider profile reports the ticker as not actively trading"),
+        "fmp_profile",
+    )
+    .await
+}
+
+/// Match FMP's delisted companies against our tickers and record the delistings
+pub async fn check_delistings(pool: &SqlitePool, fmp_client: &FMPClient) -> Result<usize> {
+    let config = config::load_config()?;
+    let tracked: HashSet<String> = config
+        .non_us_tickers
+        .into_iter()
+        .chain(config.us_tickers)
+        .collect();
+
+    status!("Fetching delisted companies from FMP API...");
+    let delisted = fmp_client.fetch_delisted_companies().await?;
+    status!("✅ Fetched {} delisted companies", delisted.len());
+
+    let mut recorded = 0;
+    for company in delisted.iter().filter(|c| tracked.contains(&c.symbol)) {
+        let date = company.delisted_date.clone().unwrap_or_else(today);
+        let reason = format!(
+            "{} delisted from {}",
+            company.company_name.as_deref().unwrap_or(&company.symbol),
+            company.exchange.as_deref().unwrap_or("its exchange")
+        );
+        if record_event(
+            pool,
+            &company.symbol,
+            EventType::Delisted,
+            &date,
+            Some(&reason),
+            "fmp_delisted",
+        )
+        .await?
+        {
+            status!("⚠️  {} delisted on {}", company.symbol, date);
+            recorded += 1;
+        }
+    }
+
+    status!("✅ Recorded {} new delistings", recorded);
+    Ok(recorded)
+}
+
+/// Print the recorded events as a table
+pub fn print_events(events: &[CorporateEvent]) {
+    if events.is_empty() {
+        status!("No corporate events recorded.");
+        return;
+    }
+
+    status!(
+        "{:<12} {:<10} {:<12} {:<15} Reason",
+        "Ticker",
+        "Event",
+        "Date",
+        "Source"
+    );
+    for event in events {
+        status!(
+            "{:<12} {:<10} {:<12} {:<15} {}",
+            event.ticker,
+            event.event_type,
+            event.event_date,
+            event.source,
+            event.reason.as_deref().unwrap_or("")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_exclusion_from_event_date() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        assert!(record_event(
+            &pool,
+            "EXPR",
+            EventType::Delisted,
+            "2024-04-22",
+            Some("Bankruptcy"),
+            "manual"
+        )
+        .await
+        .unwrap());
+        // Recording the same event again is a no-op
+        assert!(!record_event(
+            &pool,
+            "EXPR",
+            EventType::Delisted,
+            "2024-05-01",
+            None,
+            "manual"
+        )
+        .await
+        .unwrap());
+
+        assert!(excluded_tickers(&pool, "2024-04-21")
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(excluded_tickers(&pool, "2024-04-22")
+            .await
+            .unwrap()
+            .contains("EXPR"));
+
+        // Timeouts, rate limits and the like never count
+        let timeout = anyhow::anyhow!("Failed to send request");
+        for _ in 0..=FAILURE_THRESHOLD {
+            assert!(!record_fetch_failure(&pool, "NKE", &timeout).await.unwrap());
+        }
+
+        // Only the third fetch in a row without data marks a ticker as inactive
+        let no_data = anyhow::Error::new(api::NoDataFound).context("Failed to fetch profile");
+        for attempt in 1..=FAILURE_THRESHOLD {
+            let recorded = record_fetch_failure(&pool, "CHS", &no_data).await.unwrap();
+            assert_eq!(recorded, attempt == FAILURE_THRESHOLD);
+        }
+        let events = list_events(&pool).await.unwrap();
+        assert_eq!(events.len(), 2);
+        assert!(events
+            .iter()
+            .any(|e| e.ticker == "CHS" && e.event_type == "inactive"));
+        assert!(excluded_tickers(&pool, &today())
+            .await
+            .unwrap()
+            .contains("CHS"));
+
+        // CHS is still fetched, and the next success brings it back
+        let skipped = skipped_tickers(&pool, &today()).await.unwrap();
+        assert!(skipped.contains("EXPR") && !skipped.contains("CHS"));
+        record_fetch_success(&pool, "CHS").await.unwrap();
+        assert!(!excluded_tickers(&pool, &today())
+            .await
+            .unwrap()
+            .contains("CHS"));
+
+        assert_eq!(remove_events(&pool, "EXPR").await.unwrap(), 1);
+        assert!(list_events(&pool).await.unwrap().is_empty());
+    }
+}
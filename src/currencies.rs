@@ -3,6 +3,7 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 use crate::api::FMPClient;
+use crate::output::status;
 use anyhow::Result;
 use sqlx::sqlite::SqlitePool;
 use std::collections::HashMap;
@@ -78,6 +79,15 @@ pub async fn get_rate_map_from_db(pool: &SqlitePool) -> Result<HashMap<String, f
     Ok(rate_map)
 }
 
+/// Timestamp of the newest exchange rate `get_rate_map_from_db` is built from
+pub async fn get_rate_map_timestamp(pool: &SqlitePool) -> Result<Option<i64>> {
+    let (timestamp,) =
+        sqlx::query_as::<_, (Option<i64>,)>("SELECT MAX(timestamp) FROM forex_rates")
+            .fetch_one(pool)
+            .await?;
+    Ok(timestamp)
+}
+
 /// Convert an amount from one currency to another using the rate map
 pub fn convert_currency(
     amount: f64,
@@ -214,10 +224,10 @@ pub async fn list_forex_symbols(pool: &SqlitePool) -> Result<Vec<String>> {
 
 /// Update currencies from FMP API
 pub async fn update_currencies(fmp_client: &FMPClient, pool: &SqlitePool) -> Result<()> {
-    println!("Fetching currencies from FMP API...");
+    status!("Fetching currencies from FMP API...");
     let exchange_rates = match fmp_client.get_exchange_rates().await {
         Ok(rates) => {
-            println!("✅ Currencies fetched");
+            status!("✅ Currencies fetched");
             rates
         }
         Err(e) => {
@@ -236,7 +246,7 @@ pub async fn update_currencies(fmp_client: &FMPClient, pool: &SqlitePool) -> Res
         }
     }
 
-    println!("✅ Currencies updated in database");
+    status!("✅ Currencies updated in database");
     Ok(())
 }
 
@@ -4,12 +4,13 @@
 use crate::api;
 use crate::config;
 use crate::currencies::get_rate_map_from_db;
+use crate::models::Details;
+use crate::output::{status, Failure, Outcome};
 use anyhow::Result;
 use chrono::Local;
 use csv::Writer;
 use sqlx::sqlite::SqlitePool;
 use std::path::PathBuf;
-use tokio;
 
 pub async fn export_details_eu_csv(pool: &SqlitePool) -> Result<()> {
     let config = config::load_config()?;
@@ -25,7 +26,7 @@ pub async fn export_details_eu_csv(pool: &SqlitePool) -> Result<()> {
     let mut writer = Writer::from_path(&csv_path)?;
 
     // Write header
-    writer.write_record(&[
+    writer.write_record([
         "Ticker",
         "Company Name",
         "Market Cap",
@@ -63,7 +64,7 @@ pub async fn export_details_eu_csv(pool: &SqlitePool) -> Result<()> {
         let (ticker, details) = task.await?;
         match details {
             Ok(details) => {
-                writer.write_record(&[
+                writer.write_record([
                     &details.ticker,
                     &details.name.unwrap_or_default(),
                     &details
@@ -107,13 +108,13 @@ pub async fn export_details_eu_csv(pool: &SqlitePool) -> Result<()> {
                         .unwrap_or_default(),
                     &details.roe.map(|r| r.to_string()).unwrap_or_default(),
                 ])?;
-                println!("✅ Data written to CSV");
+                status!("✅ Data written to CSV");
             }
             Err(e) => {
                 eprintln!("Error fetching details for {}: {}", ticker, e);
                 // Write empty row for failed ticker
                 let error_msg = format!("Error: {}", e);
-                writer.write_record(&[
+                writer.write_record([
                     &ticker, "", "", "", "", "", "", &error_msg, "", "", "", "", "", "", "", "", "",
                 ])?;
             }
@@ -121,18 +122,21 @@ pub async fn export_details_eu_csv(pool: &SqlitePool) -> Result<()> {
     }
 
     writer.flush()?;
-    println!("\n✅ CSV file created at: {}", csv_path.display());
+    status!("\n✅ CSV file created at: {}", csv_path.display());
 
     Ok(())
 }
 
-pub async fn list_details_eu(pool: &SqlitePool) -> Result<()> {
+/// Fetch the details of the non-US tickers, printing each company as it arrives
+pub async fn list_details_eu(pool: &SqlitePool) -> Result<Outcome<Vec<Details>>> {
     let config = config::load_config()?;
     let tickers = config.non_us_tickers;
     let rate_map = get_rate_map_from_db(pool).await?;
 
+    let mut companies = Vec::new();
+    let mut failures = Vec::new();
     for (i, ticker) in tickers.iter().enumerate() {
-        println!(
+        status!(
             "\nFetching the marketcap for {} ({}/{}) ⌛️",
             ticker,
             i + 1,
@@ -140,20 +144,27 @@ pub async fn list_details_eu(pool: &SqlitePool) -> Result<()> {
         );
         match api::get_details_eu(ticker, &rate_map).await {
             Ok(details) => {
-                println!("Company: {}", details.name.unwrap_or_default());
+                status!("Company: {}", details.name.clone().unwrap_or_default());
                 if let Some(market_cap) = details.market_cap {
-                    println!(
+                    status!(
                         "Market Cap: {} {}",
-                        details.currency_symbol.unwrap_or_default(),
+                        details.currency_symbol.clone().unwrap_or_default(),
                         market_cap
                     );
                 }
-                println!("Active: {}", details.active.unwrap_or_default());
-                println!("---");
+                status!("Active: {}", details.active.unwrap_or_default());
+                status!("---");
+                companies.push(details);
+            }
+            Err(e) => {
+                eprintln!("Error fetching details for {}: {}", ticker, e);
+                failures.push(Failure::new(ticker, e));
             }
-            Err(e) => eprintln!("Error fetching details for {}: {}", ticker, e),
         }
     }
 
-    Ok(())
+    Ok(Outcome {
+        data: companies,
+        failures,
+    })
 }
@@ -3,6 +3,8 @@
 
 use crate::api::PolygonClient;
 use crate::config;
+use crate::models::Details;
+use crate::output::{status, Failure, Outcome};
 use anyhow::Result;
 use chrono::{Local, NaiveDate};
 use csv::Writer;
@@ -26,7 +28,7 @@ pub async fn export_details_us_csv(_pool: &SqlitePool) -> Result<()> {
     let mut writer = Writer::from_path(&csv_path)?;
 
     // Write header
-    writer.write_record(&[
+    writer.write_record([
         "Ticker",
         "Company Name",
         "Market Cap",
@@ -46,7 +48,7 @@ pub async fn export_details_us_csv(_pool: &SqlitePool) -> Result<()> {
     ])?;
 
     for (i, ticker) in tickers.iter().enumerate() {
-        println!(
+        status!(
             "\nFetching the marketcap for {} ({}/{}) ",
             ticker,
             i + 1,
@@ -54,7 +56,7 @@ pub async fn export_details_us_csv(_pool: &SqlitePool) -> Result<()> {
         );
         match client.get_details(ticker, date).await {
             Ok(details) => {
-                writer.write_record(&[
+                writer.write_record([
                     &details.ticker,
                     &details.name.unwrap_or_default(),
                     &details
@@ -87,34 +89,37 @@ pub async fn export_details_us_csv(_pool: &SqlitePool) -> Result<()> {
                         .unwrap_or_default(),
                     &details.roe.map(|r| r.to_string()).unwrap_or_default(),
                 ])?;
-                println!(" Data written to CSV");
+                status!(" Data written to CSV");
             }
             Err(e) => {
                 eprintln!("Error fetching details for {}: {}", ticker, e);
                 // Write empty row for failed ticker
                 let error_msg = format!("Error: {}", e);
-                writer.write_record(&[
-                    &ticker, "", "", "", "", &error_msg, "", "", "", "", "", "", "", "", "", "",
+                writer.write_record([
+                    ticker, "", "", "", "", &error_msg, "", "", "", "", "", "", "", "", "", "",
                 ])?;
             }
         }
     }
 
     writer.flush()?;
-    println!("\n CSV file created at: {}", csv_path.display());
+    status!("\n CSV file created at: {}", csv_path.display());
 
     Ok(())
 }
 
-pub async fn list_details_us(_pool: &SqlitePool) -> Result<()> {
+/// Fetch the details of the US tickers, printing each company as it arrives
+pub async fn list_details_us(_pool: &SqlitePool) -> Result<Outcome<Vec<Details>>> {
     let config = config::load_config()?;
     let tickers = config.us_tickers;
     let api_key = env::var("POLYGON_API_KEY").expect("POLYGON_API_KEY must be set");
     let client = Arc::new(PolygonClient::new(api_key));
     let date = NaiveDate::from_ymd_opt(2023, 11, 1).unwrap();
 
+    let mut companies = Vec::new();
+    let mut failures = Vec::new();
     for (i, ticker) in tickers.iter().enumerate() {
-        println!(
+        status!(
             "\nFetching the marketcap for {} ({}/{}) ",
             ticker,
             i + 1,
@@ -122,20 +127,27 @@ pub async fn list_details_us(_pool: &SqlitePool) -> Result<()> {
         );
         match client.get_details(ticker, date).await {
             Ok(details) => {
-                println!("Company: {}", details.name.unwrap_or_default());
+                status!("Company: {}", details.name.clone().unwrap_or_default());
                 if let Some(market_cap) = details.market_cap {
-                    println!(
+                    status!(
                         "Market Cap: {} {}",
-                        details.currency_symbol.unwrap_or_default(),
+                        details.currency_symbol.clone().unwrap_or_default(),
                         market_cap
                     );
                 }
-                println!("Active: {}", details.active.unwrap_or_default());
-                println!("---");
+                status!("Active: {}", details.active.unwrap_or_default());
+                status!("---");
+                companies.push(details);
+            }
+            Err(e) => {
+                eprintln!("Error fetching details for {}: {}", ticker, e);
+                failures.push(Failure::new(ticker, e));
             }
-            Err(e) => eprintln!("Error fetching details for {}: {}", ticker, e),
         }
     }
 
-    Ok(())
+    Ok(Outcome {
+        data: companies,
+        failures,
+    })
 }
@@ -0,0 +1,272 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::Local;
+use csv::Writer;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::api::{FMPClient, ScreenerResult};
+use crate::config;
+use crate::config_editor::{ConfigEditor, TickerList};
+use crate::output::status;
+
+/// FMP industries screened when none are given on the command line
+pub const DEFAULT_INDUSTRIES: [&str; 5] = [
+    "Apparel - Manufacturers",
+    "Apparel - Retail",
+    "Apparel - Footwear & Accessories",
+    "Luxury Goods",
+    "Textile Manufacturing",
+];
+
+/// Exchanges whose tickers belong in `us_tickers`
+const US_EXCHANGES: [&str; 3] = ["NYSE", "NASDAQ", "AMEX"];
+
+/// A company that is not in our universe yet
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct Candidate {
+    #[serde(rename = "Rank")]
+    pub rank: usize,
+    #[serde(rename = "Ticker")]
+    pub ticker: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Market Cap")]
+    pub market_cap: f64,
+    #[serde(rename = "Exchange")]
+    pub exchange: String,
+    #[serde(rename = "Country")]
+    pub country: String,
+    #[serde(rename = "Reason")]
+    pub reason: String,
+}
+
+impl Candidate {
+    /// The config.toml list this candidate would be added to
+    pub fn list(&self) -> TickerList {
+        if US_EXCHANGES.contains(&self.exchange.as_str()) {
+            TickerList::Us
+        } else {
+            TickerList::NonUs
+        }
+    }
+}
+
+/// Options for a discovery run
+#[derive(Debug, Clone)]
+pub struct DiscoverOptions {
+    pub industries: Vec<String>,
+    pub min_market_cap: u64,
+    /// Only keep candidates listed on these exchanges; empty means any exchange
+    pub exchanges: Vec<String>,
+    pub limit: usize,
+}
+
+/// Format a market cap as e.g. `12.3B`
+fn format_market_cap(value: f64) -> String {
+    if value >= 1e12 {
+        format!("{:.1}T", value / 1e12)
+    } else if value >= 1e9 {
+        format!("{:.1}B", value / 1e9)
+    } else {
+        format!("{:.0}M", value / 1e6)
+    }
+}
+
+/// Turn screener results into candidates ranked by market cap, leaving out tickers
+/// we already track and companies that don't pass the filters
+pub fn select_candidates(
+    results: Vec<ScreenerResult>,
+    configured: &HashSet<String>,
+    options: &DiscoverOptions,
+) -> Vec<Candidate> {
+    let exchanges: HashSet<String> = options.exchanges.iter().map(|e| e.to_uppercase()).collect();
+
+    // The same company can show up in several industries
+    let mut unique: HashMap<String, ScreenerResult> = HashMap::new();
+    for result in results {
+        unique.entry(result.symbol.clone()).or_insert(result);
+    }
+
+    let mut candidates: Vec<Candidate> = unique
+        .into_values()
+        .filter(|r| !configured.contains(&r.symbol))
+        .filter_map(|r| {
+            let market_cap = r.market_cap.unwrap_or(0.0);
+            if market_cap < options.min_market_cap as f64 {
+                return None;
+            }
+            let exchange = r.exchange.clone().unwrap_or_default();
+            if !exchanges.is_empty() && !exchanges.contains(&exchange.to_uppercase()) {
+                return None;
+            }
+            let industry = r.industry.as_deref().unwrap_or("Unknown industry");
+            Some(Candidate {
+                rank: 0,
+                name: r.company_name.clone().unwrap_or_else(|| r.symbol.clone()),
+                reason: format!(
+                    "{} with a {} market cap on {}",
+                    industry,
+                    format_market_cap(market_cap),
+                    if exchange.is_empty() {
+                        "an unknown exchange"
+                    } else {
+                        &exchange
+                    }
+                ),
+                ticker: r.symbol,
+                market_cap,
+                exchange,
+                country: r.country.unwrap_or_default(),
+            })
+        })
+        .collect();
+
+    candidates.sort_by(|a, b| {
+        b.market_cap
+            .partial_cmp(&a.market_cap)
+            .unwrap_or(std::cmp::Ordering::Equal)
+            .then_with(|| a.ticker.cmp(&b.ticker))
+    });
+    candidates.truncate(options.limit);
+    for (i, candidate) in candidates.iter_mut().enumerate() {
+        candidate.rank = i + 1;
+    }
+
+    candidates
+}
+
+/// Screen the industries, print and export the candidates, and optionally add them to config.toml
+pub async fn discover(
+    fmp_client: &FMPClient,
+    options: &DiscoverOptions,
+    config_path: &str,
+    append: bool,
+) -> Result<Vec<Candidate>> {
+    let config = config::load_config_from(config_path)?;
+    let configured: HashSet<String> = config
+        .non_us_tickers
+        .into_iter()
+        .chain(config.us_tickers)
+        .collect();
+
+    let mut results = Vec::new();
+    for industry in &options.industries {
+        status!("Screening {}...", industry);
+        match fmp_client
+            .screen_industry(industry, options.min_market_cap)
+            .await
+        {
+            Ok(batch) => {
+                status!("  {} companies", batch.len());
+                results.extend(batch);
+            }
+            Err(e) => eprintln!("⚠️  Skipping {}: {}", industry, e),
+        }
+    }
+
+    let candidates = select_candidates(results, &configured, options);
+    if candidates.is_empty() {
+        status!("No new candidates found.");
+        return Ok(candidates);
+    }
+
+    status!(
+        "\n{:<5} {:<12} {:<40} {:>10}  Reason",
+        "Rank",
+        "Ticker",
+        "Name",
+        "Market Cap"
+    );
+    for c in &candidates {
+        status!(
+            "{:<5} {:<12} {:<40} {:>10}  {}",
+            c.rank,
+            c.ticker,
+            c.name.chars().take(40).collect::<String>(),
+            format_market_cap(c.market_cap),
+            c.reason
+        );
+    }
+
+    std::fs::create_dir_all("output")?;
+    let filename = format!(
+        "output/discover_candidates_{}.csv",
+        Local::now().format("%Y%m%d_%H%M%S")
+    );
+    let mut writer = Writer::from_path(&filename)?;
+    for candidate in &candidates {
+        writer.serialize(candidate)?;
+    }
+    writer.flush()?;
+    status!(
+        "\n✅ {} candidates written to {}",
+        candidates.len(),
+        filename
+    );
+
+    if append {
+        let mut editor = ConfigEditor::open(config_path)?;
+        let mut added = 0;
+        for c in &candidates {
+            match editor.add_ticker(c.list(), &c.ticker, Some(&c.name)) {
+                Ok(()) => added += 1,
+                Err(e) => status!("⚠️  Warning: Skipping {}: {}", c.ticker, e),
+            }
+        }
+        editor.save()?;
+        status!("✅ Added {} candidates to {}", added, config_path);
+    }
+
+    Ok(candidates)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(symbol: &str, market_cap: f64, exchange: &str) -> ScreenerResult {
+        ScreenerResult {
+            symbol: symbol.to_string(),
+            company_name: Some(format!("{} Inc", symbol)),
+            market_cap: Some(market_cap),
+            industry: Some("Luxury Goods".to_string()),
+            exchange: Some(exchange.to_string()),
+            country: None,
+        }
+    }
+
+    #[test]
+    fn test_select_candidates() {
+        let configured: HashSet<String> = ["MC.PA".to_string()].into();
+        let options = DiscoverOptions {
+            industries: vec![],
+            min_market_cap: 1_000_000_000,
+            exchanges: vec!["nyse".to_string(), "EURONEXT".to_string()],
+            limit: 10,
+        };
+        let results = vec![
+            result("MC.PA", 300e9, "EURONEXT"),
+            result("RL", 15e9, "NYSE"),
+            result("CPRI", 2.5e9, "NYSE"),
+            result("RL", 15e9, "NYSE"),
+            result("SMALL", 0.5e9, "NYSE"),
+            result("KER.PA", 25e9, "EURONEXT"),
+            result("9983.T", 90e9, "JPX"),
+        ];
+
+        let candidates = select_candidates(results, &configured, &options);
+        let tickers: Vec<_> = candidates.iter().map(|c| c.ticker.as_str()).collect();
+        assert_eq!(tickers, vec!["KER.PA", "RL", "CPRI"]);
+        assert_eq!(candidates[0].rank, 1);
+        assert_eq!(candidates[0].list(), TickerList::NonUs);
+        assert_eq!(candidates[1].list(), TickerList::Us);
+        assert_eq!(
+            candidates[1].reason,
+            "Luxury Goods with a 15.0B market cap on NYSE"
+        );
+    }
+}
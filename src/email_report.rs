@@ -0,0 +1,316 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use pulldown_cmark::{html, Options, Parser};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::env;
+use std::path::Path;
+
+use crate::config::{self, EmailConfig};
+use crate::output::status;
+use crate::theme::Presentation;
+use crate::visualizations;
+
+const DEFAULT_BREVO_BASE_URL: &str = "https://api.brevo.com";
+
+#[derive(Debug, Serialize)]
+pub struct Contact {
+    pub email: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Attachment {
+    /// Base64 encoded file content
+    pub content: String,
+    pub name: String,
+}
+
+/// Request body for Brevo's `POST /v3/smtp/email`
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransactionalEmail {
+    pub sender: Contact,
+    pub to: Vec<Contact>,
+    pub subject: String,
+    pub html_content: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub attachment: Vec<Attachment>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SendEmailResponse {
+    message_id: Option<String>,
+}
+
+pub struct BrevoClient {
+    client: Client,
+    api_key: String,
+    base_url: String,
+}
+
+impl BrevoClient {
+    /// Create a client; `base_url` overrides the Brevo API host, e.g. for a local stand-in
+    pub fn new(api_key: String, base_url: Option<String>) -> Self {
+        let base_url = base_url.unwrap_or_else(|| DEFAULT_BREVO_BASE_URL.to_string());
+
+        Self {
+            client: Client::new(),
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Send a transactional email and return Brevo's message id
+    pub async fn send_email(&self, email: &TransactionalEmail) -> Result<String> {
+        let url = format!("{}/v3/smtp/email", self.base_url);
+        let response = self
+            .client
+            .post(&url)
+            .header("accept", "application/json")
+            .header("api-key", &self.api_key)
+            .json(email)
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach {}", url))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            anyhow::bail!("Brevo returned {}: {}", status, body);
+        }
+
+        let result: SendEmailResponse = response.json().await?;
+        Ok(result.message_id.unwrap_or_default())
+    }
+}
+
+/// Find the most recent summary report for the given dates
+fn find_summary_report(from_date: &str, to_date: &str) -> Result<String> {
+    let output_dir = Path::new("output");
+    let pattern = format!("comparison_{}_to_{}_summary_", from_date, to_date);
+
+    let mut matching_files = Vec::new();
+    for entry in std::fs::read_dir(output_dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name();
+        let file_name_str = file_name.to_string_lossy();
+
+        if file_name_str.starts_with(&pattern) && file_name_str.ends_with(".md") {
+            matching_files.push(file_name_str.to_string());
+        }
+    }
+
+    if matching_files.is_empty() {
+        anyhow::bail!(
+            "No summary report found for {} to {}. Please run 'compare-market-caps' first.",
+            from_date,
+            to_date
+        );
+    }
+
+    matching_files.sort();
+    let selected_file = matching_files.last().unwrap();
+
+    Ok(format!("output/{}", selected_file))
+}
+
+/// Render the Markdown summary report as an HTML email body
+fn markdown_to_html(markdown: &str) -> String {
+    let parser = Parser::new_ext(markdown, Options::ENABLE_TABLES);
+    let mut body = String::new();
+    html::push_html(&mut body, parser);
+
+    format!(
+        "<html><head><style>\
+         body {{ font-family: Arial, sans-serif; color: #1f2937; }} \
+         table {{ border-collapse: collapse; }} \
+         th, td {{ border: 1px solid #d1d5db; padding: 4px 8px; text-align: left; }}\
+         </style></head><body>{}</body></html>",
+        body
+    )
+}
+
+/// Use the report's first heading as the subject line
+fn subject_from_markdown(markdown: &str) -> Option<String> {
+    markdown
+        .lines()
+        .find_map(|line| line.strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+}
+
+/// Read the comparison charts as PNG, which mail services accept as attachments
+/// unlike SVG, rendering them first if any are missing
+fn chart_attachments(
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<Vec<Attachment>> {
+    let paths: Vec<String> = visualizations::chart_kinds()
+        .map(|kind| visualizations::chart_path(from_date, to_date, kind, "png"))
+        .collect();
+
+    if paths.iter().any(|path| !Path::new(path).exists()) {
+        visualizations::generate_png_charts(from_date, to_date, presentation)?;
+    }
+
+    paths
+        .iter()
+        .map(|path| {
+            let content =
+                std::fs::read(path).with_context(|| format!("Failed to read {}", path))?;
+            let name = Path::new(path)
+                .file_name()
+                .unwrap()
+                .to_string_lossy()
+                .to_string();
+            Ok(Attachment {
+                content: base64::engine::general_purpose::STANDARD.encode(content),
+                name,
+            })
+        })
+        .collect()
+}
+
+/// Build the report email for the given dates and recipients
+async fn build_report_email(
+    from_date: &str,
+    to_date: &str,
+    email_config: &EmailConfig,
+    presentation: &Presentation,
+) -> Result<TransactionalEmail> {
+    let report_path = find_summary_report(from_date, to_date)?;
+    status!("Using report: {}", report_path);
+    let markdown = std::fs::read_to_string(&report_path)
+        .with_context(|| format!("Failed to read {}", report_path))?;
+
+    let attachment = chart_attachments(from_date, to_date, presentation)?;
+
+    Ok(TransactionalEmail {
+        sender: Contact {
+            email: email_config.sender_email.clone(),
+            name: Some(email_config.sender_name.clone()),
+        },
+        to: email_config
+            .recipients
+            .iter()
+            .map(|email| Contact {
+                email: email.clone(),
+                name: None,
+            })
+            .collect(),
+        subject: subject_from_markdown(&markdown)
+            .unwrap_or_else(|| format!("Market cap comparison {} to {}", from_date, to_date)),
+        html_content: markdown_to_html(&markdown),
+        attachment,
+    })
+}
+
+/// Email the comparison report and charts to the recipients configured in config.toml
+pub async fn send_report(
+    from_date: &str,
+    to_date: &str,
+    config_path: &str,
+    base_url: Option<String>,
+    presentation: &Presentation,
+) -> Result<()> {
+    let config = config::load_config_from(config_path)?;
+    let email_config = config
+        .email
+        .with_context(|| format!("No [email] section in {}", config_path))?;
+    if email_config.recipients.is_empty() {
+        anyhow::bail!("No recipients configured in [email] of {}", config_path);
+    }
+
+    let api_key = env::var("BREVO_API_KEY").context("BREVO_API_KEY must be set")?;
+    let base_url = base_url.or_else(|| env::var("BREVO_BASE_URL").ok());
+    let client = BrevoClient::new(api_key, base_url);
+
+    let email = build_report_email(from_date, to_date, &email_config, presentation).await?;
+    status!(
+        "Sending \"{}\" with {} attachments to {} recipients...",
+        email.subject,
+        email.attachment.len(),
+        email.to.len()
+    );
+
+    let message_id = client.send_email(&email).await?;
+    status!("✅ Report sent (message id: {})", message_id);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::HeaderMap, routing::post, Json, Router};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn test_markdown_report_to_html() {
+        let markdown = "# Comparison: 2025-01-01 to 2025-02-01\n\n| Ticker | Change |\n|---|---|\n| AAA | +5% |\n";
+
+        assert_eq!(
+            subject_from_markdown(markdown).as_deref(),
+            Some("Comparison: 2025-01-01 to 2025-02-01")
+        );
+        let html = markdown_to_html(markdown);
+        assert!(html.contains("<h1>Comparison: 2025-01-01 to 2025-02-01</h1>"));
+        assert!(html.contains("<td>AAA</td>"));
+    }
+
+    #[tokio::test]
+    async fn test_send_email_against_stand_in() {
+        let received: Arc<Mutex<Option<(String, serde_json::Value)>>> = Arc::new(Mutex::new(None));
+        let captured = received.clone();
+        let app = Router::new().route(
+            "/v3/smtp/email",
+            post(
+                move |headers: HeaderMap, Json(body): Json<serde_json::Value>| {
+                    let captured = captured.clone();
+                    async move {
+                        let api_key = headers["api-key"].to_str().unwrap().to_string();
+                        *captured.lock().unwrap() = Some((api_key, body));
+                        Json(serde_json::json!({ "messageId": "<test@stand-in>" }))
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let client = BrevoClient::new("secret".to_string(), Some(format!("http://{}/", addr)));
+        let email = TransactionalEmail {
+            sender: Contact {
+                email: "reports@example.com".to_string(),
+                name: Some("Reports".to_string()),
+            },
+            to: vec![Contact {
+                email: "analyst@example.com".to_string(),
+                name: None,
+            }],
+            subject: "Report".to_string(),
+            html_content: "<p>Hi</p>".to_string(),
+            attachment: vec![Attachment {
+                content: "iVBORw0KGgo=".to_string(),
+                name: "chart.png".to_string(),
+            }],
+        };
+
+        let message_id = client.send_email(&email).await.unwrap();
+        assert_eq!(message_id, "<test@stand-in>");
+
+        let (api_key, body) = received.lock().unwrap().take().unwrap();
+        assert_eq!(api_key, "secret");
+        assert_eq!(body["to"][0]["email"], "analyst@example.com");
+        assert_eq!(body["htmlContent"], "<p>Hi</p>");
+        assert_eq!(body["attachment"][0]["name"], "chart.png");
+    }
+}
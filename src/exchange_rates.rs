@@ -3,6 +3,7 @@
 
 use crate::api::FMPClient;
 use crate::currencies::insert_forex_rate;
+use crate::output::status;
 use anyhow::Result;
 use chrono::Local;
 use sqlx::sqlite::SqlitePool;
@@ -10,10 +11,10 @@ use sqlx::sqlite::SqlitePool;
 /// Update exchange rates in the database
 pub async fn update_exchange_rates(fmp_client: &FMPClient, pool: &SqlitePool) -> Result<()> {
     // Fetch exchange rates
-    println!("Fetching current exchange rates...");
+    status!("Fetching current exchange rates...");
     let exchange_rates = match fmp_client.get_exchange_rates().await {
         Ok(rates) => {
-            println!("✅ Exchange rates fetched");
+            status!("✅ Exchange rates fetched");
             rates
         }
         Err(e) => {
@@ -29,6 +30,6 @@ pub async fn update_exchange_rates(fmp_client: &FMPClient, pool: &SqlitePool) ->
         }
     }
 
-    println!("✅ Exchange rates updated in database");
+    status!("✅ Exchange rates updated in database");
     Ok(())
 }
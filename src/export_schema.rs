@@ -0,0 +1,392 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDate};
+use csv::{Reader, WriterBuilder};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+
+use crate::consolidation;
+use crate::overrides;
+use crate::parquet_export::{self, Column};
+use crate::provenance::{self, ProvenanceRow};
+use crate::xlsx_report::Cell;
+
+/// Version of the snapshot export columns, written in every row.
+///
+/// Version 1 files have no `Schema Version` column: combined exports with both
+/// `Symbol` and `Ticker` and no rank, and date exports with `Date` instead of `As Of`.
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Columns of every market cap snapshot export, in order
+pub const HEADERS: [&str; 25] = [
+    "Schema Version",
+    "Rank",
+    "As Of",
+    "Ticker",
+    "Name",
+    "Market Cap (Original)",
+    "Original Currency",
+    "Market Cap (EUR)",
+    "Market Cap (USD)",
+    "Price",
+    "Exchange",
+    "Active",
+    "Description",
+    "Homepage URL",
+    "Employees",
+    "Timestamp",
+    "Source",
+    "Endpoint",
+    "Method",
+    "Fetched At",
+    "FX Rate (EUR)",
+    "FX Rate (USD)",
+    "FX Rate Timestamp",
+    overrides::CSV_HEADER,
+    consolidation::CSV_HEADER,
+];
+
+/// A row of a snapshot export, as written by every exporter and read back by
+/// `compare-market-caps`; columns missing from older files are empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MarketCapRecord {
+    #[serde(rename = "Schema Version", default)]
+    pub schema_version: Option<u32>,
+    #[serde(rename = "Rank", default)]
+    pub rank: Option<usize>,
+    /// Snapshot date (YYYY-MM-DD)
+    #[serde(rename = "As Of", alias = "Date", default)]
+    pub as_of: Option<String>,
+    #[serde(rename = "Ticker")]
+    pub ticker: String,
+    #[serde(rename = "Name")]
+    pub name: String,
+    #[serde(rename = "Market Cap (Original)")]
+    pub market_cap_original: Option<f64>,
+    #[serde(rename = "Original Currency")]
+    pub original_currency: Option<String>,
+    #[serde(rename = "Market Cap (EUR)")]
+    pub market_cap_eur: Option<f64>,
+    #[serde(rename = "Market Cap (USD)")]
+    pub market_cap_usd: Option<f64>,
+    #[serde(rename = "Price", default)]
+    pub price: Option<f64>,
+    #[serde(rename = "Exchange", default)]
+    pub exchange: Option<String>,
+    #[serde(rename = "Active", default)]
+    pub active: Option<bool>,
+    #[serde(rename = "Description", default)]
+    pub description: Option<String>,
+    #[serde(rename = "Homepage URL", default)]
+    pub homepage_url: Option<String>,
+    #[serde(rename = "Employees", default)]
+    pub employees: Option<i64>,
+    /// Unix timestamp of the snapshot
+    #[serde(rename = "Timestamp", default)]
+    pub timestamp: Option<i64>,
+    /// Provider of the figure; missing in exports made before provenance was recorded
+    #[serde(rename = "Source", default)]
+    pub source: Option<String>,
+    #[serde(rename = "Endpoint", default)]
+    pub endpoint: Option<String>,
+    #[serde(rename = "Method", default)]
+    pub method: Option<String>,
+    #[serde(rename = "Fetched At", default)]
+    pub fetched_at: Option<String>,
+    #[serde(rename = "FX Rate (EUR)", default)]
+    pub fx_rate_eur: Option<f64>,
+    #[serde(rename = "FX Rate (USD)", default)]
+    pub fx_rate_usd: Option<f64>,
+    #[serde(rename = "FX Rate Timestamp", default)]
+    pub fx_rate_timestamp: Option<String>,
+    /// Fields pinned by a manual override, e.g. `market_cap, name`
+    #[serde(rename = "Overridden", default)]
+    pub overridden: Option<String>,
+    /// Tickers merged into this row by consolidation, e.g. `HM-B.ST + HM-A.ST`
+    #[serde(rename = "Listings", default)]
+    pub listings: Option<String>,
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+impl MarketCapRecord {
+    /// A record of the current schema for the snapshot taken at `timestamp`
+    pub fn new(rank: usize, timestamp: i64, ticker: &str, name: &str) -> Self {
+        Self {
+            schema_version: Some(SCHEMA_VERSION),
+            rank: Some(rank),
+            as_of: DateTime::from_timestamp(timestamp, 0)
+                .map(|dt| dt.date_naive().format("%Y-%m-%d").to_string()),
+            ticker: ticker.to_string(),
+            name: name.to_string(),
+            timestamp: Some(timestamp),
+            ..Default::default()
+        }
+    }
+
+    /// Fill the provenance columns from the stored row
+    pub fn with_provenance(self, provenance: &ProvenanceRow) -> Self {
+        Self {
+            source: provenance.source_provider.clone(),
+            endpoint: provenance.source_endpoint.clone(),
+            method: provenance.market_cap_method.clone(),
+            fetched_at: non_empty(provenance::format_timestamp(provenance.fetched_at)),
+            fx_rate_eur: provenance.fx_rate_eur,
+            fx_rate_usd: provenance.fx_rate_usd,
+            fx_rate_timestamp: non_empty(provenance::format_timestamp(
+                provenance.fx_rate_timestamp,
+            )),
+            ..self
+        }
+    }
+
+    pub fn as_of_date(&self) -> Option<NaiveDate> {
+        self.as_of
+            .as_deref()
+            .and_then(|d| NaiveDate::parse_from_str(d, "%Y-%m-%d").ok())
+    }
+
+    /// Values for the `HEADERS` columns as worksheet cells
+    fn cells(&self) -> Vec<Cell> {
+        let number =
+            |value: Option<i64>| value.map(|v| Cell::Number(v as f64)).unwrap_or(Cell::Empty);
+        vec![
+            number(self.schema_version.map(i64::from)),
+            number(self.rank.map(|r| r as i64)),
+            self.as_of.as_deref().into(),
+            Cell::Text(self.ticker.clone()),
+            Cell::Text(self.name.clone()),
+            self.market_cap_original.into(),
+            self.original_currency.as_deref().into(),
+            self.market_cap_eur.into(),
+            self.market_cap_usd.into(),
+            self.price.into(),
+            self.exchange.as_deref().into(),
+            self.active.map(Cell::Bool).unwrap_or(Cell::Empty),
+            self.description.as_deref().into(),
+            self.homepage_url.as_deref().into(),
+            number(self.employees),
+            number(self.timestamp),
+            self.source.as_deref().into(),
+            self.endpoint.as_deref().into(),
+            self.method.as_deref().into(),
+            self.fetched_at.as_deref().into(),
+            self.fx_rate_eur.into(),
+            self.fx_rate_usd.into(),
+            self.fx_rate_timestamp.as_deref().into(),
+            self.overridden.as_deref().into(),
+            self.listings.as_deref().into(),
+        ]
+    }
+}
+
+/// Write records as CSV with the `HEADERS` row, also when there are no records
+pub fn write_csv<W: std::io::Write>(out: W, records: &[MarketCapRecord]) -> Result<()> {
+    let mut writer = WriterBuilder::new().has_headers(false).from_writer(out);
+    writer.write_record(HEADERS)?;
+    for record in records {
+        writer.serialize(record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Write records as Parquet, with the `HEADERS` columns in snake_case and typed
+pub fn write_parquet(filename: &str, records: &[MarketCapRecord]) -> Result<()> {
+    let text =
+        |f: fn(&MarketCapRecord) -> Option<&str>| parquet_export::strings(records.iter().map(f));
+    let timestamps = |f: fn(&MarketCapRecord) -> Option<&str>| {
+        parquet_export::timestamps(
+            records
+                .iter()
+                .map(|r| f(r).and_then(provenance::parse_timestamp)),
+        )
+    };
+    let columns: Vec<Column> = vec![
+        (
+            "schema_version",
+            parquet_export::integers(records.iter().map(|r| r.schema_version.map(i64::from))),
+        ),
+        (
+            "rank",
+            parquet_export::integers(records.iter().map(|r| r.rank.map(|v| v as i64))),
+        ),
+        (
+            "as_of",
+            parquet_export::dates(records.iter().map(MarketCapRecord::as_of_date)),
+        ),
+        ("ticker", text(|r| Some(&r.ticker))),
+        ("name", text(|r| Some(&r.name))),
+        (
+            "market_cap_original",
+            parquet_export::decimals(
+                records.iter().map(|r| r.market_cap_original),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "original_currency",
+            text(|r| r.original_currency.as_deref()),
+        ),
+        (
+            "market_cap_eur",
+            parquet_export::decimals(
+                records.iter().map(|r| r.market_cap_eur),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "market_cap_usd",
+            parquet_export::decimals(
+                records.iter().map(|r| r.market_cap_usd),
+                parquet_export::MONEY_SCALE,
+            ),
+        ),
+        (
+            "price",
+            parquet_export::decimals(records.iter().map(|r| r.price), parquet_export::MONEY_SCALE),
+        ),
+        ("exchange", text(|r| r.exchange.as_deref())),
+        (
+            "active",
+            parquet_export::booleans(records.iter().map(|r| r.active)),
+        ),
+        ("description", text(|r| r.description.as_deref())),
+        ("homepage_url", text(|r| r.homepage_url.as_deref())),
+        (
+            "employees",
+            parquet_export::integers(records.iter().map(|r| r.employees)),
+        ),
+        (
+            "timestamp",
+            parquet_export::timestamps(records.iter().map(|r| r.timestamp)),
+        ),
+        ("source", text(|r| r.source.as_deref())),
+        ("endpoint", text(|r| r.endpoint.as_deref())),
+        ("method", text(|r| r.method.as_deref())),
+        ("fetched_at", timestamps(|r| r.fetched_at.as_deref())),
+        (
+            "fx_rate_eur",
+            parquet_export::decimals(
+                records.iter().map(|r| r.fx_rate_eur),
+                parquet_export::RATE_SCALE,
+            ),
+        ),
+        (
+            "fx_rate_usd",
+            parquet_export::decimals(
+                records.iter().map(|r| r.fx_rate_usd),
+                parquet_export::RATE_SCALE,
+            ),
+        ),
+        (
+            "fx_rate_timestamp",
+            timestamps(|r| r.fx_rate_timestamp.as_deref()),
+        ),
+        ("overridden", text(|r| r.overridden.as_deref())),
+        ("listings", text(|r| r.listings.as_deref())),
+    ];
+    parquet_export::write_parquet(filename, columns)
+}
+
+/// Records as worksheet rows under the `HEADERS`
+pub fn xlsx_rows(records: &[MarketCapRecord]) -> Vec<Vec<Cell>> {
+    records.iter().map(MarketCapRecord::cells).collect()
+}
+
+/// Read a snapshot export of any schema version up to `SCHEMA_VERSION`
+pub fn read_records<R: std::io::Read>(source: R) -> Result<Vec<MarketCapRecord>> {
+    let mut reader = Reader::from_reader(source);
+    let mut records = Vec::new();
+
+    for result in reader.deserialize() {
+        let mut record: MarketCapRecord = result?;
+        if let Some(version) = record.schema_version.filter(|v| *v > SCHEMA_VERSION) {
+            anyhow::bail!(
+                "Export schema version {} is newer than the supported version {}",
+                version,
+                SCHEMA_VERSION
+            );
+        }
+        // Version 1 combined exports only carry the snapshot timestamp
+        if record.as_of.is_none() {
+            record.as_of = record
+                .timestamp
+                .and_then(|ts| DateTime::from_timestamp(ts, 0))
+                .map(|dt| dt.date_naive().format("%Y-%m-%d").to_string());
+        }
+        records.push(record);
+    }
+
+    Ok(records)
+}
+
+/// Read a snapshot export file
+pub fn read_csv(file_path: &str) -> Result<Vec<MarketCapRecord>> {
+    let file =
+        File::open(file_path).with_context(|| format!("Failed to open CSV file: {}", file_path))?;
+    read_records(file).with_context(|| format!("Failed to read CSV file: {}", file_path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_round_trip_and_old_files() {
+        let record = MarketCapRecord {
+            market_cap_eur: Some(300e9),
+            active: Some(true),
+            employees: Some(213_000),
+            listings: Some("MC.PA + LVMHF".to_string()),
+            ..MarketCapRecord::new(1, 1_756_505_400, "MC.PA", "LVMH")
+        }
+        .with_provenance(&ProvenanceRow {
+            source_provider: Some("fmp".to_string()),
+            fetched_at: Some(1_756_505_400),
+            ..Default::default()
+        });
+        assert_eq!(record.as_of.as_deref(), Some("2025-08-29"));
+
+        let mut csv = Vec::new();
+        write_csv(&mut csv, std::slice::from_ref(&record)).unwrap();
+        let text = String::from_utf8(csv).unwrap();
+        assert!(text.starts_with("Schema Version,Rank,As Of,Ticker,"));
+
+        // The header row matches the serialized field names
+        let mut serialized = WriterBuilder::new().from_writer(Vec::new());
+        serialized.serialize(&record).unwrap();
+        let serialized = String::from_utf8(serialized.into_inner().unwrap()).unwrap();
+        assert_eq!(serialized.lines().next(), text.lines().next());
+        assert_eq!(read_records(text.as_bytes()).unwrap(), vec![record]);
+
+        // Version 1 combined export: Symbol and Ticker, no rank
+        let combined = "Symbol,Ticker,Name,Market Cap (Original),Original Currency,Market Cap (EUR),Market Cap (USD),Exchange,Active,Description,Homepage URL,Employees,Timestamp\n\
+            NKE,NKE,Nike,100,USD,90,100,NYSE,true,,,,1722470340\n\
+            TJX,TJX,TJX,80,USD,72,80,NYSE,false,,,,\n";
+        let records = read_records(combined.as_bytes()).unwrap();
+        assert_eq!(records[0].rank, None);
+        assert_eq!(records[0].as_of.as_deref(), Some("2024-07-31"));
+        assert_eq!(records[1].active, Some(false));
+        assert_eq!(records[1].as_of, None);
+
+        // Version 1 date export: Date instead of As Of
+        let dated = "Rank,Ticker,Name,Market Cap (Original),Original Currency,Market Cap (EUR),Market Cap (USD),Price,Exchange,Active,Description,Homepage URL,Employees,Date\n\
+            1,MC.PA,LVMH,317,EUR,317,370,0,PAR,true,,,,2024-12-31\n";
+        let records = read_records(dated.as_bytes()).unwrap();
+        assert_eq!(records[0].rank, Some(1));
+        assert_eq!(
+            records[0].as_of_date(),
+            NaiveDate::from_ymd_opt(2024, 12, 31)
+        );
+
+        let newer = "Schema Version,Ticker,Name,Market Cap (Original),Original Currency,Market Cap (EUR),Market Cap (USD)\n\
+            3,NKE,Nike,100,USD,90,100\n";
+        assert!(read_records(newer.as_bytes()).is_err());
+    }
+}
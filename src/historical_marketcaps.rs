@@ -2,20 +2,97 @@
 //
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use crate::api;
+use crate::api::{self, HistoricalMarketCap};
 use crate::config;
 use crate::currencies::{convert_currency, get_rate_map_from_db};
+use crate::output::{status, Failure, FetchSummary, Outcome};
+use crate::overrides::Figures;
+use crate::provenance::Provenance;
+use crate::trading_calendar::TradingCalendar;
+use crate::validation;
 use anyhow::Result;
 use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
 use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
 use std::sync::Arc;
 
+/// Convert a historical market cap to EUR and USD and store it under `timestamp`, as
+/// fetched; manual overrides are applied when the data is read
+pub async fn store_historical_market_cap(
+    pool: &SqlitePool,
+    ticker: &str,
+    market_cap: &HistoricalMarketCap,
+    rate_map: &HashMap<String, f64>,
+    timestamp: i64,
+) -> Result<()> {
+    let figures = Figures {
+        name: market_cap.name.clone(),
+        market_cap_original: market_cap.market_cap_original,
+        original_currency: market_cap.original_currency.clone(),
+        market_cap_eur: convert_currency(
+            market_cap.market_cap_original,
+            &market_cap.original_currency,
+            "EUR",
+            rate_map,
+        ),
+        market_cap_usd: convert_currency(
+            market_cap.market_cap_original,
+            &market_cap.original_currency,
+            "USD",
+            rate_map,
+        ),
+    };
+
+    let provenance = Provenance::fmp(
+        pool,
+        market_cap.method,
+        figures.market_cap_original,
+        figures.market_cap_eur,
+        figures.market_cap_usd,
+    )
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT OR REPLACE INTO market_caps (
+            ticker, name, market_cap_original, original_currency,
+            market_cap_eur, market_cap_usd, exchange, price,
+            active, market_cap_method, source_provider, source_endpoint,
+            fetched_at, fx_rate_eur, fx_rate_usd, fx_rate_timestamp, timestamp
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(ticker)
+    .bind(&figures.name)
+    .bind(figures.market_cap_original)
+    .bind(&figures.original_currency)
+    .bind(figures.market_cap_eur)
+    .bind(figures.market_cap_usd)
+    .bind(&market_cap.exchange)
+    .bind(market_cap.price)
+    .bind(true)
+    .bind(&provenance.method)
+    .bind(&provenance.provider)
+    .bind(&provenance.endpoint)
+    .bind(provenance.fetched_at)
+    .bind(provenance.fx_rate_eur)
+    .bind(provenance.fx_rate_usd)
+    .bind(provenance.fx_rate_timestamp)
+    .bind(timestamp)
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
 pub async fn fetch_historical_marketcaps(
     pool: &SqlitePool,
     start_year: i32,
     end_year: i32,
-) -> Result<()> {
+) -> Result<Outcome<FetchSummary>> {
     let config = config::load_config()?;
+    let calendar = TradingCalendar::from_config(config.calendar.as_ref())?;
     let tickers = [config.non_us_tickers, config.us_tickers].concat();
 
     // Get FMP client for market data
@@ -23,70 +100,42 @@ pub async fn fetch_historical_marketcaps(
         .expect("FINANCIALMODELINGPREP_API_KEY must be set");
     let fmp_client = Arc::new(api::FMPClient::new(api_key));
 
-    println!(
+    status!(
         "Fetching historical market caps from {} to {}",
-        start_year, end_year
+        start_year,
+        end_year
     );
 
+    let mut timestamps = Vec::new();
+    let mut summary = FetchSummary::default();
+    let mut failures = Vec::new();
     for year in start_year..=end_year {
         // Get Dec 31st of each year
         let date = NaiveDate::from_ymd_opt(year, 12, 31).unwrap();
         let naive_dt = NaiveDateTime::new(date, NaiveTime::default());
         let datetime_utc = naive_dt.and_utc();
-        println!("Fetching exchange rates for {}", naive_dt);
+        status!("Fetching exchange rates for {}", naive_dt);
         let rate_map = get_rate_map_from_db(pool).await?;
+        timestamps.push(datetime_utc.timestamp());
+        summary.dates.push(date.to_string());
+        calendar.print_shifted(&tickers, naive_dt.date());
 
         for ticker in &tickers {
             match fmp_client
-                .get_historical_market_cap(ticker, &datetime_utc)
+                .get_historical_market_cap(ticker, &calendar.request_datetime(ticker, &naive_dt))
                 .await
             {
                 Ok(market_cap) => {
-                    // Convert currencies if needed
-                    let market_cap_eur = convert_currency(
-                        market_cap.market_cap_original,
-                        &market_cap.original_currency,
-                        "EUR",
-                        &rate_map,
-                    );
-
-                    let market_cap_usd = convert_currency(
-                        market_cap.market_cap_original,
-                        &market_cap.original_currency,
-                        "USD",
-                        &rate_map,
-                    );
-
                     // Store the Unix timestamp of the historical date
                     let timestamp = naive_dt.and_utc().timestamp();
+                    store_historical_market_cap(pool, ticker, &market_cap, &rate_map, timestamp)
+                        .await?;
+                    summary.stored += 1;
 
-                    // Insert into database
-                    sqlx::query!(
-                        r#"
-                        INSERT INTO market_caps (
-                            ticker, name, market_cap_original, original_currency,
-                            market_cap_eur, market_cap_usd, exchange, price,
-                            active, timestamp
-                        )
-                        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                        "#,
-                        ticker,
-                        market_cap.name,
-                        market_cap.market_cap_original,
-                        market_cap.original_currency,
-                        market_cap_eur,
-                        market_cap_usd,
-                        market_cap.exchange,
-                        market_cap.price,
-                        true,
-                        timestamp,
-                    )
-                    .execute(pool)
-                    .await?;
-
-                    println!(
+                    status!(
                         "✅ Added historical market cap for {} on {}",
-                        ticker, naive_dt
+                        ticker,
+                        naive_dt
                     );
                 }
                 Err(e) => {
@@ -94,10 +143,16 @@ pub async fn fetch_historical_marketcaps(
                         "❌ Failed to fetch market cap for {} on {}: {}",
                         ticker, naive_dt, e
                     );
+                    failures.push(Failure::new(ticker, e).on(date));
                 }
             }
         }
     }
 
-    Ok(())
+    summary.issues = validation::run_validation(pool, &timestamps).await?;
+
+    Ok(Outcome {
+        data: summary,
+        failures,
+    })
 }
@@ -0,0 +1,431 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime, TimeZone};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::export_schema::{self, MarketCapRecord};
+use crate::output::{status, Failure, Outcome};
+use crate::provenance;
+
+/// A snapshot export found in the output directory
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum SnapshotFile {
+    /// `combined_marketcaps_<exported>.csv`, the latest snapshot at export time
+    Combined { exported: NaiveDateTime },
+    /// `marketcaps_<date>_<exported>.csv`, the snapshot of a date
+    Dated { date: NaiveDate },
+}
+
+impl SnapshotFile {
+    fn parse(file_name: &str) -> Option<Self> {
+        let stem = file_name.strip_suffix(".csv")?;
+        if let Some(exported) = stem.strip_prefix("combined_marketcaps_") {
+            let exported = NaiveDateTime::parse_from_str(exported, "%Y%m%d_%H%M%S").ok()?;
+            return Some(SnapshotFile::Combined { exported });
+        }
+        let (date, _exported) = stem.strip_prefix("marketcaps_")?.split_once('_')?;
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+        Some(SnapshotFile::Dated { date })
+    }
+
+    /// Snapshot timestamp of rows that carry neither a timestamp nor a date.
+    ///
+    /// A combined export holds the latest snapshot at export time, so it belongs to the
+    /// last snapshot stored earlier that day, or to the export time if there is none.
+    async fn fallback_timestamp(self, pool: &SqlitePool) -> Result<i64> {
+        let exported = match self {
+            SnapshotFile::Combined { exported } => exported,
+            SnapshotFile::Dated { date } => return Ok(midnight(date)),
+        };
+        // Exports were named after the local time of the export
+        let local = |dt: NaiveDateTime| {
+            Local
+                .from_local_datetime(&dt)
+                .earliest()
+                .map(|dt| dt.timestamp())
+                .unwrap_or_else(|| dt.and_utc().timestamp())
+        };
+        let exported_at = local(exported);
+        let day_start = local(exported.date().and_time(NaiveTime::default()));
+
+        let (stored,) = sqlx::query_as::<_, (Option<i64>,)>(
+            "SELECT MAX(timestamp) FROM market_caps WHERE timestamp BETWEEN ? AND ?",
+        )
+        .bind(day_start)
+        .bind(exported_at)
+        .fetch_one(pool)
+        .await?;
+        Ok(stored.unwrap_or(exported_at))
+    }
+}
+
+/// Date fetches store their snapshot at midnight UTC
+fn midnight(date: NaiveDate) -> i64 {
+    NaiveDateTime::new(date, NaiveTime::default())
+        .and_utc()
+        .timestamp()
+}
+
+/// Snapshot a row belongs to: its `Timestamp`, else midnight of its as-of date, else
+/// the file's fallback
+fn snapshot_timestamp(record: &MarketCapRecord, fallback: i64) -> i64 {
+    record
+        .timestamp
+        .or_else(|| record.as_of_date().map(midnight))
+        .unwrap_or(fallback)
+}
+
+/// The listing a row was stored under, or `None` for rows whose figures were never
+/// stored as such: sums of several share classes, and values pinned by an override
+fn stored_ticker(record: &MarketCapRecord) -> Option<&str> {
+    if record.overridden.as_deref().is_some_and(|o| !o.is_empty()) {
+        return None;
+    }
+    match record.listings.as_deref().filter(|l| !l.is_empty()) {
+        Some(listings) if listings.contains(" + ") => None,
+        Some(listing) => Some(listing),
+        None => Some(&record.ticker),
+    }
+}
+
+/// What was imported from one file
+#[derive(Debug, Serialize)]
+pub struct ImportedFile {
+    pub file: String,
+    /// Snapshot dates (YYYY-MM-DD) the rows were stored under
+    pub snapshots: Vec<String>,
+    pub rows: usize,
+    /// Rows left out because they sum several listings or carry override values
+    pub skipped: usize,
+}
+
+/// What an import read and stored
+#[derive(Debug, Default, Serialize)]
+pub struct ImportSummary {
+    pub files: Vec<ImportedFile>,
+    /// Number of rows inserted or updated
+    pub rows: usize,
+}
+
+async fn upsert(
+    conn: &mut sqlx::SqliteConnection,
+    ticker: &str,
+    record: &MarketCapRecord,
+    timestamp: i64,
+) -> Result<()> {
+    // Stored values win, so an import only fills in what the database is missing
+    sqlx::query(
+        r#"
+        INSERT INTO market_caps (
+            ticker, name, market_cap_original, original_currency,
+            market_cap_eur, market_cap_usd, exchange, price,
+            active, market_cap_method, source_provider, source_endpoint,
+            fetched_at, fx_rate_eur, fx_rate_usd, fx_rate_timestamp, timestamp
+        )
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (ticker, timestamp) DO UPDATE SET
+            market_cap_original = COALESCE(market_cap_original, excluded.market_cap_original),
+            original_currency = COALESCE(original_currency, excluded.original_currency),
+            market_cap_eur = COALESCE(market_cap_eur, excluded.market_cap_eur),
+            market_cap_usd = COALESCE(market_cap_usd, excluded.market_cap_usd),
+            exchange = COALESCE(exchange, excluded.exchange),
+            price = COALESCE(price, excluded.price),
+            active = COALESCE(active, excluded.active),
+            market_cap_method = COALESCE(market_cap_method, excluded.market_cap_method),
+            source_provider = COALESCE(source_provider, excluded.source_provider),
+            source_endpoint = COALESCE(source_endpoint, excluded.source_endpoint),
+            fetched_at = COALESCE(fetched_at, excluded.fetched_at),
+            fx_rate_eur = COALESCE(fx_rate_eur, excluded.fx_rate_eur),
+            fx_rate_usd = COALESCE(fx_rate_usd, excluded.fx_rate_usd),
+            fx_rate_timestamp = COALESCE(fx_rate_timestamp, excluded.fx_rate_timestamp),
+            updated_at = CURRENT_TIMESTAMP
+        "#,
+    )
+    .bind(ticker)
+    .bind(&record.name)
+    .bind(record.market_cap_original)
+    .bind(
+        record
+            .original_currency
+            .as_deref()
+            .filter(|c| !c.is_empty()),
+    )
+    .bind(record.market_cap_eur)
+    .bind(record.market_cap_usd)
+    .bind(record.exchange.as_deref().filter(|e| !e.is_empty()))
+    .bind(record.price)
+    .bind(record.active)
+    .bind(&record.method)
+    .bind(&record.source)
+    .bind(&record.endpoint)
+    .bind(
+        record
+            .fetched_at
+            .as_deref()
+            .and_then(provenance::parse_timestamp),
+    )
+    .bind(record.fx_rate_eur)
+    .bind(record.fx_rate_usd)
+    .bind(
+        record
+            .fx_rate_timestamp
+            .as_deref()
+            .and_then(provenance::parse_timestamp),
+    )
+    .bind(timestamp)
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Store the rows of one snapshot export; nothing is written on a dry run
+async fn import_file(
+    pool: &SqlitePool,
+    path: &Path,
+    file: SnapshotFile,
+    dry_run: bool,
+) -> Result<ImportedFile> {
+    let records = export_schema::read_csv(&path.to_string_lossy())?;
+    let fallback = file.fallback_timestamp(pool).await?;
+
+    let mut snapshots = BTreeSet::new();
+    let mut rows = 0;
+    let mut skipped = 0;
+    let mut tx = pool.begin().await?;
+    for record in &records {
+        let Some(ticker) = stored_ticker(record) else {
+            skipped += 1;
+            continue;
+        };
+        let timestamp = snapshot_timestamp(record, fallback);
+        if !dry_run {
+            upsert(&mut tx, ticker, record, timestamp).await?;
+        }
+        if let Some(dt) = chrono::DateTime::from_timestamp(timestamp, 0) {
+            snapshots.insert(dt.date_naive().to_string());
+        }
+        rows += 1;
+    }
+    tx.commit().await?;
+
+    Ok(ImportedFile {
+        file: path.to_string_lossy().to_string(),
+        snapshots: snapshots.into_iter().collect(),
+        rows,
+        skipped,
+    })
+}
+
+/// Import the combined and date snapshot exports in `dir` into `market_caps`; files
+/// that cannot be read are reported as failures.
+///
+/// Rows are upserted by ticker and snapshot timestamp, so importing a file twice, or
+/// several exports of the same snapshot, does not duplicate them.
+pub async fn import_exports(
+    pool: &SqlitePool,
+    dir: &str,
+    dry_run: bool,
+) -> Result<Outcome<ImportSummary>> {
+    let mut paths: Vec<_> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .collect();
+    paths.sort();
+
+    let mut summary = ImportSummary::default();
+    let mut failures = Vec::new();
+    for path in paths {
+        let Some(file) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(SnapshotFile::parse)
+        else {
+            continue;
+        };
+
+        match import_file(pool, &path, file, dry_run).await {
+            Ok(imported) => {
+                status!(
+                    "✅ {}: {} rows for {}{}",
+                    imported.file,
+                    imported.rows,
+                    imported.snapshots.join(", "),
+                    if imported.skipped > 0 {
+                        format!(
+                            " ({} consolidated or overridden rows skipped)",
+                            imported.skipped
+                        )
+                    } else {
+                        String::new()
+                    }
+                );
+                summary.rows += imported.rows;
+                summary.files.push(imported);
+            }
+            Err(e) => {
+                status!("⚠️  Skipping {}: {:#}", path.display(), e);
+                failures.push(Failure::in_file(
+                    &path.to_string_lossy(),
+                    format!("{:#}", e),
+                ));
+            }
+        }
+    }
+
+    status!(
+        "{} {} rows from {} files",
+        if dry_run {
+            "Would import"
+        } else {
+            "✅ Imported"
+        },
+        summary.rows,
+        summary.files.len()
+    );
+    Ok(Outcome {
+        data: summary,
+        failures,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_import_header_variants_without_duplicates() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let write = |name: &str, contents: &str| {
+            std::fs::write(dir.path().join(name), contents).unwrap();
+        };
+        // Version 1 combined export without a timestamp
+        write(
+            "combined_marketcaps_20250124_162115.csv",
+            "Symbol,Ticker,Name,Market Cap (Original),Original Currency,Market Cap (EUR),Market Cap (USD),Exchange,Active,Description,Homepage URL,Employees,Timestamp\n\
+             NKE,NKE,Nike,100,USD,90,100,NYSE,true,,,,\n",
+        );
+        // Version 1 date export, exported twice
+        let dated = "Rank,Ticker,Name,Market Cap (Original),Original Currency,Market Cap (EUR),Market Cap (USD),Price,Exchange,Active,Description,Homepage URL,Employees,Date\n\
+             1,MC.PA,LVMH,317,EUR,317,370,0,PAR,true,,,,2024-12-31\n";
+        write("marketcaps_2024-12-31_20250815_134351.csv", dated);
+        write("marketcaps_2024-12-31_20250816_090000.csv", dated);
+        // Current schema with a consolidated row
+        let mut csv = Vec::new();
+        export_schema::write_csv(
+            &mut csv,
+            &[
+                MarketCapRecord {
+                    market_cap_eur: Some(80.0),
+                    listings: Some("HM-B.ST + HM-A.ST".to_string()),
+                    ..MarketCapRecord::new(1, 1_756_505_400, "HM-B.ST", "H&M")
+                },
+                MarketCapRecord {
+                    market_cap_eur: Some(70.0),
+                    fetched_at: Some("2025-08-29 22:10:00".to_string()),
+                    ..MarketCapRecord::new(2, 1_756_505_400, "ITX.MC", "Inditex")
+                },
+                // Pinned by an override, not what the provider reported
+                MarketCapRecord {
+                    market_cap_eur: Some(30.0),
+                    overridden: Some("market_cap".to_string()),
+                    ..MarketCapRecord::new(3, 1_756_505_400, "CDI.PA", "Christian Dior")
+                },
+            ],
+        )
+        .unwrap();
+        write(
+            "marketcaps_2025-08-29_20250830_080000.csv",
+            &String::from_utf8(csv).unwrap(),
+        );
+        write("top_100_active_20250124_162115.csv", "not a snapshot");
+        write(
+            "marketcaps_2025-08-30_20250831_080000.csv",
+            "Ticker,Name,Market Cap (EUR)\nNKE,Nike,not a number\n",
+        );
+
+        // The combined export belongs to the snapshot stored a minute before it
+        let combined = SnapshotFile::parse("combined_marketcaps_20250124_162115.csv").unwrap();
+        let stored = combined.fallback_timestamp(&pool).await.unwrap() - 60;
+        sqlx::query("INSERT INTO market_caps (ticker, name, timestamp) VALUES ('NKE', 'Nike', ?)")
+            .bind(stored)
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let dir = dir.path().to_str().unwrap();
+        let outcome = import_exports(&pool, dir, true).await.unwrap();
+        assert_eq!(outcome.data.rows, 4);
+        assert_eq!(outcome.failures.len(), 1);
+        assert!(outcome.failures[0]
+            .file
+            .as_deref()
+            .is_some_and(|f| f.ends_with("marketcaps_2025-08-30_20250831_080000.csv")));
+        let count = || async {
+            sqlx::query_as::<_, (i64,)>("SELECT COUNT(*) FROM market_caps")
+                .fetch_one(&pool)
+                .await
+                .unwrap()
+                .0
+        };
+        assert_eq!(count().await, 1);
+
+        import_exports(&pool, dir, false).await.unwrap();
+        let summary = import_exports(&pool, dir, false).await.unwrap().data;
+        assert_eq!(summary.files.len(), 4);
+        assert_eq!(summary.files[3].skipped, 2);
+        assert_eq!(count().await, 3);
+
+        // Stored figures are never overwritten by exported ones
+        sqlx::query("UPDATE market_caps SET market_cap_eur = 75 WHERE ticker = 'ITX.MC'")
+            .execute(&pool)
+            .await
+            .unwrap();
+        import_exports(&pool, dir, false).await.unwrap();
+        let (eur,) = sqlx::query_as::<_, (Option<f64>,)>(
+            "SELECT CAST(market_cap_eur AS REAL) FROM market_caps WHERE ticker = 'ITX.MC'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(eur, Some(75.0));
+        let (eur,) = sqlx::query_as::<_, (Option<f64>,)>(
+            "SELECT CAST(market_cap_eur AS REAL) FROM market_caps WHERE ticker = 'NKE' AND timestamp = ?",
+        )
+        .bind(stored)
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(eur, Some(90.0));
+
+        let (timestamp, fetched_at) = sqlx::query_as::<_, (i64, Option<i64>)>(
+            "SELECT timestamp, fetched_at FROM market_caps WHERE ticker = 'ITX.MC'",
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(timestamp, 1_756_505_400);
+        assert_eq!(fetched_at, Some(1_756_505_400));
+        let (timestamp,) =
+            sqlx::query_as::<_, (i64,)>("SELECT timestamp FROM market_caps WHERE ticker = 'MC.PA'")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(
+            timestamp,
+            midnight(NaiveDate::from_ymd_opt(2024, 12, 31).unwrap())
+        );
+    }
+}
@@ -3,34 +3,63 @@
 // SPDX-License-Identifier: AGPL-3.0-only
 
 mod api;
+mod bar_chart_race;
+mod companies;
 mod compare_marketcaps;
 mod config;
+mod config_editor;
+mod consolidation;
+mod corporate_events;
 mod currencies;
 mod db;
 mod details_eu_fmp;
 mod details_us_polygon;
+mod discover;
+mod email_report;
 mod exchange_rates;
+mod export_schema;
 mod historical_marketcaps;
+mod import;
 mod marketcaps;
 mod models;
 mod monthly_historical_marketcaps;
+mod notifications;
+mod output;
+mod overrides;
+mod parquet_export;
+mod provenance;
+mod rankings;
+mod reconciliation;
+mod scheduler;
+mod server;
 mod specific_date_marketcaps;
 mod symbol_changes;
+mod theme;
 mod ticker_details;
+mod trading_calendar;
 mod utils;
+mod validation;
 mod visualizations;
+mod xlsx_report;
 
 use anyhow::Result;
+use chrono::Utc;
 use clap::{Parser, Subcommand};
-// use sqlx::sqlite::SqlitePool;
+use config::ScheduledTask;
+use config_editor::{ConfigEditor, TickerList};
+use corporate_events::EventType;
+use output::{status, ExportFormat, Outcome, OutputFormat, PartialFailure};
+use sqlx::sqlite::SqlitePool;
 use std::env;
-use tokio;
 
 #[derive(Debug, Parser)]
 #[command(author, version, about, long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Option<Commands>,
+    /// Print results as text or as a single JSON document
+    #[arg(long, value_enum, global = true, default_value_t = OutputFormat::Text)]
+    output: OutputFormat,
 }
 
 #[derive(Debug, Subcommand)]
@@ -39,8 +68,27 @@ enum Commands {
     ExportUs,
     /// Export EU market caps to CSV
     ExportEu,
-    /// Export combined market caps to CSV
-    ExportCombined,
+    /// Export combined market caps to CSV, Parquet or an Excel workbook
+    ExportCombined {
+        /// File format of the exported snapshot and top 100; xlsx puts both in one workbook
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+    },
+    /// Export every stored market cap, all snapshots
+    ExportHistory {
+        /// File format of the export
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
+    },
+    /// Import combined and per-date market cap CSV exports back into the database
+    Import {
+        /// Directory with the exports
+        #[arg(long, default_value = "output")]
+        dir: String,
+        /// Show what would be imported without writing to the database
+        #[arg(long)]
+        dry_run: bool,
+    },
     /// List US market caps
     ListUs,
     /// List EU market caps
@@ -63,6 +111,13 @@ enum Commands {
         from: String,
         #[arg(long)]
         to: String,
+        /// Path to a theme file with locale settings (defaults to theme.toml if present)
+        #[arg(long)]
+        theme: Option<String>,
+        /// File format of the comparison data; xlsx adds the ranked lists and summary
+        /// sheets, and the Markdown summary is always written
+        #[arg(long, value_enum, default_value_t = ExportFormat::Csv)]
+        format: ExportFormat,
     },
     /// Generate visualization charts from comparison data
     GenerateCharts {
@@ -70,6 +125,121 @@ enum Commands {
         from: String,
         #[arg(long)]
         to: String,
+        /// Path to a theme file (defaults to theme.toml if present)
+        #[arg(long)]
+        theme: Option<String>,
+    },
+    /// Generate an animated bar chart race GIF from monthly historical market caps
+    GenerateBarChartRace {
+        start_year: i32,
+        end_year: i32,
+        /// Number of companies shown in each frame
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+        /// Total length of the animation in seconds
+        #[arg(long, default_value_t = 20)]
+        duration: u32,
+        /// Interpolated frames between two monthly snapshots
+        #[arg(long, default_value_t = 10)]
+        frames_per_step: usize,
+        /// Path to a theme file (defaults to theme.toml if present)
+        #[arg(long)]
+        theme: Option<String>,
+    },
+    /// Email the comparison report and charts through Brevo
+    SendReport {
+        #[arg(long)]
+        from: String,
+        #[arg(long)]
+        to: String,
+        /// Path to config.toml file with the [email] recipients
+        #[arg(long, default_value = "config.toml")]
+        config: String,
+        /// Override the Brevo API base URL (also read from BREVO_BASE_URL)
+        #[arg(long)]
+        base_url: Option<String>,
+        /// Path to a theme file used if charts need generating (defaults to theme.toml if present)
+        #[arg(long)]
+        theme: Option<String>,
+    },
+    /// Serve snapshots, comparisons and charts over a local HTTP JSON API
+    Serve {
+        /// Address to listen on
+        #[arg(long, default_value = "127.0.0.1:3000")]
+        addr: String,
+        /// Path to a theme file used for rendered charts (defaults to theme.toml if present)
+        #[arg(long)]
+        theme: Option<String>,
+    },
+    /// Run the daily snapshot, symbol change check and month-end backfill on the schedule
+    /// from config.toml until interrupted
+    Schedule {
+        /// Show the configured jobs and their next run instead of starting the scheduler
+        #[arg(long)]
+        list: bool,
+        /// Run one job now and exit
+        #[arg(long, value_enum)]
+        run: Option<ScheduledTask>,
+    },
+    /// Edit the ticker lists in config.toml, keeping comments and layout
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommands,
+    },
+    /// Suggest companies to add to the universe from FMP's industry screener
+    Discover {
+        /// Industry to screen; repeat for several (defaults to apparel, footwear, luxury and textiles)
+        #[arg(long = "industry")]
+        industries: Vec<String>,
+        /// Minimum market cap in USD
+        #[arg(long, default_value_t = 1_000_000_000)]
+        min_market_cap: u64,
+        /// Only suggest companies listed on this exchange, e.g. NYSE; repeat for several
+        #[arg(long = "exchange")]
+        exchanges: Vec<String>,
+        /// Maximum number of candidates
+        #[arg(long, default_value_t = 50)]
+        limit: usize,
+        /// Add the candidates to config.toml
+        #[arg(long)]
+        append: bool,
+        /// Path to config.toml file
+        #[arg(long, default_value = "config.toml")]
+        config: String,
+    },
+    /// Compare US tickers between FMP and Polygon for one date and report discrepancies
+    Reconcile {
+        /// Date to compare (YYYY-MM-DD); defaults to yesterday
+        #[arg(long)]
+        date: Option<String>,
+        /// Report differences larger than this percentage
+        #[arg(long, default_value_t = 5.0)]
+        tolerance: f64,
+        /// Provider whose market cap is used in the reconciled columns
+        #[arg(long, value_enum, default_value_t = reconciliation::Source::Fmp)]
+        market_cap_source: reconciliation::Source,
+        /// Provider whose shares outstanding are used in the reconciled columns
+        #[arg(long, value_enum, default_value_t = reconciliation::Source::Polygon)]
+        shares_source: reconciliation::Source,
+        /// Provider whose company name is used in the reconciled columns
+        #[arg(long, value_enum, default_value_t = reconciliation::Source::Fmp)]
+        name_source: reconciliation::Source,
+    },
+    /// Run the data quality checks on a stored snapshot and write a report
+    Validate {
+        /// Snapshot date (YYYY-MM-DD); defaults to the latest snapshot
+        #[arg(long)]
+        date: Option<String>,
+    },
+    /// Track delistings, acquisitions and other events that remove tickers from the rankings
+    CorporateEvents {
+        #[command(subcommand)]
+        command: CorporateEventCommands,
+    },
+    /// Pin hand-corrected market caps, currencies or names, or exclude a ticker, over a date range
+    Override {
+        #[command(subcommand)]
+        command: OverrideCommands,
     },
     /// Check for symbol changes that need to be applied
     CheckSymbolChanges {
@@ -77,7 +247,8 @@ enum Commands {
         #[arg(long, default_value = "config.toml")]
         config: String,
     },
-    /// Apply pending symbol changes to configuration
+    /// Apply pending symbol changes to configuration, reviewing each one interactively
+    /// unless --auto-apply or --dry-run is given
     ApplySymbolChanges {
         /// Path to config.toml file
         #[arg(long, default_value = "config.toml")]
@@ -91,23 +262,143 @@ enum Commands {
     },
 }
 
+#[derive(Debug, Subcommand)]
+enum CorporateEventCommands {
+    /// Fetch delisted companies from FMP and record those we track
+    Check,
+    /// List recorded corporate events
+    List,
+    /// Record an event by hand, e.g. an acquisition
+    Add {
+        ticker: String,
+        /// Kind of event
+        #[arg(long, value_enum)]
+        event: EventType,
+        /// Date of the event (YYYY-MM-DD); the ticker is unranked from this date
+        #[arg(long)]
+        date: String,
+        /// Reason shown alongside the event
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// Remove the events of a ticker so it is ranked again
+    Remove { ticker: String },
+}
+
+#[derive(Debug, Subcommand)]
+enum OverrideCommands {
+    /// Override values of a ticker from --from through --to
+    Set {
+        ticker: String,
+        /// Market cap in --currency, or in the fetched currency if none is given
+        #[arg(long)]
+        market_cap: Option<f64>,
+        /// Currency of the market cap
+        #[arg(long)]
+        currency: Option<String>,
+        /// Company name
+        #[arg(long)]
+        name: Option<String>,
+        /// Leave the ticker out of the rankings
+        #[arg(long)]
+        exclude: bool,
+        /// First day the override applies (YYYY-MM-DD)
+        #[arg(long)]
+        from: String,
+        /// Last day the override applies (YYYY-MM-DD); open-ended if omitted
+        #[arg(long)]
+        to: Option<String>,
+        /// Reason shown alongside the override
+        #[arg(long)]
+        reason: Option<String>,
+    },
+    /// List overrides, optionally of one ticker
+    List { ticker: Option<String> },
+    /// Remove an override by its id
+    Remove { id: i64 },
+}
+
+#[derive(Debug, Subcommand)]
+#[allow(clippy::enum_variant_names)]
+enum ConfigCommands {
+    /// Add a ticker to one of the ticker lists
+    AddTicker {
+        ticker: String,
+        /// List to add the ticker to
+        #[arg(long, value_enum)]
+        list: TickerList,
+        /// Comment written after the ticker, e.g. the company name
+        #[arg(long)]
+        comment: Option<String>,
+        /// Path to config.toml file
+        #[arg(long, default_value = "config.toml")]
+        config: String,
+    },
+    /// Remove a ticker from the ticker lists
+    RemoveTicker {
+        ticker: String,
+        /// Path to config.toml file
+        #[arg(long, default_value = "config.toml")]
+        config: String,
+    },
+    /// Rename a ticker in place, keeping its position and comment
+    RenameTicker {
+        old: String,
+        new: String,
+        /// Path to config.toml file
+        #[arg(long, default_value = "config.toml")]
+        config: String,
+    },
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenvy::dotenv().ok();
 
     let cli = Cli::parse();
+    output::init(cli.output);
 
     let db_url = env::var("DATABASE_URL").unwrap_or_else(|_| "sqlite:data.db".to_string());
     let pool = db::create_db_pool(&db_url).await?;
 
-    match cli.command {
+    let result = run(cli.command, pool).await;
+    if let Some(partial) = result
+        .as_ref()
+        .err()
+        .and_then(|e| e.downcast_ref::<PartialFailure>())
+    {
+        eprintln!("⚠️  {}", partial);
+        std::process::exit(output::PARTIAL_FAILURE_EXIT_CODE);
+    }
+    result
+}
+
+async fn run(command: Option<Commands>, pool: SqlitePool) -> Result<()> {
+    match command {
         Some(Commands::ExportUs) => details_us_polygon::export_details_us_csv(&pool).await?,
         Some(Commands::ExportEu) => details_eu_fmp::export_details_eu_csv(&pool).await?,
-        Some(Commands::ExportCombined) => {
-            marketcaps::marketcaps(&pool).await?;
+        Some(Commands::ExportCombined { format }) => {
+            // Don't overlap with a scheduled run
+            let schedule = config::load_config()?.schedule.unwrap_or_default();
+            let _lock = scheduler::RunLock::acquire(&schedule.lock_file)?;
+            let outcome = marketcaps::marketcaps(&pool, format).await?;
+            output::emit("export-combined", &outcome)?;
+        }
+        Some(Commands::ExportHistory { format }) => {
+            marketcaps::export_history(&pool, format).await?;
+        }
+        Some(Commands::Import { dir, dry_run }) => {
+            let outcome = import::import_exports(&pool, &dir, dry_run).await?;
+            output::emit("import", &outcome)?;
+        }
+        Some(Commands::ListUs) => {
+            let outcome = details_us_polygon::list_details_us(&pool).await?;
+            output::emit("list-us", &outcome)?;
+        }
+        Some(Commands::ListEu) => {
+            let outcome = details_eu_fmp::list_details_eu(&pool).await?;
+            output::emit("list-eu", &outcome)?;
         }
-        Some(Commands::ListUs) => details_us_polygon::list_details_us(&pool).await?,
-        Some(Commands::ListEu) => details_eu_fmp::list_details_eu(&pool).await?,
         Some(Commands::ExportRates) => {
             let api_key = env::var("FINANCIALMODELINGPREP_API_KEY")
                 .expect("FINANCIALMODELINGPREP_API_KEY must be set");
@@ -118,43 +409,298 @@ async fn main() -> Result<()> {
             start_year,
             end_year,
         }) => {
-            historical_marketcaps::fetch_historical_marketcaps(&pool, start_year, end_year).await?;
+            let outcome =
+                historical_marketcaps::fetch_historical_marketcaps(&pool, start_year, end_year)
+                    .await?;
+            output::emit("fetch-historical-market-caps", &outcome)?;
         }
         Some(Commands::FetchMonthlyHistoricalMarketCaps {
             start_year,
             end_year,
         }) => {
-            monthly_historical_marketcaps::fetch_monthly_historical_marketcaps(
+            let outcome = monthly_historical_marketcaps::fetch_monthly_historical_marketcaps(
                 &pool, start_year, end_year,
             )
             .await?;
+            output::emit("fetch-monthly-historical-market-caps", &outcome)?;
         }
         Some(Commands::FetchSpecificDateMarketCaps { date }) => {
-            specific_date_marketcaps::fetch_specific_date_marketcaps(&pool, &date).await?;
+            let outcome =
+                specific_date_marketcaps::fetch_specific_date_marketcaps(&pool, &date).await?;
+            output::emit("fetch-specific-date-market-caps", &outcome)?;
         }
         Some(Commands::AddCurrency { code, name }) => {
             let api_key = env::var("FINANCIALMODELINGPREP_API_KEY")
                 .expect("FINANCIALMODELINGPREP_API_KEY must be set");
             let fmp_client = api::FMPClient::new(api_key);
             currencies::update_currencies(&fmp_client, &pool).await?;
-            println!("✅ Currencies updated from FMP API");
+            status!("✅ Currencies updated from FMP API");
 
             // Also add the manually specified currency
             currencies::insert_currency(&pool, &code, &name).await?;
-            println!("✅ Added currency: {} ({})", name, code);
+            status!("✅ Added currency: {} ({})", name, code);
         }
         Some(Commands::ListCurrencies) => {
             let currencies = currencies::list_currencies(&pool).await?;
-            for (code, name) in currencies {
-                println!("{}: {}", code, name);
+            if output::is_json() {
+                let currencies: Vec<_> = currencies
+                    .into_iter()
+                    .map(|(code, name)| serde_json::json!({ "code": code, "name": name }))
+                    .collect();
+                output::emit("list-currencies", &Outcome::ok(currencies))?;
+            } else {
+                for (code, name) in currencies {
+                    println!("{}: {}", code, name);
+                }
+            }
+        }
+        Some(Commands::CompareMarketCaps {
+            from,
+            to,
+            theme,
+            format,
+        }) => {
+            let presentation = theme::load_presentation(theme.as_deref())?;
+            let run = compare_marketcaps::compare_market_caps(
+                &pool,
+                &from,
+                &to,
+                &presentation.locale,
+                format,
+            )
+            .await?;
+            output::emit("compare-market-caps", &Outcome::ok(run))?;
+        }
+        Some(Commands::GenerateCharts { from, to, theme }) => {
+            let presentation = theme::load_presentation(theme.as_deref())?;
+            visualizations::generate_all_charts(&from, &to, &presentation).await?;
+        }
+        Some(Commands::GenerateBarChartRace {
+            start_year,
+            end_year,
+            top,
+            duration,
+            frames_per_step,
+            theme,
+        }) => {
+            let presentation = theme::load_presentation(theme.as_deref())?;
+            bar_chart_race::generate_bar_chart_race(
+                &pool,
+                start_year,
+                end_year,
+                top,
+                duration,
+                frames_per_step,
+                &presentation,
+            )
+            .await?;
+        }
+        Some(Commands::SendReport {
+            from,
+            to,
+            config,
+            base_url,
+            theme,
+        }) => {
+            let presentation = theme::load_presentation(theme.as_deref())?;
+            email_report::send_report(&from, &to, &config, base_url, &presentation).await?;
+        }
+        Some(Commands::Serve { addr, theme }) => {
+            let presentation = theme::load_presentation(theme.as_deref())?;
+            server::serve(pool, &addr, presentation).await?;
+        }
+        Some(Commands::Schedule { list, run }) => {
+            let schedule = config::load_config()?.schedule.unwrap_or_default();
+            if list {
+                scheduler::print_jobs(&scheduler::parse_jobs(&schedule)?, &Utc::now());
+            } else if let Some(task) = run {
+                scheduler::run_locked(&pool, &schedule.lock_file, task, Utc::now().date_naive())
+                    .await?;
+            } else {
+                scheduler::run_daemon(&pool).await?;
+            }
+        }
+        Some(Commands::Config { command }) => match command {
+            ConfigCommands::AddTicker {
+                ticker,
+                list,
+                comment,
+                config,
+            } => {
+                let mut editor = ConfigEditor::open(&config)?;
+                editor.add_ticker(list, &ticker, comment.as_deref())?;
+                editor.save()?;
+                status!("✅ Added {} to {} in {}", ticker, list, config);
+            }
+            ConfigCommands::RemoveTicker { ticker, config } => {
+                let mut editor = ConfigEditor::open(&config)?;
+                let list = editor.remove_ticker(&ticker)?;
+                editor.save()?;
+                status!("✅ Removed {} from {} in {}", ticker, list, config);
             }
+            ConfigCommands::RenameTicker { old, new, config } => {
+                let mut editor = ConfigEditor::open(&config)?;
+                let list = editor.rename_ticker(&old, &new, None)?;
+                editor.save()?;
+                status!("✅ Renamed {} to {} in {} of {}", old, new, list, config);
+            }
+        },
+        Some(Commands::Discover {
+            industries,
+            min_market_cap,
+            exchanges,
+            limit,
+            append,
+            config,
+        }) => {
+            let api_key = env::var("FINANCIALMODELINGPREP_API_KEY")
+                .expect("FINANCIALMODELINGPREP_API_KEY must be set");
+            let fmp_client = api::FMPClient::new(api_key);
+            let industries = if industries.is_empty() {
+                discover::DEFAULT_INDUSTRIES
+                    .iter()
+                    .map(|i| i.to_string())
+                    .collect()
+            } else {
+                industries
+            };
+            let options = discover::DiscoverOptions {
+                industries,
+                min_market_cap,
+                exchanges,
+                limit,
+            };
+            discover::discover(&fmp_client, &options, &config, append).await?;
         }
-        Some(Commands::CompareMarketCaps { from, to }) => {
-            compare_marketcaps::compare_market_caps(&from, &to).await?;
+        Some(Commands::Reconcile {
+            date,
+            tolerance,
+            market_cap_source,
+            shares_source,
+            name_source,
+        }) => {
+            let date = match date {
+                Some(date) => chrono::NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+                    .map_err(|_| anyhow::anyhow!("Invalid date '{}', expected YYYY-MM-DD", date))?,
+                None => chrono::Local::now().date_naive() - chrono::Duration::days(1),
+            };
+            let fmp_client = api::FMPClient::new(
+                env::var("FINANCIALMODELINGPREP_API_KEY")
+                    .expect("FINANCIALMODELINGPREP_API_KEY must be set"),
+            );
+            let polygon_client = api::PolygonClient::new(
+                env::var("POLYGON_API_KEY").expect("POLYGON_API_KEY must be set"),
+            );
+            let preferred = reconciliation::PreferredSources {
+                market_cap: market_cap_source,
+                shares_outstanding: shares_source,
+                name: name_source,
+            };
+            reconciliation::reconcile_us_tickers(
+                &fmp_client,
+                &polygon_client,
+                date,
+                tolerance,
+                &preferred,
+            )
+            .await?;
         }
-        Some(Commands::GenerateCharts { from, to }) => {
-            visualizations::generate_all_charts(&from, &to).await?;
+        Some(Commands::Validate { date }) => {
+            let timestamp = validation::snapshot_timestamp(&pool, date.as_deref()).await?;
+            validation::run_validation(&pool, &[timestamp]).await?;
         }
+        Some(Commands::CorporateEvents { command }) => match command {
+            CorporateEventCommands::Check => {
+                let api_key = env::var("FINANCIALMODELINGPREP_API_KEY")
+                    .expect("FINANCIALMODELINGPREP_API_KEY must be set");
+                let fmp_client = api::FMPClient::new(api_key);
+                corporate_events::check_delistings(&pool, &fmp_client).await?;
+                corporate_events::print_events(&corporate_events::list_events(&pool).await?);
+            }
+            CorporateEventCommands::List => {
+                let events = corporate_events::list_events(&pool).await?;
+                if output::is_json() {
+                    output::emit("corporate-events-list", &Outcome::ok(events))?;
+                } else {
+                    corporate_events::print_events(&events);
+                }
+            }
+            CorporateEventCommands::Add {
+                ticker,
+                event,
+                date,
+                reason,
+            } => {
+                if corporate_events::record_event(
+                    &pool,
+                    &ticker,
+                    event,
+                    &date,
+                    reason.as_deref(),
+                    "manual",
+                )
+                .await?
+                {
+                    status!("✅ Recorded {} as {} on {}", ticker, event, date);
+                } else {
+                    status!("⚠️  {} already has a {} event", ticker, event);
+                }
+            }
+            CorporateEventCommands::Remove { ticker } => {
+                let removed = corporate_events::remove_events(&pool, &ticker).await?;
+                status!("✅ Removed {} events for {}", removed, ticker);
+            }
+        },
+        Some(Commands::Override { command }) => match command {
+            OverrideCommands::Set {
+                ticker,
+                market_cap,
+                currency,
+                name,
+                exclude,
+                from,
+                to,
+                reason,
+            } => {
+                let values = overrides::OverrideValues {
+                    market_cap,
+                    currency,
+                    name,
+                    exclude,
+                };
+                let id = overrides::set_override(
+                    &pool,
+                    &ticker,
+                    &values,
+                    &from,
+                    to.as_deref(),
+                    reason.as_deref(),
+                )
+                .await?;
+                status!(
+                    "✅ Override {} set for {} from {} to {}",
+                    id,
+                    ticker,
+                    from,
+                    to.as_deref().unwrap_or("further notice")
+                );
+            }
+            OverrideCommands::List { ticker } => {
+                let overrides = overrides::list_overrides(&pool, ticker.as_deref()).await?;
+                if output::is_json() {
+                    output::emit("override-list", &Outcome::ok(overrides))?;
+                } else {
+                    overrides::print_overrides(&overrides);
+                }
+            }
+            OverrideCommands::Remove { id } => {
+                if overrides::remove_override(&pool, id).await? {
+                    status!("✅ Removed override {}", id);
+                } else {
+                    status!("⚠️  No override with id {}", id);
+                }
+            }
+        },
         Some(Commands::CheckSymbolChanges { config }) => {
             let api_key = env::var("FINANCIALMODELINGPREP_API_KEY")
                 .or_else(|_| env::var("FMP_API_KEY"))
@@ -162,11 +708,16 @@ async fn main() -> Result<()> {
             let fmp_client = api::FMPClient::new(api_key);
 
             // Fetch and store latest symbol changes
-            symbol_changes::fetch_and_store_symbol_changes(&pool, &fmp_client).await?;
+            let stored = symbol_changes::fetch_and_store_symbol_changes(&pool, &fmp_client).await?;
 
             // Check which changes apply to our config
             let report = symbol_changes::check_ticker_updates(&pool, &config).await?;
-            symbol_changes::print_symbol_change_report(&report);
+            if output::is_json() {
+                let data = serde_json::json!({ "stored": stored, "report": report });
+                output::emit("check-symbol-changes", &Outcome::ok(data))?;
+            } else {
+                symbol_changes::print_symbol_change_report(&report);
+            }
         }
         Some(Commands::ApplySymbolChanges {
             config,
@@ -177,8 +728,8 @@ async fn main() -> Result<()> {
             let report = symbol_changes::check_ticker_updates(&pool, &config).await?;
             symbol_changes::print_symbol_change_report(&report);
 
-            if report.applicable_changes.is_empty() {
-                println!("\nNo applicable changes to apply.");
+            if report.applicable_changes.is_empty() && report.conflicts.is_empty() {
+                status!("\nNo applicable changes to apply.");
             } else if auto_apply || dry_run {
                 // Apply all applicable changes
                 symbol_changes::apply_ticker_updates(
@@ -189,20 +740,18 @@ async fn main() -> Result<()> {
                 )
                 .await?;
             } else {
-                // Interactive mode - ask user to confirm
-                println!("\nFound {} applicable changes. Run with --auto-apply to apply them or --dry-run to preview.", 
-                    report.applicable_changes.len());
+                // Interactive mode - review each change
+                symbol_changes::review_symbol_changes(&pool, &config, &report).await?;
             }
         }
         None => {
-            marketcaps::marketcaps(&pool).await?;
+            // Same as export-combined, so it must not overlap with a scheduled run either
+            let schedule = config::load_config()?.schedule.unwrap_or_default();
+            let _lock = scheduler::RunLock::acquire(&schedule.lock_file)?;
+            let outcome = marketcaps::marketcaps(&pool, ExportFormat::Csv).await?;
+            output::emit("export-combined", &outcome)?;
         }
     }
 
     Ok(())
 }
-
-#[cfg(test)]
-mod tests {
-    use super::*;
-}
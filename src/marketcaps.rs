@@ -1,61 +1,95 @@
 // SPDX-FileCopyrightText: 2025 Joost van der Laan
 // SPDX-License-Identifier: AGPL-3.0-only
 
-use crate::api;
-use crate::config;
+use crate::api::{self, MarketCapMethod};
+use crate::companies;
+use crate::config::{self, SortCurrency};
+use crate::consolidation::{self, Listing};
+use crate::corporate_events;
 use crate::currencies::{convert_currency, get_rate_map_from_db, update_currencies};
 use crate::exchange_rates;
+use crate::export_schema::{self, MarketCapRecord};
 use crate::models;
+use crate::notifications::{self, RunSummary};
+use crate::output::{status, ExportFormat, Failure, FetchSummary, Outcome};
+use crate::overrides::{self, Figures};
+use crate::provenance::{Provenance, ProvenanceRow};
+use crate::rankings::{self, Rankable};
 use crate::ticker_details::{self, TickerDetails};
+use crate::validation;
+use crate::xlsx_report::{self, RankedCompany};
 use anyhow::Result;
-use chrono::Local;
-use csv::Writer;
+use chrono::{DateTime, Local};
 use indicatif::{ProgressBar, ProgressStyle};
+use rust_xlsxwriter::Workbook;
 use sqlx::sqlite::SqlitePool;
 use std::sync::Arc;
 
-/// Store market cap data in the database
+/// Store market cap data in the database as fetched; manual overrides are applied
+/// when the data is read, so the provider's figures are never lost
 async fn store_market_cap(
     pool: &SqlitePool,
     details: &models::Details,
     rate_map: &std::collections::HashMap<String, f64>,
     timestamp: i64,
 ) -> Result<()> {
-    let original_market_cap = details.market_cap.unwrap_or(0.0) as i64;
+    let original_market_cap = details.market_cap.unwrap_or(0.0);
     let currency = details.currency_symbol.clone().unwrap_or_default();
-    let eur_market_cap =
-        convert_currency(original_market_cap as f64, &currency, "EUR", rate_map) as i64;
-    let usd_market_cap =
-        convert_currency(original_market_cap as f64, &currency, "USD", rate_map) as i64;
-    let name = details.name.as_ref().unwrap_or(&String::new()).to_string();
-    let currency_name = details
-        .currency_name
-        .as_ref()
-        .unwrap_or(&String::new())
-        .to_string();
+    let name = details.name.clone().unwrap_or_default();
+    let eur_market_cap = convert_currency(original_market_cap, &currency, "EUR", rate_map) as i64;
+    let usd_market_cap = convert_currency(original_market_cap, &currency, "USD", rate_map) as i64;
+    let original_market_cap = original_market_cap as i64;
+    // Exchange short name from the provider profile, e.g. NASDAQ or EURONEXT
+    let exchange = details
+        .extra
+        .get("exchange")
+        .and_then(|e| e.as_str())
+        .filter(|e| !e.is_empty());
     let active = details.active.unwrap_or(true);
+    let price = details.extra.get("price").and_then(|p| p.as_f64());
+
+    let provenance = Provenance::fmp(
+        pool,
+        MarketCapMethod::Profile,
+        original_market_cap as f64,
+        eur_market_cap as f64,
+        usd_market_cap as f64,
+    )
+    .await?;
 
     // Store market cap data
-    sqlx::query!(
+    sqlx::query(
         r#"
         INSERT INTO market_caps (
             ticker, name, market_cap_original, original_currency, market_cap_eur, market_cap_usd,
-            exchange, active, timestamp
-        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            exchange, price, active, market_cap_method, source_provider, source_endpoint,
+            fetched_at, fx_rate_eur, fx_rate_usd, fx_rate_timestamp, timestamp
+        ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
         "#,
-        details.ticker,
-        name,
-        original_market_cap,
-        currency,
-        eur_market_cap,
-        usd_market_cap,
-        currency_name,
-        active,
-        timestamp,
     )
+    .bind(&details.ticker)
+    .bind(&name)
+    .bind(original_market_cap)
+    .bind(&currency)
+    .bind(eur_market_cap)
+    .bind(usd_market_cap)
+    .bind(exchange)
+    .bind(price)
+    .bind(active)
+    .bind(&provenance.method)
+    .bind(&provenance.provider)
+    .bind(&provenance.endpoint)
+    .bind(provenance.fetched_at)
+    .bind(provenance.fx_rate_eur)
+    .bind(provenance.fx_rate_usd)
+    .bind(provenance.fx_rate_timestamp)
+    .bind(timestamp)
     .execute(pool)
     .await?;
 
+    // Keep the company identity in sync with the tickers we store
+    companies::ensure_company(pool, &details.ticker, Some(&name)).await?;
+
     // Store ticker details
     let ticker_details = TickerDetails {
         ticker: details.ticker.clone(),
@@ -68,72 +102,250 @@ async fn store_market_cap(
     Ok(())
 }
 
-/// Fetch market cap data from the database
-async fn get_market_caps(pool: &SqlitePool) -> Result<Vec<(f64, Vec<String>)>> {
-    let records = sqlx::query!(
+#[derive(sqlx::FromRow)]
+struct MarketCapRow {
+    ticker: String,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    exchange: Option<String>,
+    active: Option<bool>,
+    price: Option<f64>,
+    timestamp: i64,
+    description: Option<String>,
+    homepage_url: Option<String>,
+    employees: Option<i64>,
+    overridden: Option<String>,
+    #[sqlx(flatten)]
+    provenance: ProvenanceRow,
+}
+
+/// A row of the latest snapshot with overrides applied
+struct ExportRow {
+    row: MarketCapRow,
+    figures: Figures,
+    overridden: Option<String>,
+    /// Tickers merged into this row by consolidation
+    listings: Vec<String>,
+}
+
+impl Listing for ExportRow {
+    fn ticker(&self) -> &str {
+        &self.row.ticker
+    }
+
+    fn set_ticker(&mut self, ticker: &str) {
+        self.row.ticker = ticker.to_string();
+    }
+
+    fn figures(&self) -> Figures {
+        self.figures.clone()
+    }
+
+    fn set_figures(&mut self, figures: Figures) {
+        self.figures = figures;
+    }
+}
+
+impl Rankable for ExportRow {
+    fn ticker(&self) -> &str {
+        &self.row.ticker
+    }
+
+    fn exchange(&self) -> Option<&str> {
+        self.row.exchange.as_deref()
+    }
+
+    fn active(&self) -> bool {
+        self.row.active.unwrap_or(true)
+    }
+
+    fn market_cap(&self, currency: SortCurrency) -> f64 {
+        match currency {
+            SortCurrency::Eur => self.figures.market_cap_eur,
+            SortCurrency::Usd => self.figures.market_cap_usd,
+        }
+    }
+}
+
+impl ExportRow {
+    /// The row in the export schema, at `rank` in its list
+    fn record(&self, rank: usize) -> MarketCapRecord {
+        let r = &self.row;
+        MarketCapRecord {
+            market_cap_original: Some(self.figures.market_cap_original),
+            original_currency: Some(self.figures.original_currency.clone()),
+            market_cap_eur: Some(self.figures.market_cap_eur),
+            market_cap_usd: Some(self.figures.market_cap_usd),
+            price: r.price,
+            exchange: r.exchange.clone(),
+            active: Some(r.active.unwrap_or(true)),
+            description: r.description.clone(),
+            homepage_url: r.homepage_url.clone(),
+            employees: r.employees,
+            overridden: self.overridden.clone(),
+            listings: (!self.listings.is_empty()).then(|| self.listings.join(" + ")),
+            ..MarketCapRecord::new(rank, r.timestamp, &r.ticker, &self.figures.name)
+        }
+        .with_provenance(&r.provenance)
+    }
+}
+
+/// Rows as a ranked list for a workbook sheet
+fn ranked(rows: &[&ExportRow]) -> Vec<RankedCompany> {
+    rows.iter()
+        .map(|r| RankedCompany {
+            ticker: r.row.ticker.clone(),
+            name: r.figures.name.clone(),
+            market_cap_original: r.figures.market_cap_original,
+            original_currency: r.figures.original_currency.clone(),
+            market_cap_eur: r.figures.market_cap_eur,
+            market_cap_usd: r.figures.market_cap_usd,
+            exchange: r.row.exchange.clone(),
+            active: Some(r.row.active.unwrap_or(true)),
+        })
+        .collect()
+}
+
+/// Write snapshot rows in the chosen format, ranked in the given order; `sheet` names
+/// the worksheet of an xlsx file
+fn write_rows(
+    filename: &str,
+    sheet: &str,
+    rows: &[&ExportRow],
+    format: ExportFormat,
+) -> Result<()> {
+    let records: Vec<MarketCapRecord> = rows
+        .iter()
+        .enumerate()
+        .map(|(i, r)| r.record(i + 1))
+        .collect();
+    match format {
+        ExportFormat::Csv => export_schema::write_csv(std::fs::File::create(filename)?, &records),
+        ExportFormat::Parquet => export_schema::write_parquet(filename, &records),
+        ExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            xlsx_report::add_ranking_sheet(&mut workbook, sheet, &ranked(rows))?;
+            xlsx_report::save(&mut workbook, filename)
+        }
+    }
+}
+
+/// Fetch the latest snapshot from the database, sorted by EUR market cap
+async fn get_market_caps(pool: &SqlitePool) -> Result<Vec<ExportRow>> {
+    let records = sqlx::query_as::<_, MarketCapRow>(
         r#"
         SELECT 
-            m.ticker as "ticker!",
-            m.name as "name!",
+            m.ticker,
+            m.name,
             CAST(m.market_cap_original AS REAL) as market_cap_original,
             m.original_currency,
             CAST(m.market_cap_eur AS REAL) as market_cap_eur,
             CAST(m.market_cap_usd AS REAL) as market_cap_usd,
             m.exchange,
             m.active,
-            strftime('%s', m.timestamp) as timestamp,
+            CAST(m.price AS REAL) as price,
+            m.timestamp,
             td.description,
             td.homepage_url,
-            td.employees
+            td.employees,
+            m.overridden,
+            m.source_provider,
+            m.source_endpoint,
+            m.market_cap_method,
+            m.fetched_at,
+            m.fx_rate_eur,
+            m.fx_rate_usd,
+            m.fx_rate_timestamp
         FROM market_caps m
         LEFT JOIN ticker_details td ON m.ticker = td.ticker
         WHERE m.timestamp = (SELECT MAX(timestamp) FROM market_caps)
-        "#
+        "#,
     )
     .fetch_all(pool)
     .await?;
 
-    let results = records
+    // Delisted and acquired companies drop out of the rankings from their event date
+    let (latest,) = sqlx::query_as::<_, (Option<i64>,)>("SELECT MAX(timestamp) FROM market_caps")
+        .fetch_one(pool)
+        .await?;
+    let (excluded, active_overrides) = match latest {
+        Some(timestamp) => (
+            corporate_events::excluded_at(pool, timestamp).await?,
+            overrides::active_at(pool, timestamp).await?,
+        ),
+        None => Default::default(),
+    };
+    let rate_map = get_rate_map_from_db(pool).await?;
+
+    let rows: Vec<ExportRow> = records
         .into_iter()
-        .map(|r| {
-            let market_cap_eur = r.market_cap_eur.unwrap_or(0.0) as f64;
-            (
-                market_cap_eur,
-                vec![
-                    r.ticker.clone(),
-                    r.ticker,
-                    r.name,
-                    r.market_cap_original.unwrap_or(0.0).to_string(),
-                    r.original_currency.unwrap_or_default(),
-                    r.market_cap_eur.unwrap_or(0.0).to_string(),
-                    r.market_cap_usd.unwrap_or(0.0).to_string(),
-                    r.exchange.unwrap_or_default(),
-                    if r.active.unwrap_or(true) {
-                        "true".to_string()
-                    } else {
-                        "false".to_string()
-                    },
-                    r.description.unwrap_or_default(),
-                    r.homepage_url.unwrap_or_default(),
-                    r.employees.map(|e| e.to_string()).unwrap_or_default(),
-                    r.timestamp.unwrap_or_default().to_string(),
-                ],
-            )
+        .filter(|r| !excluded.contains(&r.ticker))
+        .filter(|r| !active_overrides.get(&r.ticker).is_some_and(|o| o.exclude))
+        .map(|row| {
+            let mut figures = Figures {
+                name: row.name.clone(),
+                market_cap_original: row.market_cap_original.unwrap_or(0.0),
+                original_currency: row.original_currency.clone().unwrap_or_default(),
+                market_cap_eur: row.market_cap_eur.unwrap_or(0.0),
+                market_cap_usd: row.market_cap_usd.unwrap_or(0.0),
+            };
+            let applied = active_overrides
+                .get(&row.ticker)
+                .and_then(|o| o.apply(&mut figures, &rate_map));
+            let overridden =
+                overrides::merge_markers(row.overridden.as_deref(), applied.as_deref());
+            ExportRow {
+                row,
+                figures,
+                overridden,
+                listings: Vec::new(),
+            }
         })
         .collect();
 
+    // Rank companies, not listings
+    let consolidations = config::load_config()?.consolidations;
+    let mut results: Vec<ExportRow> = consolidation::consolidate(rows, &consolidations)?
+        .into_iter()
+        .map(|(row, listings)| ExportRow { listings, ..row })
+        .collect();
+    results.sort_by(|a, b| {
+        b.figures
+            .market_cap_eur
+            .partial_cmp(&a.figures.market_cap_eur)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
     Ok(results)
 }
 
 /// Update market cap data in the database
-async fn update_market_caps(pool: &SqlitePool) -> Result<()> {
+async fn update_market_caps(pool: &SqlitePool) -> Result<RunSummary> {
     let config = config::load_config()?;
     let tickers = [config.non_us_tickers, config.us_tickers].concat();
 
+    // Skip tickers that were delisted, acquired or reported inactive; tickers that
+    // only failed to fetch are tried again so a success can bring them back
+    let today = Local::now().format("%Y-%m-%d").to_string();
+    let excluded = corporate_events::skipped_tickers(pool, &today).await?;
+    let tickers: Vec<String> = tickers
+        .into_iter()
+        .filter(|ticker| !excluded.contains(ticker))
+        .collect();
+    if !excluded.is_empty() {
+        status!(
+            "Skipping {} tickers with recorded corporate events",
+            excluded.len()
+        );
+    }
+
     // Get latest exchange rates from database
-    println!("Fetching current exchange rates from database...");
+    status!("Fetching current exchange rates from database...");
     let rate_map = get_rate_map_from_db(pool).await?;
-    println!("✅ Exchange rates fetched from database");
+    status!("✅ Exchange rates fetched from database");
 
     // Get FMP client for market data
     let api_key = std::env::var("FINANCIALMODELINGPREP_API_KEY")
@@ -157,7 +369,7 @@ async fn update_market_caps(pool: &SqlitePool) -> Result<()> {
     );
 
     // Update market cap data in database
-    println!("Updating market cap data in database...");
+    status!("Updating market cap data in database...");
     let mut failed_tickers = Vec::new();
     for ticker in &tickers {
         let rate_map = rate_map.clone();
@@ -165,14 +377,28 @@ async fn update_market_caps(pool: &SqlitePool) -> Result<()> {
 
         match fmp_client.get_details(ticker, &rate_map).await {
             Ok(details) => {
+                corporate_events::record_fetch_success(pool, ticker).await?;
+                if details.active == Some(false)
+                    && corporate_events::record_inactive_profile(pool, ticker).await?
+                {
+                    eprintln!("⚠️  {} is no longer actively trading", ticker);
+                }
                 if let Err(e) = store_market_cap(pool, &details, &rate_map, timestamp).await {
                     eprintln!("Failed to store market cap for {}: {}", ticker, e);
-                    failed_tickers.push((ticker, format!("Failed to store market cap: {}", e)));
+                    failed_tickers
+                        .push((ticker.clone(), format!("Failed to store market cap: {}", e)));
                 }
             }
             Err(e) => {
                 eprintln!("Failed to fetch details for {}: {}", ticker, e);
-                failed_tickers.push((ticker, format!("Failed to fetch details: {}", e)));
+                if corporate_events::record_fetch_failure(pool, ticker, &e).await? {
+                    eprintln!(
+                        "⚠️  {} had no data {} times in a row and is now treated as inactive",
+                        ticker,
+                        corporate_events::FAILURE_THRESHOLD
+                    );
+                }
+                failed_tickers.push((ticker.clone(), format!("Failed to fetch details: {}", e)));
             }
         }
         progress.inc(1);
@@ -181,127 +407,359 @@ async fn update_market_caps(pool: &SqlitePool) -> Result<()> {
 
     // Print summary of failed tickers
     if !failed_tickers.is_empty() {
-        println!("\nFailed to process {} tickers:", failed_tickers.len());
+        status!("\nFailed to process {} tickers:", failed_tickers.len());
         for (ticker, error) in &failed_tickers {
-            println!("  {} - {}", ticker, error);
+            status!("  {} - {}", ticker, error);
         }
     }
 
-    println!(
+    status!(
         "✅ Market cap data updated in database ({} successful, {} failed)",
         total_tickers - failed_tickers.len(),
         failed_tickers.len()
     );
 
-    Ok(())
+    Ok(RunSummary {
+        timestamp,
+        total_tickers,
+        failed_tickers,
+    })
 }
 
-/// Export market cap data to CSV
-pub async fn export_market_caps(pool: &SqlitePool) -> Result<()> {
+/// Export market cap data; returns the file written
+pub async fn export_market_caps(pool: &SqlitePool, format: ExportFormat) -> Result<String> {
     // Get market cap data from database
-    println!("Fetching market cap data from database...");
-    let mut results = get_market_caps(pool).await?;
-    println!("✅ Market cap data fetched from database");
+    status!("Fetching market cap data from database...");
+    let results = get_market_caps(pool).await?;
+    status!("✅ Market cap data fetched from database");
 
-    // Sort by EUR market cap
-    results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let filename = format!(
+        "output/combined_marketcaps_{}.{}",
+        timestamp,
+        format.extension()
+    );
+    write_rows(
+        &filename,
+        "Market Caps",
+        &results.iter().collect::<Vec<_>>(),
+        format,
+    )?;
+
+    status!("✅ Market cap data exported to {}", filename);
+    Ok(filename)
+}
+
+/// Export each ranked list configured under `[[lists]]` (by default the top 100
+/// active companies); returns the files written
+pub async fn export_lists(pool: &SqlitePool, format: ExportFormat) -> Result<Vec<String>> {
+    let results = get_market_caps(pool).await?;
+    let lists = rankings::configured_lists(&config::load_config()?.lists);
 
-    // Export to CSV
     let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("output/combined_marketcaps_{}.csv", timestamp);
-    let file = std::fs::File::create(&filename)?;
-    let mut writer = Writer::from_writer(file);
-
-    // Write headers
-    writer.write_record(&[
-        "Symbol",
-        "Ticker",
-        "Name",
-        "Market Cap (Original)",
-        "Original Currency",
-        "Market Cap (EUR)",
-        "Market Cap (USD)",
-        "Exchange",
-        "Active",
-        "Description",
-        "Homepage URL",
-        "Employees",
-        "Timestamp",
-    ])?;
-
-    // Write data
-    for (_, record) in &results {
-        writer.write_record(record)?;
+    let mut files = Vec::new();
+    for list in &lists {
+        let filename = format!(
+            "output/{}_{}.{}",
+            list.file_stem(),
+            timestamp,
+            format.extension()
+        );
+        write_rows(&filename, &list.name, &list.select(&results), format)?;
+        status!("✅ {} exported to {}", list.name, filename);
+        files.push(filename);
     }
+    Ok(files)
+}
 
-    println!("✅ Market cap data exported to {}", filename);
-    Ok(())
+/// Export each ranked list configured under `[[lists]]` as a sheet of one workbook;
+/// returns the file written
+pub async fn export_workbook(pool: &SqlitePool) -> Result<String> {
+    let results = get_market_caps(pool).await?;
+    let lists = rankings::configured_lists(&config::load_config()?.lists);
+
+    let mut workbook = Workbook::new();
+    for list in &lists {
+        xlsx_report::add_ranking_sheet(&mut workbook, &list.name, &ranked(&list.select(&results)))?;
+    }
+
+    let filename = format!(
+        "output/combined_marketcaps_{}.xlsx",
+        Local::now().format("%Y%m%d_%H%M%S")
+    );
+    xlsx_report::save(&mut workbook, &filename)?;
+
+    status!("✅ Market cap workbook exported to {}", filename);
+    Ok(filename)
 }
 
-/// Export top 100 active companies to CSV
-pub async fn export_top_100_active(pool: &SqlitePool) -> Result<()> {
-    // Get market cap data from database
-    let mut results = get_market_caps(pool).await?;
+#[derive(sqlx::FromRow)]
+struct HistoryRow {
+    ticker: String,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    exchange: Option<String>,
+    price: Option<f64>,
+    active: Option<bool>,
+    timestamp: i64,
+    overridden: Option<String>,
+    #[sqlx(flatten)]
+    provenance: ProvenanceRow,
+}
 
-    // Sort by EUR market cap
-    results.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+impl HistoryRow {
+    /// The row in the export schema, at `rank` in its snapshot
+    fn record(&self, rank: usize) -> MarketCapRecord {
+        MarketCapRecord {
+            market_cap_original: self.market_cap_original,
+            original_currency: self.original_currency.clone(),
+            market_cap_eur: self.market_cap_eur,
+            market_cap_usd: self.market_cap_usd,
+            price: self.price,
+            exchange: self.exchange.clone(),
+            active: self.active,
+            overridden: self.overridden.clone(),
+            ..MarketCapRecord::new(rank, self.timestamp, &self.ticker, &self.name)
+        }
+        .with_provenance(&self.provenance)
+    }
+}
 
-    // Filter for active companies first, then take top 100
-    let active_results: Vec<_> = results
-        .iter()
-        .filter(|(_, record)| record[8] == "true") // Active column
-        .take(100)
-        .collect();
+/// Export every stored market cap, oldest snapshot first; returns the file written
+pub async fn export_history(pool: &SqlitePool, format: ExportFormat) -> Result<String> {
+    status!("Fetching market cap history from database...");
+    let rows = sqlx::query_as::<_, HistoryRow>(
+        r#"
+        SELECT
+            ticker,
+            name,
+            CAST(market_cap_original AS REAL) as market_cap_original,
+            original_currency,
+            CAST(market_cap_eur AS REAL) as market_cap_eur,
+            CAST(market_cap_usd AS REAL) as market_cap_usd,
+            exchange,
+            CAST(price AS REAL) as price,
+            active,
+            timestamp,
+            overridden,
+            source_provider,
+            source_endpoint,
+            market_cap_method,
+            fetched_at,
+            fx_rate_eur,
+            fx_rate_usd,
+            fx_rate_timestamp
+        FROM market_caps
+        ORDER BY timestamp, market_cap_eur DESC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
 
-    // Export to CSV
-    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
-    let filename = format!("output/top_100_active_{}.csv", timestamp);
-    let file = std::fs::File::create(&filename)?;
-    let mut writer = Writer::from_writer(file);
-
-    // Write headers
-    writer.write_record(&[
-        "Symbol",
-        "Ticker",
-        "Name",
-        "Market Cap (Original)",
-        "Original Currency",
-        "Market Cap (EUR)",
-        "Market Cap (USD)",
-        "Exchange",
-        "Active",
-        "Description",
-        "Homepage URL",
-        "Employees",
-        "Timestamp",
-    ])?;
-
-    // Write data
-    for (_, record) in active_results {
-        writer.write_record(record)?;
+    std::fs::create_dir_all("output")?;
+    let filename = format!(
+        "output/market_caps_history_{}.{}",
+        Local::now().format("%Y%m%d_%H%M%S"),
+        format.extension()
+    );
+
+    // Rank each company within its snapshot
+    let mut records: Vec<MarketCapRecord> = Vec::with_capacity(rows.len());
+    for row in &rows {
+        let rank = match records.last() {
+            Some(previous) if previous.timestamp == Some(row.timestamp) => {
+                previous.rank.unwrap_or_default() + 1
+            }
+            _ => 1,
+        };
+        records.push(row.record(rank));
     }
 
-    println!("✅ Top 100 active companies exported to {}", filename);
-    Ok(())
+    match format {
+        ExportFormat::Csv => export_schema::write_csv(std::fs::File::create(&filename)?, &records)?,
+        ExportFormat::Parquet => export_schema::write_parquet(&filename, &records)?,
+        ExportFormat::Xlsx => {
+            let mut workbook = Workbook::new();
+            xlsx_report::add_table_sheet(
+                &mut workbook,
+                "History",
+                &export_schema::HEADERS,
+                &export_schema::xlsx_rows(&records),
+            )?;
+            xlsx_report::save(&mut workbook, &filename)?;
+        }
+    }
+
+    status!(
+        "✅ Market cap history ({} rows) exported to {}",
+        rows.len(),
+        filename
+    );
+    Ok(filename)
 }
 
 /// Main entry point for market cap functionality
-pub async fn marketcaps(pool: &SqlitePool) -> Result<()> {
+pub async fn marketcaps(pool: &SqlitePool, format: ExportFormat) -> Result<Outcome<FetchSummary>> {
     // First update currencies and exchange rates
     let api_key = std::env::var("FINANCIALMODELINGPREP_API_KEY")
         .expect("FINANCIALMODELINGPREP_API_KEY must be set");
     let fmp_client = api::FMPClient::new(api_key);
 
-    println!("Updating currencies and exchange rates...");
+    status!("Updating currencies and exchange rates...");
     update_currencies(&fmp_client, pool).await?;
     exchange_rates::update_exchange_rates(&fmp_client, pool).await?;
 
     // Then update market caps
-    update_market_caps(pool).await?;
+    let summary = update_market_caps(pool).await?;
+
+    // Check the new snapshot before exporting it
+    let issues = validation::run_validation(pool, &[summary.timestamp]).await?;
+    let export_allowed = validation::ensure_export_allowed(&issues);
+
+    // Export both the full list and the ranked lists
+    let mut files = Vec::new();
+    if export_allowed.is_ok() {
+        if format == ExportFormat::Xlsx {
+            files.push(export_workbook(pool).await?);
+        } else {
+            files.push(export_market_caps(pool, format).await?);
+            files.extend(export_lists(pool, format).await?);
+        }
+    }
 
-    // Export both the full list and top 100 active
-    export_market_caps(pool).await?;
-    export_top_100_active(pool).await?;
+    // Notify configured webhooks about the run
+    if let Some(notification_config) = config::load_config()?.notifications {
+        notifications::notify_run(pool, &notification_config, &summary).await;
+    }
 
-    Ok(())
+    export_allowed?;
+    let date = DateTime::from_timestamp(summary.timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    Ok(Outcome {
+        data: FetchSummary {
+            dates: vec![date],
+            stored: summary.total_tickers - summary.failed_tickers.len(),
+            issues,
+            files,
+        },
+        failures: summary
+            .failed_tickers
+            .iter()
+            .map(|(ticker, error)| Failure::new(ticker, error))
+            .collect(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RankedListConfig;
+    use serde_json::json;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_lists_filter_on_stored_exchange() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let rate_map = std::collections::HashMap::new();
+        for (ticker, exchange) in [("LULU", "NASDAQ"), ("NKE", "NYSE"), ("MC.PA", "EURONEXT")] {
+            let details: models::Details = serde_json::from_value(json!({
+                "ticker": ticker,
+                "market_cap": 1e9,
+                "name": ticker,
+                "currency_name": "USD",
+                "currency_symbol": "USD",
+                "active": true,
+                "exchange": exchange,
+                "price": 100.0,
+            }))
+            .unwrap();
+            store_market_cap(&pool, &details, &rate_map, 1_722_470_400)
+                .await
+                .unwrap();
+        }
+
+        let rows = get_market_caps(&pool).await.unwrap();
+        assert!(rows
+            .iter()
+            .any(|r| r.row.exchange.as_deref() == Some("NASDAQ")));
+
+        let us = RankedListConfig {
+            exchanges: vec!["NYSE".to_string(), "NASDAQ".to_string()],
+            ..rankings::default_lists().remove(0)
+        };
+        let mut selected: Vec<&str> = us
+            .select(&rows)
+            .iter()
+            .map(|r| r.row.ticker.as_str())
+            .collect();
+        selected.sort();
+        assert_eq!(selected, vec!["LULU", "NKE"]);
+    }
+
+    #[tokio::test]
+    async fn test_overrides_apply_when_read() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let exclude = overrides::OverrideValues {
+            exclude: true,
+            ..Default::default()
+        };
+        overrides::set_override(&pool, "NKE", &exclude, "2024-01-01", None, None)
+            .await
+            .unwrap();
+        let pin = overrides::OverrideValues {
+            market_cap: Some(2e9),
+            ..Default::default()
+        };
+        overrides::set_override(&pool, "LULU", &pin, "2024-01-01", None, None)
+            .await
+            .unwrap();
+
+        let rate_map = std::collections::HashMap::new();
+        for ticker in ["LULU", "NKE"] {
+            let details: models::Details = serde_json::from_value(json!({
+                "ticker": ticker,
+                "market_cap": 1e9,
+                "name": ticker,
+                "currency_symbol": "USD",
+            }))
+            .unwrap();
+            store_market_cap(&pool, &details, &rate_map, 1_722_470_400)
+                .await
+                .unwrap();
+        }
+
+        // The fetched figures are stored untouched
+        let stored: Vec<(String, f64)> = sqlx::query_as(
+            "SELECT ticker, CAST(market_cap_original AS REAL) FROM market_caps ORDER BY ticker",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            stored,
+            vec![("LULU".to_string(), 1e9), ("NKE".to_string(), 1e9)]
+        );
+
+        let rows = get_market_caps(&pool).await.unwrap();
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].row.ticker, "LULU");
+        assert_eq!(rows[0].overridden.as_deref(), Some("market_cap"));
+        assert_eq!(rows[0].figures.market_cap_original, 2e9);
+    }
 }
@@ -65,8 +65,8 @@ pub struct FMPCompanyProfile {
     pub currency: String,
     #[serde(rename = "exchangeShortName")]
     pub exchange: String,
-    #[serde(rename = "isActivelyTrading", default)]
-    pub is_active: bool,
+    #[serde(rename = "isActivelyTrading")]
+    pub is_active: Option<bool>,
     // Add any other fields you need from the FMP API
     #[serde(flatten)]
     pub extra: std::collections::HashMap<String, Value>,
@@ -99,6 +99,7 @@ pub struct FMPIncomeStatement {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[allow(dead_code)]
 pub struct Stock {
     pub ticker: String,
     pub name: String,
@@ -207,7 +208,7 @@ mod tests {
         assert_eq!(profile.price, 150.0);
         assert_eq!(profile.currency, "USD");
         assert_eq!(profile.exchange, "NASDAQ");
-        assert_eq!(profile.is_active, true);
+        assert_eq!(profile.is_active, Some(true));
     }
 
     #[test]
@@ -274,7 +275,7 @@ mod tests {
         assert_eq!(deserialized.ticker, "AAPL");
         assert_eq!(deserialized.market_cap, 2000000000000.0);
         assert_eq!(deserialized.currency_symbol, "USD");
-        assert_eq!(deserialized.active, true);
+        assert!(deserialized.active);
         assert_eq!(deserialized.revenue, 365000000000.0);
         assert_eq!(deserialized.eps, 6.05);
     }
@@ -4,7 +4,11 @@
 
 use crate::api;
 use crate::config;
-use crate::currencies::{convert_currency, get_rate_map_from_db};
+use crate::currencies::get_rate_map_from_db;
+use crate::historical_marketcaps::store_historical_market_cap;
+use crate::output::{status, Failure, FetchSummary, Outcome};
+use crate::trading_calendar::TradingCalendar;
+use crate::validation;
 use anyhow::Result;
 use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use sqlx::sqlite::SqlitePool;
@@ -15,8 +19,44 @@ pub async fn fetch_monthly_historical_marketcaps(
     pool: &SqlitePool,
     start_year: i32,
     end_year: i32,
-) -> Result<()> {
+) -> Result<Outcome<FetchSummary>> {
+    status!(
+        "Fetching monthly historical market caps from {} to {}",
+        start_year,
+        end_year
+    );
+
+    // Skip future months in the current year
+    let now = Utc::now();
+    let months: Vec<(i32, u32)> = (start_year..=end_year)
+        .flat_map(|year| (1..=12).map(move |month| (year, month)))
+        .filter(|&(year, month)| !(year == now.year() && month > now.month()))
+        .collect();
+    fetch_month_ends(pool, &months).await
+}
+
+/// Fetches historical market caps for the last day of one month, stored under the same
+/// timestamp as the monthly fetch so both fill the same snapshot
+pub async fn fetch_month_end_marketcaps(
+    pool: &SqlitePool,
+    year: i32,
+    month: u32,
+) -> Result<Outcome<FetchSummary>> {
+    fetch_month_ends(pool, &[(year, month)]).await
+}
+
+/// The last day of a month at 23:59 UTC, the time month-end snapshots are stored at
+pub fn month_end_datetime(year: i32, month: u32) -> NaiveDateTime {
+    let time = NaiveTime::from_hms_opt(23, 59, 0).unwrap();
+    NaiveDateTime::new(get_last_day_of_month(year, month), time)
+}
+
+async fn fetch_month_ends(
+    pool: &SqlitePool,
+    months: &[(i32, u32)],
+) -> Result<Outcome<FetchSummary>> {
     let config = config::load_config()?;
+    let calendar = TradingCalendar::from_config(config.calendar.as_ref())?;
     let tickers = [config.non_us_tickers, config.us_tickers].concat();
 
     // Get FMP client for market data
@@ -24,96 +64,60 @@ pub async fn fetch_monthly_historical_marketcaps(
         .expect("FINANCIALMODELINGPREP_API_KEY must be set");
     let fmp_client = Arc::new(api::FMPClient::new(api_key));
 
-    println!(
-        "Fetching monthly historical market caps from {} to {}",
-        start_year, end_year
-    );
-
-    for year in start_year..=end_year {
-        for month in 1..=12 {
-            // Skip future months in the current year
-            if year == Utc::now().year() && month > Utc::now().month() {
-                break;
-            }
-
-            // Get the last day of the month at 23:59
-            let last_day = get_last_day_of_month(year, month);
-            let time = NaiveTime::from_hms_opt(23, 59, 0).unwrap();
-            let naive_dt = NaiveDateTime::new(last_day, time);
-            let datetime_utc = naive_dt.and_utc();
-
-            println!("Fetching exchange rates for {}", naive_dt);
-            let rate_map = get_rate_map_from_db(pool).await?;
-
-            for ticker in &tickers {
-                match fmp_client
-                    .get_historical_market_cap(ticker, &datetime_utc)
-                    .await
-                {
-                    Ok(market_cap) => {
-                        // Convert currencies if needed
-                        let market_cap_eur = convert_currency(
-                            market_cap.market_cap_original,
-                            &market_cap.original_currency,
-                            "EUR",
-                            &rate_map,
-                        );
-
-                        let market_cap_usd = convert_currency(
-                            market_cap.market_cap_original,
-                            &market_cap.original_currency,
-                            "USD",
-                            &rate_map,
-                        );
-
-                        // Store the Unix timestamp of the historical date
-                        let timestamp = naive_dt.and_utc().timestamp();
-
-                        // Insert into database
-                        sqlx::query!(
-                            r#"
-                            INSERT INTO market_caps (
-                                ticker, name, market_cap_original, original_currency,
-                                market_cap_eur, market_cap_usd, exchange, price,
-                                active, timestamp
-                            )
-                            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                            "#,
-                            ticker,
-                            market_cap.name,
-                            market_cap.market_cap_original,
-                            market_cap.original_currency,
-                            market_cap_eur,
-                            market_cap_usd,
-                            market_cap.exchange,
-                            market_cap.price,
-                            true,
-                            timestamp,
-                        )
-                        .execute(pool)
+    let mut timestamps = Vec::new();
+    let mut summary = FetchSummary::default();
+    let mut failures = Vec::new();
+    for &(year, month) in months {
+        // Get the last day of the month at 23:59
+        let naive_dt = month_end_datetime(year, month);
+        let last_day = naive_dt.date();
+        let datetime_utc = naive_dt.and_utc();
+
+        status!("Fetching exchange rates for {}", naive_dt);
+        let rate_map = get_rate_map_from_db(pool).await?;
+        timestamps.push(datetime_utc.timestamp());
+        summary.dates.push(last_day.to_string());
+        calendar.print_shifted(&tickers, naive_dt.date());
+
+        for ticker in &tickers {
+            match fmp_client
+                .get_historical_market_cap(ticker, &calendar.request_datetime(ticker, &naive_dt))
+                .await
+            {
+                Ok(market_cap) => {
+                    // Store the Unix timestamp of the historical date
+                    let timestamp = naive_dt.and_utc().timestamp();
+                    store_historical_market_cap(pool, ticker, &market_cap, &rate_map, timestamp)
                         .await?;
+                    summary.stored += 1;
 
-                        println!(
-                            "✅ Added historical market cap for {} on {}",
-                            ticker, naive_dt
-                        );
-                    }
-                    Err(e) => {
-                        eprintln!(
-                            "❌ Failed to fetch market cap for {} on {}: {}",
-                            ticker, naive_dt, e
-                        );
-                    }
+                    status!(
+                        "✅ Added historical market cap for {} on {}",
+                        ticker,
+                        naive_dt
+                    );
+                }
+                Err(e) => {
+                    eprintln!(
+                        "❌ Failed to fetch market cap for {} on {}: {}",
+                        ticker, naive_dt, e
+                    );
+                    failures.push(Failure::new(ticker, e).on(last_day));
                 }
             }
         }
     }
 
-    Ok(())
+    summary.issues = validation::run_validation(pool, &timestamps).await?;
+
+    Ok(Outcome {
+        data: summary,
+        failures,
+    })
 }
 
 /// Helper function to get the last day of a given month
-fn get_last_day_of_month(year: i32, month: u32) -> NaiveDate {
+pub fn get_last_day_of_month(year: i32, month: u32) -> NaiveDate {
     let first_day_next_month = if month == 12 {
         NaiveDate::from_ymd_opt(year + 1, 1, 1).unwrap()
     } else {
@@ -0,0 +1,364 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use reqwest::Client;
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::time::sleep;
+
+use crate::config::{self, NotificationConfig, WebhookConfig};
+use crate::currencies::get_rate_map_from_db;
+use crate::output::status;
+use crate::overrides::{self, Figures, ManualOverride};
+use crate::symbol_changes::{self, StoredSymbolChange};
+
+/// Outcome of a market cap update run
+#[derive(Debug)]
+pub struct RunSummary {
+    pub timestamp: i64,
+    pub total_tickers: usize,
+    pub failed_tickers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedTicker {
+    pub ticker: String,
+    pub error: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Mover {
+    pub ticker: String,
+    pub name: String,
+    pub market_cap_usd_previous: f64,
+    pub market_cap_usd_current: f64,
+    pub percentage_change: f64,
+}
+
+/// JSON body posted to every webhook after a run
+#[derive(Debug, Serialize)]
+pub struct RunNotification {
+    pub event: &'static str,
+    pub timestamp: i64,
+    pub total_tickers: usize,
+    pub successful: usize,
+    pub failed: usize,
+    pub failed_tickers: Vec<FailedTicker>,
+    pub biggest_movers: Vec<Mover>,
+    pub pending_symbol_changes: Vec<StoredSymbolChange>,
+}
+
+/// USD market cap of a stored row as exported: None when an override excludes the
+/// ticker, the pinned value when one is set
+fn exported_usd(
+    active_overrides: &HashMap<String, ManualOverride>,
+    ticker: &str,
+    (original, currency, usd): (f64, String, f64),
+    rate_map: &HashMap<String, f64>,
+) -> Option<f64> {
+    let mut figures = Figures {
+        name: String::new(),
+        market_cap_original: original,
+        original_currency: currency,
+        market_cap_eur: 0.0,
+        market_cap_usd: usd,
+    };
+    if let Some(o) = active_overrides.get(ticker) {
+        if o.exclude {
+            return None;
+        }
+        o.apply(&mut figures, rate_map);
+    }
+    Some(figures.market_cap_usd)
+}
+
+/// Largest USD market cap changes between the run's snapshot and the one before it
+async fn biggest_movers(pool: &SqlitePool, timestamp: i64, limit: usize) -> Result<Vec<Mover>> {
+    let previous_timestamp: Option<i64> =
+        sqlx::query_scalar("SELECT MAX(timestamp) FROM market_caps WHERE timestamp < ?")
+            .bind(timestamp)
+            .fetch_one(pool)
+            .await?;
+    let Some(previous_timestamp) = previous_timestamp else {
+        return Ok(Vec::new());
+    };
+
+    let rows = sqlx::query_as::<
+        _,
+        (
+            String,
+            String,
+            Option<f64>,
+            Option<String>,
+            Option<f64>,
+            Option<f64>,
+            Option<String>,
+            Option<f64>,
+        ),
+    >(
+        r#"
+        SELECT c.ticker, c.name,
+            CAST(p.market_cap_original AS REAL), p.original_currency,
+            CAST(p.market_cap_usd AS REAL),
+            CAST(c.market_cap_original AS REAL), c.original_currency,
+            CAST(c.market_cap_usd AS REAL)
+        FROM market_caps c
+        JOIN market_caps p ON p.ticker = c.ticker AND p.timestamp = ?
+        WHERE c.timestamp = ?
+        "#,
+    )
+    .bind(previous_timestamp)
+    .bind(timestamp)
+    .fetch_all(pool)
+    .await?;
+
+    // Compare the figures as exported, with overrides applied
+    let rate_map = get_rate_map_from_db(pool).await?;
+    let previous_overrides = overrides::active_at(pool, previous_timestamp).await?;
+    let current_overrides = overrides::active_at(pool, timestamp).await?;
+
+    let mut movers: Vec<Mover> = rows
+        .into_iter()
+        .filter_map(
+            |(ticker, name, p_orig, p_currency, p_usd, c_orig, c_currency, c_usd)| {
+                let previous = (p_orig?, p_currency.unwrap_or_default(), p_usd?);
+                let current = (c_orig?, c_currency.unwrap_or_default(), c_usd?);
+                let previous = exported_usd(&previous_overrides, &ticker, previous, &rate_map)?;
+                let current = exported_usd(&current_overrides, &ticker, current, &rate_map)?;
+                if previous <= 0.0 {
+                    return None;
+                }
+                Some(Mover {
+                    ticker,
+                    name,
+                    market_cap_usd_previous: previous,
+                    market_cap_usd_current: current,
+                    percentage_change: (current - previous) / previous * 100.0,
+                })
+            },
+        )
+        .collect();
+
+    movers.sort_by(|a, b| {
+        b.percentage_change
+            .abs()
+            .partial_cmp(&a.percentage_change.abs())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    movers.truncate(limit);
+
+    Ok(movers)
+}
+
+/// Build the notification payload for a finished run
+async fn build_notification(
+    pool: &SqlitePool,
+    config: &NotificationConfig,
+    summary: &RunSummary,
+) -> Result<RunNotification> {
+    let biggest_movers = biggest_movers(pool, summary.timestamp, config.top_movers).await?;
+
+    let config_path = config::get_config_path();
+    let pending_symbol_changes =
+        symbol_changes::check_ticker_updates(pool, &config_path.to_string_lossy())
+            .await?
+            .applicable_changes;
+
+    Ok(RunNotification {
+        event: "run_completed",
+        timestamp: summary.timestamp,
+        total_tickers: summary.total_tickers,
+        successful: summary.total_tickers - summary.failed_tickers.len(),
+        failed: summary.failed_tickers.len(),
+        failed_tickers: summary
+            .failed_tickers
+            .iter()
+            .map(|(ticker, error)| FailedTicker {
+                ticker: ticker.clone(),
+                error: error.clone(),
+            })
+            .collect(),
+        biggest_movers,
+        pending_symbol_changes,
+    })
+}
+
+/// POST the payload to a webhook, retrying with exponential backoff
+async fn send_with_retry<T: Serialize>(
+    client: &Client,
+    webhook: &WebhookConfig,
+    payload: &T,
+    max_retries: u32,
+    retry_delay: Duration,
+) -> Result<()> {
+    let mut retries = 0;
+    let mut delay = retry_delay;
+
+    loop {
+        let mut request = client.post(&webhook.url).json(payload);
+        for (name, value) in &webhook.headers {
+            request = request.header(name, value);
+        }
+
+        let error = match request.send().await {
+            Ok(response) if response.status().is_success() => return Ok(()),
+            Ok(response) => anyhow::anyhow!("webhook returned {}", response.status()),
+            Err(e) => e.into(),
+        };
+
+        if retries >= max_retries {
+            return Err(error.context(format!(
+                "Giving up on {} after {} retries",
+                webhook.name, retries
+            )));
+        }
+
+        retries += 1;
+        eprintln!(
+            "Webhook {} failed ({}), retrying in {:?} (attempt {}/{})",
+            webhook.name, error, delay, retries, max_retries
+        );
+        sleep(delay).await;
+        delay *= 2;
+    }
+}
+
+/// Write the payload that would have been sent to a webhook
+fn write_dry_run<T: Serialize>(dir: &str, webhook: &WebhookConfig, payload: &T) -> Result<PathBuf> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir))?;
+
+    let safe_name: String = webhook
+        .name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let timestamp = Local::now().format("%Y%m%d_%H%M%S");
+    let path = Path::new(dir).join(format!("{}_{}.json", safe_name, timestamp));
+
+    std::fs::write(&path, serde_json::to_string_pretty(payload)?)?;
+
+    Ok(path)
+}
+
+/// Notify all configured webhooks about a finished run; failures are reported but not fatal
+pub async fn notify_run(pool: &SqlitePool, config: &NotificationConfig, summary: &RunSummary) {
+    if config.webhooks.is_empty() {
+        return;
+    }
+
+    let payload = match build_notification(pool, config, summary).await {
+        Ok(payload) => payload,
+        Err(e) => {
+            eprintln!("⚠️  Failed to build run notification: {:#}", e);
+            return;
+        }
+    };
+
+    let client = Client::new();
+    for webhook in &config.webhooks {
+        if config.dry_run {
+            match write_dry_run(&config.dry_run_dir, webhook, &payload) {
+                Ok(path) => status!(
+                    "📝 Dry run: notification for {} written to {}",
+                    webhook.name,
+                    path.display()
+                ),
+                Err(e) => eprintln!("⚠️  Failed to write notification payload: {:#}", e),
+            }
+            continue;
+        }
+
+        match send_with_retry(
+            &client,
+            webhook,
+            &payload,
+            config.max_retries,
+            Duration::from_secs(config.retry_delay_secs),
+        )
+        .await
+        {
+            Ok(()) => status!("✅ Notified {}", webhook.name),
+            Err(e) => eprintln!("⚠️  Failed to notify {}: {:#}", webhook.name, e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::StatusCode, routing::post, Router};
+    use std::collections::BTreeMap;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn webhook(url: String) -> WebhookConfig {
+        WebhookConfig {
+            name: "test hook".to_string(),
+            url,
+            headers: BTreeMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_send_with_retry_recovers_from_failure() {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = attempts.clone();
+        let app = Router::new().route(
+            "/hook",
+            post(move || {
+                let counter = counter.clone();
+                async move {
+                    // Fail the first attempt only
+                    if counter.fetch_add(1, Ordering::SeqCst) == 0 {
+                        StatusCode::INTERNAL_SERVER_ERROR
+                    } else {
+                        StatusCode::OK
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let hook = webhook(format!("http://{}/hook", addr));
+        let payload = serde_json::json!({ "event": "run_completed" });
+
+        send_with_retry(&Client::new(), &hook, &payload, 2, Duration::ZERO)
+            .await
+            .unwrap();
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        // With no retries allowed a failing endpoint is reported as an error
+        attempts.store(0, Ordering::SeqCst);
+        assert!(
+            send_with_retry(&Client::new(), &hook, &payload, 0, Duration::ZERO)
+                .await
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_write_dry_run() {
+        let dir = tempfile::tempdir().unwrap();
+        let hook = webhook("http://unused".to_string());
+        let payload = serde_json::json!({ "failed": 2 });
+
+        let path = write_dry_run(dir.path().to_str().unwrap(), &hook, &payload).unwrap();
+
+        assert!(path
+            .file_name()
+            .unwrap()
+            .to_string_lossy()
+            .starts_with("test_hook_"));
+        let written: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+        assert_eq!(written["failed"], 2);
+    }
+}
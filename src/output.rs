@@ -0,0 +1,225 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::validation::Issue;
+
+/// Exit code of a command that ran but failed for some of its tickers
+pub const PARTIAL_FAILURE_EXIT_CODE: i32 = 2;
+
+/// How commands print their results
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable progress and results
+    #[default]
+    Text,
+    /// A single JSON document on stdout; progress goes to stderr
+    Json,
+}
+
+/// File format of the exports written to `output/`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    /// Typed columns for notebooks: numbers, booleans, dates and timestamps
+    Parquet,
+    /// A formatted Excel workbook with one sheet per list and charts
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+static JSON: AtomicBool = AtomicBool::new(false);
+
+/// Select the output format for the rest of the process
+pub fn init(format: OutputFormat) {
+    JSON.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+/// Print a progress line to stdout, or to stderr when stdout carries JSON
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::output::is_json() {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+pub(crate) use status;
+
+/// A ticker, or a file for commands that read files, a command could not process
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Failure {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub ticker: String,
+    /// Snapshot date (YYYY-MM-DD) for commands that fetch several dates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    pub error: String,
+}
+
+impl Failure {
+    pub fn new(ticker: &str, error: impl ToString) -> Self {
+        Self {
+            ticker: ticker.to_string(),
+            date: None,
+            file: None,
+            error: error.to_string(),
+        }
+    }
+
+    /// A file that could not be read
+    pub fn in_file(file: &str, error: impl ToString) -> Self {
+        Self {
+            file: Some(file.to_string()),
+            ..Self::new("", error)
+        }
+    }
+
+    pub fn on(self, date: impl ToString) -> Self {
+        Self {
+            date: Some(date.to_string()),
+            ..self
+        }
+    }
+}
+
+/// The result of a command together with the tickers it failed for
+#[derive(Debug)]
+pub struct Outcome<T> {
+    pub data: T,
+    pub failures: Vec<Failure>,
+}
+
+impl<T> Outcome<T> {
+    pub fn ok(data: T) -> Self {
+        Self {
+            data,
+            failures: Vec::new(),
+        }
+    }
+}
+
+/// What a fetch command stored and exported
+#[derive(Debug, Default, Serialize)]
+pub struct FetchSummary {
+    /// Snapshot dates (YYYY-MM-DD) that were fetched
+    pub dates: Vec<String>,
+    /// Number of market caps stored
+    pub stored: usize,
+    /// Data quality issues found in the fetched snapshots
+    pub issues: Vec<Issue>,
+    /// Files written to `output/`
+    pub files: Vec<String>,
+}
+
+/// Returned when a command failed for some tickers so `main` can exit with
+/// [`PARTIAL_FAILURE_EXIT_CODE`]
+#[derive(Debug)]
+pub struct PartialFailure {
+    pub failed: usize,
+}
+
+impl std::fmt::Display for PartialFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Completed with {} failures", self.failed)
+    }
+}
+
+impl std::error::Error for PartialFailure {}
+
+#[derive(Serialize)]
+struct Document<'a, T> {
+    command: &'a str,
+    status: &'static str,
+    data: &'a T,
+    failures: &'a [Failure],
+}
+
+fn render<T: Serialize>(command: &str, outcome: &Outcome<T>) -> Result<String> {
+    let document = Document {
+        command,
+        status: if outcome.failures.is_empty() {
+            "ok"
+        } else {
+            "partial_failure"
+        },
+        data: &outcome.data,
+        failures: &outcome.failures,
+    };
+    Ok(serde_json::to_string_pretty(&document)?)
+}
+
+/// Print the outcome of a command as JSON when that output was requested.
+///
+/// A command that failed for some tickers returns [`PartialFailure`] in either output
+/// mode, so callers can tell a partial run from a complete one by its exit status;
+/// text mode has already reported the failures as they happened.
+pub fn emit<T: Serialize>(command: &str, outcome: &Outcome<T>) -> Result<()> {
+    if is_json() {
+        println!("{}", render(command, outcome)?);
+    }
+    if !outcome.failures.is_empty() {
+        return Err(PartialFailure {
+            failed: outcome.failures.len(),
+        }
+        .into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_outcome() {
+        let mut outcome = Outcome::ok(FetchSummary {
+            dates: vec!["2025-08-29".to_string()],
+            stored: 1,
+            ..Default::default()
+        });
+        let json: serde_json::Value =
+            serde_json::from_str(&render("fetch-specific-date-market-caps", &outcome).unwrap())
+                .unwrap();
+        assert_eq!(json["status"], "ok");
+        assert_eq!(json["data"]["dates"][0], "2025-08-29");
+        assert_eq!(json["failures"].as_array().unwrap().len(), 0);
+
+        outcome
+            .failures
+            .push(Failure::new("MC.PA", "HTTP 429").on("2025-08-29"));
+        outcome.failures.push(Failure::new("NKE", "HTTP 500"));
+        let json: serde_json::Value =
+            serde_json::from_str(&render("fetch", &outcome).unwrap()).unwrap();
+        assert_eq!(json["status"], "partial_failure");
+        assert_eq!(json["failures"][0]["date"], "2025-08-29");
+        assert!(json["failures"][1].get("date").is_none());
+
+        // Partial failures are reported in text mode too
+        assert!(!is_json());
+        let error = emit("fetch", &outcome).unwrap_err();
+        assert_eq!(error.downcast_ref::<PartialFailure>().unwrap().failed, 2);
+    }
+}
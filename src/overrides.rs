@@ -0,0 +1,366 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+
+use crate::currencies::convert_currency;
+
+/// Export column listing the overridden fields of a row
+pub const CSV_HEADER: &str = "Overridden";
+
+/// Values pinned by an override; unset fields keep the fetched value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OverrideValues {
+    /// Market cap in `currency`, or in the fetched currency if none is pinned
+    pub market_cap: Option<f64>,
+    pub currency: Option<String>,
+    pub name: Option<String>,
+    /// Leave the ticker out of the rankings altogether
+    pub exclude: bool,
+}
+
+#[derive(Debug, Clone, Serialize, sqlx::FromRow)]
+pub struct ManualOverride {
+    pub id: i64,
+    pub ticker: String,
+    pub market_cap: Option<f64>,
+    pub currency: Option<String>,
+    pub name: Option<String>,
+    pub exclude: bool,
+    pub start_date: String,
+    /// Last day the override applies; open-ended when missing
+    pub end_date: Option<String>,
+    pub reason: Option<String>,
+}
+
+/// The figures of a market cap row an override can change
+#[derive(Debug, Clone, PartialEq)]
+pub struct Figures {
+    pub name: String,
+    pub market_cap_original: f64,
+    pub original_currency: String,
+    pub market_cap_eur: f64,
+    pub market_cap_usd: f64,
+}
+
+impl ManualOverride {
+    /// Combine with a newer override for the same ticker; the newer one wins per field
+    fn merge(self, newer: ManualOverride) -> ManualOverride {
+        ManualOverride {
+            market_cap: newer.market_cap.or(self.market_cap),
+            currency: newer.currency.or(self.currency),
+            name: newer.name.or(self.name),
+            exclude: newer.exclude || self.exclude,
+            ..newer
+        }
+    }
+
+    /// Pin the overridden figures, converting a pinned market cap with `rate_map`.
+    /// Returns the marker listing the changed fields, or None if nothing changed.
+    pub fn apply(&self, figures: &mut Figures, rate_map: &HashMap<String, f64>) -> Option<String> {
+        let mut fields = Vec::new();
+
+        if let Some(name) = &self.name {
+            figures.name = name.clone();
+            fields.push("name");
+        }
+        if let Some(currency) = &self.currency {
+            figures.original_currency = currency.clone();
+            fields.push("currency");
+        }
+        if let Some(market_cap) = self.market_cap {
+            figures.market_cap_original = market_cap;
+            fields.push("market_cap");
+        }
+        if self.market_cap.is_some() || self.currency.is_some() {
+            figures.market_cap_eur = convert_currency(
+                figures.market_cap_original,
+                &figures.original_currency,
+                "EUR",
+                rate_map,
+            );
+            figures.market_cap_usd = convert_currency(
+                figures.market_cap_original,
+                &figures.original_currency,
+                "USD",
+                rate_map,
+            );
+        }
+
+        (!fields.is_empty()).then(|| fields.join(", "))
+    }
+}
+
+/// Union of two override markers, e.g. the stored one and one applied at export time
+pub fn merge_markers(a: Option<&str>, b: Option<&str>) -> Option<String> {
+    let mut fields: Vec<&str> = Vec::new();
+    for field in [a, b]
+        .into_iter()
+        .flatten()
+        .flat_map(|marker| marker.split(", "))
+        .filter(|field| !field.is_empty())
+    {
+        if !fields.contains(&field) {
+            fields.push(field);
+        }
+    }
+    (!fields.is_empty()).then(|| fields.join(", "))
+}
+
+fn parse_date(date: &str) -> Result<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|_| anyhow::anyhow!("Invalid date '{}', expected YYYY-MM-DD", date))
+}
+
+/// Pin values for a ticker from `start_date` through `end_date` (inclusive); returns the
+/// id of the new override
+pub async fn set_override(
+    pool: &SqlitePool,
+    ticker: &str,
+    values: &OverrideValues,
+    start_date: &str,
+    end_date: Option<&str>,
+    reason: Option<&str>,
+) -> Result<i64> {
+    let start = parse_date(start_date)?;
+    if let Some(end_date) = end_date {
+        if parse_date(end_date)? < start {
+            anyhow::bail!("End date {} is before start date {}", end_date, start_date);
+        }
+    }
+    if values.market_cap.is_none()
+        && values.currency.is_none()
+        && values.name.is_none()
+        && !values.exclude
+    {
+        anyhow::bail!("Nothing to override: give a market cap, currency, name or --exclude");
+    }
+    if values.market_cap.is_some_and(|m| m <= 0.0) {
+        anyhow::bail!("Market cap must be positive");
+    }
+
+    let result = sqlx::query(
+        r#"
+        INSERT INTO manual_overrides
+            (ticker, market_cap, currency, name, exclude, start_date, end_date, reason)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+        "#,
+    )
+    .bind(ticker)
+    .bind(values.market_cap)
+    .bind(&values.currency)
+    .bind(&values.name)
+    .bind(values.exclude)
+    .bind(start_date)
+    .bind(end_date)
+    .bind(reason)
+    .execute(pool)
+    .await?;
+
+    Ok(result.last_insert_rowid())
+}
+
+/// Overrides, optionally of one ticker, ordered by ticker and start date
+pub async fn list_overrides(
+    pool: &SqlitePool,
+    ticker: Option<&str>,
+) -> Result<Vec<ManualOverride>> {
+    let overrides = sqlx::query_as::<_, ManualOverride>(
+        r#"
+        SELECT id, ticker, market_cap, currency, name, exclude, start_date, end_date, reason
+        FROM manual_overrides
+        WHERE ? IS NULL OR ticker = ?
+        ORDER BY ticker, start_date, id
+        "#,
+    )
+    .bind(ticker)
+    .bind(ticker)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(overrides)
+}
+
+/// Remove an override by id; returns false if there was none
+pub async fn remove_override(pool: &SqlitePool, id: i64) -> Result<bool> {
+    let result = sqlx::query("DELETE FROM manual_overrides WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Overrides in effect on a YYYY-MM-DD date, merged per ticker
+pub async fn active_overrides(
+    pool: &SqlitePool,
+    date: &str,
+) -> Result<HashMap<String, ManualOverride>> {
+    let rows = sqlx::query_as::<_, ManualOverride>(
+        r#"
+        SELECT id, ticker, market_cap, currency, name, exclude, start_date, end_date, reason
+        FROM manual_overrides
+        WHERE start_date <= ? AND (end_date IS NULL OR end_date >= ?)
+        ORDER BY id
+        "#,
+    )
+    .bind(date)
+    .bind(date)
+    .fetch_all(pool)
+    .await?;
+
+    let mut active: HashMap<String, ManualOverride> = HashMap::new();
+    for row in rows {
+        let merged = match active.remove(&row.ticker) {
+            Some(older) => older.merge(row),
+            None => row,
+        };
+        active.insert(merged.ticker.clone(), merged);
+    }
+    Ok(active)
+}
+
+/// Overrides in effect for the snapshot taken at a unix timestamp
+pub async fn active_at(
+    pool: &SqlitePool,
+    timestamp: i64,
+) -> Result<HashMap<String, ManualOverride>> {
+    let date = DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default();
+    active_overrides(pool, &date).await
+}
+
+/// Print overrides as a table
+pub fn print_overrides(overrides: &[ManualOverride]) {
+    if overrides.is_empty() {
+        println!("No manual overrides recorded.");
+        return;
+    }
+
+    println!(
+        "{:<5} {:<12} {:<12} {:<12} {:<30} Reason",
+        "ID", "Ticker", "From", "To", "Override"
+    );
+    for o in overrides {
+        let mut pinned = Vec::new();
+        match (o.market_cap, &o.currency) {
+            (Some(market_cap), Some(currency)) => {
+                pinned.push(format!("market cap {} {}", market_cap, currency))
+            }
+            (Some(market_cap), None) => pinned.push(format!("market cap {}", market_cap)),
+            (None, Some(currency)) => pinned.push(format!("currency {}", currency)),
+            (None, None) => {}
+        }
+        if let Some(name) = &o.name {
+            pinned.push(format!("name \"{}\"", name));
+        }
+        if o.exclude {
+            pinned.push("excluded".to_string());
+        }
+        println!(
+            "{:<5} {:<12} {:<12} {:<12} {:<30} {}",
+            o.id,
+            o.ticker,
+            o.start_date,
+            o.end_date.as_deref().unwrap_or("open"),
+            pinned.join(", "),
+            o.reason.as_deref().unwrap_or("")
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_active_overrides_merge_and_apply() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let pinned = OverrideValues {
+            market_cap: Some(30e9),
+            currency: Some("EUR".to_string()),
+            ..Default::default()
+        };
+        set_override(
+            &pool,
+            "CDI.PA",
+            &pinned,
+            "2024-01-01",
+            Some("2024-12-31"),
+            None,
+        )
+        .await
+        .unwrap();
+        let renamed = OverrideValues {
+            name: Some("Christian Dior".to_string()),
+            ..Default::default()
+        };
+        set_override(
+            &pool,
+            "CDI.PA",
+            &renamed,
+            "2024-06-01",
+            None,
+            Some("Holding company"),
+        )
+        .await
+        .unwrap();
+        assert!(set_override(
+            &pool,
+            "CDI.PA",
+            &OverrideValues::default(),
+            "2024-01-01",
+            None,
+            None
+        )
+        .await
+        .is_err());
+
+        assert!(active_overrides(&pool, "2023-12-31")
+            .await
+            .unwrap()
+            .is_empty());
+        let active = active_overrides(&pool, "2024-07-01").await.unwrap();
+        let o = &active["CDI.PA"];
+        assert_eq!(o.market_cap, Some(30e9));
+        assert_eq!(o.name.as_deref(), Some("Christian Dior"));
+        let active = active_overrides(&pool, "2025-01-01").await.unwrap();
+        assert_eq!(active["CDI.PA"].market_cap, None);
+
+        let rate_map = HashMap::from([("EUR/USD".to_string(), 1.1)]);
+        let mut figures = Figures {
+            name: "Christian Dior SE".to_string(),
+            market_cap_original: 120e9,
+            original_currency: "EUR".to_string(),
+            market_cap_eur: 120e9,
+            market_cap_usd: 132e9,
+        };
+        let o = &active_overrides(&pool, "2024-07-01").await.unwrap()["CDI.PA"];
+        let marker = o.apply(&mut figures, &rate_map);
+        assert_eq!(marker.as_deref(), Some("name, currency, market_cap"));
+        assert_eq!(figures.market_cap_eur, 30e9);
+        assert!((figures.market_cap_usd - 33e9).abs() < 1.0);
+
+        assert_eq!(
+            merge_markers(Some("market_cap"), Some("name, market_cap")).as_deref(),
+            Some("market_cap, name")
+        );
+        assert_eq!(merge_markers(Some(""), None), None);
+
+        let id = list_overrides(&pool, Some("CDI.PA")).await.unwrap()[0].id;
+        assert!(remove_override(&pool, id).await.unwrap());
+        assert!(!remove_override(&pool, id).await.unwrap());
+    }
+}
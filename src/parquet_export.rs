@@ -0,0 +1,158 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use arrow_array::{
+    ArrayRef, BooleanArray, Date32Array, Decimal128Array, Float64Array, Int64Array, RecordBatch,
+    StringArray, TimestampSecondArray,
+};
+use arrow_schema::{Field, Schema};
+use chrono::NaiveDate;
+use parquet::arrow::ArrowWriter;
+use std::fs::File;
+use std::sync::Arc;
+
+/// A named, typed column of an export
+pub type Column = (&'static str, ArrayRef);
+
+/// Digits after the decimal point of money columns: market caps, changes and prices
+pub const MONEY_SCALE: i8 = 4;
+/// Digits after the decimal point of FX rate columns, enough for rates like 0.0118
+pub const RATE_SCALE: i8 = 12;
+
+pub fn strings<'a>(values: impl IntoIterator<Item = Option<&'a str>>) -> ArrayRef {
+    Arc::new(values.into_iter().collect::<StringArray>())
+}
+
+pub fn floats(values: impl IntoIterator<Item = Option<f64>>) -> ArrayRef {
+    Arc::new(values.into_iter().collect::<Float64Array>())
+}
+
+/// Exact decimals with `scale` digits after the point, so sums in notebooks do not
+/// pick up floating point noise; non-finite values become null
+pub fn decimals(values: impl IntoIterator<Item = Option<f64>>, scale: i8) -> ArrayRef {
+    let factor = 10f64.powi(scale as i32);
+    Arc::new(
+        values
+            .into_iter()
+            .map(|v| {
+                v.filter(|v| v.is_finite())
+                    .map(|v| (v * factor).round() as i128)
+            })
+            .collect::<Decimal128Array>()
+            .with_precision_and_scale(38, scale)
+            .expect("scale fits a 38 digit decimal"),
+    )
+}
+
+pub fn integers(values: impl IntoIterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(values.into_iter().collect::<Int64Array>())
+}
+
+pub fn booleans(values: impl IntoIterator<Item = Option<bool>>) -> ArrayRef {
+    Arc::new(values.into_iter().collect::<BooleanArray>())
+}
+
+/// UTC timestamps from unix seconds
+pub fn timestamps(values: impl IntoIterator<Item = Option<i64>>) -> ArrayRef {
+    Arc::new(
+        values
+            .into_iter()
+            .collect::<TimestampSecondArray>()
+            .with_timezone("UTC"),
+    )
+}
+
+pub fn dates(values: impl IntoIterator<Item = Option<NaiveDate>>) -> ArrayRef {
+    let epoch = NaiveDate::from_ymd_opt(1970, 1, 1).unwrap();
+    Arc::new(
+        values
+            .into_iter()
+            .map(|date| date.map(|d| (d - epoch).num_days() as i32))
+            .collect::<Date32Array>(),
+    )
+}
+
+/// Write the columns as a single-row-group Parquet file; all columns are nullable
+pub fn write_parquet(path: &str, columns: Vec<Column>) -> Result<()> {
+    let schema = Schema::new(
+        columns
+            .iter()
+            .map(|(name, array)| Field::new(*name, array.data_type().clone(), true))
+            .collect::<Vec<_>>(),
+    );
+    let batch = RecordBatch::try_new(
+        Arc::new(schema),
+        columns.into_iter().map(|(_, array)| array).collect(),
+    )?;
+
+    let file = File::create(path)?;
+    let mut writer = ArrowWriter::try_new(file, batch.schema(), None)?;
+    writer.write(&batch)?;
+    writer.close()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow_array::Array;
+    use arrow_schema::{DataType, TimeUnit};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    #[test]
+    fn test_write_typed_columns() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("snapshot.parquet");
+        let path = path.to_str().unwrap();
+
+        write_parquet(
+            path,
+            vec![
+                ("ticker", strings([Some("MC.PA"), Some("NKE")])),
+                (
+                    "market_cap_eur",
+                    decimals([Some(300e9 + 0.01), None], MONEY_SCALE),
+                ),
+                ("employees", integers([Some(213_000), None])),
+                ("active", booleans([Some(true), Some(false)])),
+                ("timestamp", timestamps([Some(1_756_505_400), None])),
+                ("date", dates([NaiveDate::from_ymd_opt(2025, 8, 29), None])),
+            ],
+        )
+        .unwrap();
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path).unwrap())
+            .unwrap()
+            .build()
+            .unwrap();
+        let batches: Vec<_> = reader.map(|b| b.unwrap()).collect();
+        let batch = &batches[0];
+        assert_eq!(batch.num_rows(), 2);
+
+        let schema = batch.schema();
+        assert_eq!(schema.field(1).data_type(), &DataType::Decimal128(38, 4));
+        let market_caps = batch
+            .column(1)
+            .as_any()
+            .downcast_ref::<Decimal128Array>()
+            .unwrap();
+        assert_eq!(market_caps.value(0), 3_000_000_000_000_100);
+        assert!(market_caps.is_null(1));
+        assert_eq!(schema.field(3).data_type(), &DataType::Boolean);
+        assert_eq!(
+            schema.field(4).data_type(),
+            &DataType::Timestamp(TimeUnit::Second, Some("UTC".into()))
+        );
+        assert_eq!(schema.field(5).data_type(), &DataType::Date32);
+
+        let dates = batch
+            .column(5)
+            .as_any()
+            .downcast_ref::<Date32Array>()
+            .unwrap();
+        assert_eq!(dates.value(0), 20_329);
+        assert!(dates.is_null(1));
+    }
+}
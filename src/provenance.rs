@@ -0,0 +1,114 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use sqlx::sqlite::SqlitePool;
+
+use crate::api::MarketCapMethod;
+use crate::currencies;
+
+/// Where a stored market cap came from and how it was converted
+#[derive(Debug, Clone, PartialEq)]
+pub struct Provenance {
+    pub provider: String,
+    pub endpoint: String,
+    pub method: String,
+    /// Unix timestamp of the fetch
+    pub fetched_at: i64,
+    /// EUR per unit of the original currency, as applied to this row
+    pub fx_rate_eur: Option<f64>,
+    /// USD per unit of the original currency, as applied to this row
+    pub fx_rate_usd: Option<f64>,
+    /// Timestamp of the exchange rates used for the conversion
+    pub fx_rate_timestamp: Option<i64>,
+}
+
+/// The rate that turned `original` into `converted`, including subunit adjustments
+/// such as GBp to GBP
+fn effective_rate(original: f64, converted: f64) -> Option<f64> {
+    (original > 0.0).then(|| converted / original)
+}
+
+impl Provenance {
+    /// Provenance of a figure fetched from FMP just now
+    pub async fn fmp(
+        pool: &SqlitePool,
+        method: MarketCapMethod,
+        original: f64,
+        eur: f64,
+        usd: f64,
+    ) -> Result<Self> {
+        Ok(Self {
+            provider: "fmp".to_string(),
+            endpoint: method.endpoint().to_string(),
+            method: method.as_str().to_string(),
+            fetched_at: Utc::now().timestamp(),
+            fx_rate_eur: effective_rate(original, eur),
+            fx_rate_usd: effective_rate(original, usd),
+            fx_rate_timestamp: currencies::get_rate_map_timestamp(pool).await?,
+        })
+    }
+}
+
+/// Provenance columns as read back from `market_caps`
+#[derive(Debug, Clone, Default, sqlx::FromRow)]
+pub struct ProvenanceRow {
+    pub source_provider: Option<String>,
+    pub source_endpoint: Option<String>,
+    pub market_cap_method: Option<String>,
+    pub fetched_at: Option<i64>,
+    pub fx_rate_eur: Option<f64>,
+    pub fx_rate_usd: Option<f64>,
+    pub fx_rate_timestamp: Option<i64>,
+}
+
+/// A timestamp as written in the export columns, e.g. `2025-08-17 11:07:11`; empty when
+/// missing, as for rows stored before provenance was recorded
+pub fn format_timestamp(timestamp: Option<i64>) -> String {
+    timestamp
+        .and_then(|ts| DateTime::from_timestamp(ts, 0))
+        .map(|dt| dt.format("%Y-%m-%d %H:%M:%S").to_string())
+        .unwrap_or_default()
+}
+
+/// Parse a timestamp written by `format_timestamp` back to unix seconds
+pub fn parse_timestamp(value: &str) -> Option<i64> {
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|dt| dt.and_utc().timestamp())
+}
+
+/// Short description of a figure's origin such as `fmp/close_x_shares`
+pub fn summary(provider: Option<&str>, method: Option<&str>) -> Option<String> {
+    let provider = provider.filter(|p| !p.is_empty());
+    let method = method.filter(|m| !m.is_empty());
+    match (provider, method) {
+        (Some(provider), Some(method)) => Some(format!("{}/{}", provider, method)),
+        (Some(value), None) | (None, Some(value)) => Some(value.to_string()),
+        (None, None) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_provenance_fields() {
+        // 1,000 GBp converted to 10 GBP worth 11.8 EUR
+        let rate = effective_rate(1000.0, 11.8).unwrap();
+        assert!((rate - 0.0118).abs() < 1e-12);
+        assert_eq!(effective_rate(0.0, 0.0), None);
+
+        assert_eq!(
+            summary(Some("fmp"), Some("quote_fallback")).as_deref(),
+            Some("fmp/quote_fallback")
+        );
+        assert_eq!(summary(Some(""), None), None);
+        assert_eq!(format_timestamp(Some(1_755_428_831)), "2025-08-17 11:07:11");
+        assert_eq!(format_timestamp(None), "");
+        assert_eq!(parse_timestamp("2025-08-17 11:07:11"), Some(1_755_428_831));
+    }
+}
@@ -0,0 +1,236 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use crate::config::{RankedListConfig, SortCurrency};
+use crate::trading_calendar;
+use std::collections::HashMap;
+
+/// The list exported when config.toml defines none
+pub fn default_lists() -> Vec<RankedListConfig> {
+    vec![RankedListConfig {
+        name: "Top 100 Active".to_string(),
+        size: 100,
+        currency: SortCurrency::Eur,
+        exchanges: Vec::new(),
+        countries: Vec::new(),
+        active: Some(true),
+        min_market_cap: None,
+        include: Vec::new(),
+        exclude: Vec::new(),
+    }]
+}
+
+/// The `[[lists]]` of config.toml, or the default list when there are none
+pub fn configured_lists(lists: &[RankedListConfig]) -> Vec<RankedListConfig> {
+    if lists.is_empty() {
+        default_lists()
+    } else {
+        lists.to_vec()
+    }
+}
+
+/// Reject lists whose names give the same file name stem, which would overwrite each
+/// other's exports, or no stem at all
+pub fn check_list_names(lists: &[RankedListConfig]) -> anyhow::Result<()> {
+    let mut seen: HashMap<String, &str> = HashMap::new();
+    for list in lists {
+        let stem = list.file_stem();
+        if stem.is_empty() {
+            anyhow::bail!(
+                "List name \"{}\" needs at least one letter or digit",
+                list.name
+            );
+        }
+        if let Some(other) = seen.insert(stem.clone(), &list.name) {
+            anyhow::bail!(
+                "Lists \"{}\" and \"{}\" would both be exported as {}; give them distinct names",
+                other,
+                list.name,
+                stem
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A company that can be ranked in a list
+pub trait Rankable {
+    fn ticker(&self) -> &str;
+    /// Exchange reported by the provider, if known
+    fn exchange(&self) -> Option<&str>;
+    fn active(&self) -> bool;
+    fn market_cap(&self, currency: SortCurrency) -> f64;
+}
+
+impl RankedListConfig {
+    /// File name stem of the list, e.g. `top_100_active`
+    pub fn file_stem(&self) -> String {
+        self.name
+            .to_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("_")
+    }
+
+    fn matches<T: Rankable>(&self, company: &T) -> bool {
+        let ticker = company.ticker();
+        if self.exclude.iter().any(|t| t.eq_ignore_ascii_case(ticker)) {
+            return false;
+        }
+        if self.include.iter().any(|t| t.eq_ignore_ascii_case(ticker)) {
+            return true;
+        }
+
+        // Without a provider exchange, fall back to the exchange of the ticker suffix
+        let exchange = company
+            .exchange()
+            .unwrap_or_else(|| trading_calendar::exchange_for_ticker(ticker));
+        let exchange_matches = self.exchanges.is_empty()
+            || self
+                .exchanges
+                .iter()
+                .any(|e| exchange.eq_ignore_ascii_case(e));
+        let country_matches = self.countries.is_empty()
+            || self
+                .countries
+                .iter()
+                .any(|c| trading_calendar::country_for_ticker(ticker).eq_ignore_ascii_case(c));
+        let active_matches = self.active.is_none_or(|active| company.active() == active);
+        let cap_matches = self
+            .min_market_cap
+            .is_none_or(|min| company.market_cap(self.currency) >= min);
+
+        exchange_matches && country_matches && active_matches && cap_matches
+    }
+
+    /// The companies in this list, largest first
+    pub fn select<'a, T: Rankable>(&self, companies: &'a [T]) -> Vec<&'a T> {
+        let mut selected: Vec<&T> = companies.iter().filter(|c| self.matches(*c)).collect();
+        selected.sort_by(|a, b| {
+            b.market_cap(self.currency)
+                .total_cmp(&a.market_cap(self.currency))
+        });
+        selected.truncate(self.size);
+        selected
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Company {
+        ticker: &'static str,
+        exchange: Option<&'static str>,
+        active: bool,
+        eur: f64,
+        usd: f64,
+    }
+
+    impl Rankable for Company {
+        fn ticker(&self) -> &str {
+            self.ticker
+        }
+
+        fn exchange(&self) -> Option<&str> {
+            self.exchange
+        }
+
+        fn active(&self) -> bool {
+            self.active
+        }
+
+        fn market_cap(&self, currency: SortCurrency) -> f64 {
+            match currency {
+                SortCurrency::Eur => self.eur,
+                SortCurrency::Usd => self.usd,
+            }
+        }
+    }
+
+    fn company(
+        ticker: &'static str,
+        exchange: Option<&'static str>,
+        eur: f64,
+        usd: f64,
+    ) -> Company {
+        Company {
+            ticker,
+            exchange,
+            active: true,
+            eur,
+            usd,
+        }
+    }
+
+    #[test]
+    fn test_select_lists() {
+        let mut companies = vec![
+            company("MC.PA", Some("EURONEXT"), 300e9, 320e9),
+            company("ITX.MC", Some("BME"), 150e9, 160e9),
+            company("NKE", Some("NYSE"), 100e9, 110e9),
+            company("TJX", Some("NYSE"), 105e9, 105e9),
+            company("LULU", Some("NASDAQ"), 30e9, 33e9),
+            company("ZAL.DE", None, 8e9, 9e9),
+        ];
+        companies[1].active = false;
+
+        let names = |list: &RankedListConfig| -> Vec<&str> {
+            list.select(&companies).iter().map(|c| c.ticker).collect()
+        };
+
+        let top = &default_lists()[0];
+        assert_eq!(top.file_stem(), "top_100_active");
+        assert_eq!(names(top), vec!["MC.PA", "TJX", "NKE", "LULU", "ZAL.DE"]);
+
+        let europe = RankedListConfig {
+            name: "Top 50 Europe".to_string(),
+            size: 50,
+            countries: vec!["fr".to_string(), "ES".to_string(), "DE".to_string()],
+            min_market_cap: Some(10e9),
+            ..default_lists().remove(0)
+        };
+        assert_eq!(names(&europe), vec!["MC.PA"]);
+        let xetra = RankedListConfig {
+            exchanges: vec!["xetra".to_string()],
+            ..default_lists().remove(0)
+        };
+        assert_eq!(names(&xetra), vec!["ZAL.DE"]);
+
+        // Sorted by USD, NKE overtakes TJX; LULU is pinned in despite the cap filter
+        let us = RankedListConfig {
+            name: "Top 25 US".to_string(),
+            size: 2,
+            currency: SortCurrency::Usd,
+            exchanges: vec!["NYSE".to_string()],
+            active: None,
+            min_market_cap: Some(50e9),
+            include: vec!["LULU".to_string()],
+            exclude: vec!["MC.PA".to_string()],
+            ..default_lists().remove(0)
+        };
+        assert_eq!(names(&us), vec!["NKE", "TJX"]);
+        assert_eq!(
+            names(&RankedListConfig { size: 3, ..us }),
+            vec!["NKE", "TJX", "LULU"]
+        );
+
+        assert_eq!(trading_calendar::country_for_ticker("HM-B.ST"), "SE");
+        assert_eq!(trading_calendar::country_for_ticker("BRK.B"), "US");
+    }
+
+    #[test]
+    fn test_list_names_must_give_distinct_files() {
+        let list = |name: &str| RankedListConfig {
+            name: name.to_string(),
+            ..default_lists().remove(0)
+        };
+
+        assert!(check_list_names(&[list("Top 50"), list("Top 50 Europe")]).is_ok());
+        let err = check_list_names(&[list("Top 50"), list("top-50")]).unwrap_err();
+        assert!(err.to_string().contains("top_50"), "{}", err);
+        assert!(check_list_names(&[list("!!!")]).is_err());
+    }
+}
@@ -0,0 +1,364 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
+use csv::Writer;
+use serde::Serialize;
+use std::fmt;
+
+use crate::api::{FMPClient, MarketCapMethod, PolygonClient};
+use crate::config;
+use crate::output::status;
+
+/// Data provider a reconciled value is taken from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Source {
+    Fmp,
+    Polygon,
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Fmp => f.write_str("fmp"),
+            Source::Polygon => f.write_str("polygon"),
+        }
+    }
+}
+
+/// Preferred provider per field; the other provider is used when it has no value
+#[derive(Debug, Clone, Copy)]
+pub struct PreferredSources {
+    pub market_cap: Source,
+    pub shares_outstanding: Source,
+    pub name: Source,
+}
+
+/// What one provider reports for a ticker
+#[derive(Debug, Clone, Default)]
+pub struct ProviderData {
+    pub name: Option<String>,
+    pub market_cap: Option<f64>,
+    pub shares_outstanding: Option<f64>,
+    /// How the market cap was obtained, when the provider reports it
+    pub market_cap_method: Option<MarketCapMethod>,
+}
+
+/// Both providers' values for a ticker, the chosen values and the discrepancies found
+#[derive(Debug, Clone, Serialize)]
+pub struct Reconciliation {
+    #[serde(rename = "Ticker")]
+    pub ticker: String,
+    #[serde(rename = "FMP Name")]
+    pub fmp_name: Option<String>,
+    #[serde(rename = "Polygon Name")]
+    pub polygon_name: Option<String>,
+    #[serde(rename = "FMP Market Cap")]
+    pub fmp_market_cap: Option<f64>,
+    #[serde(rename = "FMP Method")]
+    pub fmp_method: Option<&'static str>,
+    #[serde(rename = "Polygon Market Cap")]
+    pub polygon_market_cap: Option<f64>,
+    #[serde(rename = "Market Cap Difference (%)")]
+    pub market_cap_diff_pct: Option<f64>,
+    #[serde(rename = "FMP Shares Outstanding")]
+    pub fmp_shares_outstanding: Option<f64>,
+    #[serde(rename = "Polygon Shares Outstanding")]
+    pub polygon_shares_outstanding: Option<f64>,
+    #[serde(rename = "Shares Difference (%)")]
+    pub shares_diff_pct: Option<f64>,
+    #[serde(rename = "Name")]
+    pub name: Option<String>,
+    #[serde(rename = "Market Cap")]
+    pub market_cap: Option<f64>,
+    #[serde(rename = "Shares Outstanding")]
+    pub shares_outstanding: Option<f64>,
+    #[serde(rename = "Discrepancies")]
+    pub discrepancies: String,
+}
+
+impl Reconciliation {
+    pub fn has_discrepancies(&self) -> bool {
+        !self.discrepancies.is_empty()
+    }
+}
+
+/// Difference between two values as a percentage of the larger one
+fn diff_pct(a: Option<f64>, b: Option<f64>) -> Option<f64> {
+    match (a, b) {
+        (Some(a), Some(b)) if a > 0.0 || b > 0.0 => Some((a - b).abs() / a.max(b) * 100.0),
+        _ => None,
+    }
+}
+
+/// Lowercase a company name and drop punctuation and legal suffixes, so
+/// "NIKE, Inc." and "Nike Inc" compare equal
+fn normalize_name(name: &str) -> String {
+    const SUFFIXES: [&str; 12] = [
+        "inc",
+        "incorporated",
+        "corp",
+        "corporation",
+        "co",
+        "company",
+        "ltd",
+        "limited",
+        "plc",
+        "holdings",
+        "group",
+        "the",
+    ];
+    name.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty() && !SUFFIXES.contains(word))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn pick<T: Clone>(preferred: Source, fmp: &Option<T>, polygon: &Option<T>) -> Option<T> {
+    match preferred {
+        Source::Fmp => fmp.clone().or_else(|| polygon.clone()),
+        Source::Polygon => polygon.clone().or_else(|| fmp.clone()),
+    }
+}
+
+/// Compare what both providers report for a ticker
+pub fn reconcile(
+    ticker: &str,
+    fmp: &ProviderData,
+    polygon: &ProviderData,
+    tolerance_pct: f64,
+    preferred: &PreferredSources,
+) -> Reconciliation {
+    let market_cap_diff_pct = diff_pct(fmp.market_cap, polygon.market_cap);
+    let shares_diff_pct = diff_pct(fmp.shares_outstanding, polygon.shares_outstanding);
+
+    // FMP only had a figure for today, which is left out of the comparison
+    let fmp_not_dated = fmp.market_cap_method.filter(|m| !m.is_point_in_time());
+
+    let mut discrepancies = Vec::new();
+    if let Some(method) = fmp_not_dated {
+        discrepancies.push(format!(
+            "FMP has no market cap for the date ({} skipped)",
+            method.as_str()
+        ));
+    }
+    if let Some(diff) = market_cap_diff_pct.filter(|d| *d > tolerance_pct) {
+        discrepancies.push(format!("market cap differs by {:.1}%", diff));
+    }
+    if let Some(diff) = shares_diff_pct.filter(|d| *d > tolerance_pct) {
+        discrepancies.push(format!("shares outstanding differ by {:.1}%", diff));
+    }
+    if let (Some(a), Some(b)) = (&fmp.name, &polygon.name) {
+        if normalize_name(a) != normalize_name(b) {
+            discrepancies.push(format!("name \"{}\" vs \"{}\"", a, b));
+        }
+    }
+    for (field, fmp_value, polygon_value) in [
+        ("market cap", fmp.market_cap, polygon.market_cap),
+        (
+            "shares outstanding",
+            fmp.shares_outstanding,
+            polygon.shares_outstanding,
+        ),
+    ] {
+        match (fmp_value, polygon_value) {
+            (Some(_), None) => discrepancies.push(format!("{} missing from Polygon", field)),
+            (None, Some(_)) if field != "market cap" || fmp_not_dated.is_none() => {
+                discrepancies.push(format!("{} missing from FMP", field))
+            }
+            _ => {}
+        }
+    }
+
+    Reconciliation {
+        ticker: ticker.to_string(),
+        fmp_name: fmp.name.clone(),
+        polygon_name: polygon.name.clone(),
+        fmp_market_cap: fmp.market_cap,
+        fmp_method: fmp.market_cap_method.map(MarketCapMethod::as_str),
+        polygon_market_cap: polygon.market_cap,
+        market_cap_diff_pct,
+        fmp_shares_outstanding: fmp.shares_outstanding,
+        polygon_shares_outstanding: polygon.shares_outstanding,
+        shares_diff_pct,
+        name: pick(preferred.name, &fmp.name, &polygon.name),
+        market_cap: pick(preferred.market_cap, &fmp.market_cap, &polygon.market_cap),
+        shares_outstanding: pick(
+            preferred.shares_outstanding,
+            &fmp.shares_outstanding,
+            &polygon.shares_outstanding,
+        ),
+        discrepancies: discrepancies.join("; "),
+    }
+}
+
+/// FMP's figures for the date; a market cap from today's quote is left out so both
+/// providers describe the same day
+async fn fetch_fmp(client: &FMPClient, ticker: &str, date: NaiveDate) -> Result<ProviderData> {
+    let datetime = NaiveDateTime::new(date, NaiveTime::default()).and_utc();
+    let market_cap = client.get_historical_market_cap(ticker, &datetime).await?;
+    let shares_outstanding = client.get_shares_outstanding_at(ticker, date).await?;
+
+    Ok(ProviderData {
+        name: Some(market_cap.name),
+        market_cap: Some(market_cap.market_cap_original)
+            .filter(|m| *m > 0.0 && market_cap.method.is_point_in_time()),
+        shares_outstanding,
+        market_cap_method: Some(market_cap.method),
+    })
+}
+
+async fn fetch_polygon(
+    client: &PolygonClient,
+    ticker: &str,
+    date: NaiveDate,
+) -> Result<ProviderData> {
+    let details = client.get_details(ticker, date).await?;
+    let shares_outstanding = details
+        .extra
+        .get("share_class_shares_outstanding")
+        .and_then(|v| v.as_f64())
+        .or(details.weighted_shares_outstanding);
+
+    Ok(ProviderData {
+        name: details.name,
+        market_cap: details.market_cap,
+        shares_outstanding,
+        market_cap_method: None,
+    })
+}
+
+/// Fetch the US tickers from FMP and Polygon for one date, report discrepancies above
+/// `tolerance_pct` and export both providers' values with the chosen ones to CSV
+pub async fn reconcile_us_tickers(
+    fmp_client: &FMPClient,
+    polygon_client: &PolygonClient,
+    date: NaiveDate,
+    tolerance_pct: f64,
+    preferred: &PreferredSources,
+) -> Result<Vec<Reconciliation>> {
+    let tickers = config::load_config()?.us_tickers;
+    status!(
+        "Reconciling {} US tickers between FMP and Polygon for {}",
+        tickers.len(),
+        date
+    );
+
+    let mut results = Vec::new();
+    for ticker in &tickers {
+        let (fmp, polygon) = tokio::join!(
+            fetch_fmp(fmp_client, ticker, date),
+            fetch_polygon(polygon_client, ticker, date)
+        );
+        let fmp = fmp.unwrap_or_else(|e| {
+            eprintln!("Failed to fetch {} from FMP: {}", ticker, e);
+            ProviderData::default()
+        });
+        let polygon = polygon.unwrap_or_else(|e| {
+            eprintln!("Failed to fetch {} from Polygon: {}", ticker, e);
+            ProviderData::default()
+        });
+
+        let result = reconcile(ticker, &fmp, &polygon, tolerance_pct, preferred);
+        if result.has_discrepancies() {
+            status!("⚠️  {}: {}", ticker, result.discrepancies);
+        }
+        results.push(result);
+    }
+
+    std::fs::create_dir_all("output")?;
+    let filename = format!(
+        "output/reconciliation_{}_{}.csv",
+        date,
+        Local::now().format("%Y%m%d_%H%M%S")
+    );
+    let mut writer = Writer::from_path(&filename)?;
+    for result in &results {
+        writer.serialize(result)?;
+    }
+    writer.flush()?;
+
+    let with_discrepancies = results.iter().filter(|r| r.has_discrepancies()).count();
+    status!(
+        "✅ Reconciled {} tickers ({} with discrepancies above {}%), written to {}",
+        results.len(),
+        with_discrepancies,
+        tolerance_pct,
+        filename
+    );
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reconcile_flags_discrepancies_and_picks_sources() {
+        let preferred = PreferredSources {
+            market_cap: Source::Fmp,
+            shares_outstanding: Source::Polygon,
+            name: Source::Fmp,
+        };
+        let fmp = ProviderData {
+            name: Some("NIKE, Inc.".to_string()),
+            market_cap: Some(110e9),
+            shares_outstanding: None,
+            market_cap_method: Some(MarketCapMethod::Historical),
+        };
+        let polygon = ProviderData {
+            name: Some("Nike Inc".to_string()),
+            market_cap: Some(100e9),
+            shares_outstanding: Some(1.5e9),
+            market_cap_method: None,
+        };
+
+        let result = reconcile("NKE", &fmp, &polygon, 5.0, &preferred);
+        assert_eq!(
+            result.discrepancies,
+            "market cap differs by 9.1%; shares outstanding missing from FMP"
+        );
+        assert_eq!(result.market_cap, Some(110e9));
+        assert_eq!(result.shares_outstanding, Some(1.5e9));
+        assert_eq!(result.name.as_deref(), Some("NIKE, Inc."));
+
+        // Within tolerance and the names only differ in legal suffixes
+        let result = reconcile("NKE", &fmp, &polygon, 10.0, &preferred);
+        assert!(result.discrepancies.starts_with("shares"));
+
+        let renamed = ProviderData {
+            name: Some("Nike Holdings".to_string()),
+            ..polygon.clone()
+        };
+        let result = reconcile("NKE", &fmp, &renamed, 10.0, &preferred);
+        assert!(!result.discrepancies.contains("name"));
+        let other = ProviderData {
+            name: Some("Adidas AG".to_string()),
+            ..polygon
+        };
+        let result = reconcile("NKE", &fmp, &other, 10.0, &preferred);
+        assert!(result
+            .discrepancies
+            .contains("name \"NIKE, Inc.\" vs \"Adidas AG\""));
+
+        // Today's quote is not compared with Polygon's figure for the date
+        let quote = ProviderData {
+            market_cap: None,
+            market_cap_method: Some(MarketCapMethod::QuoteFallback),
+            ..fmp
+        };
+        let result = reconcile("NKE", &quote, &polygon, 5.0, &preferred);
+        assert_eq!(result.market_cap_diff_pct, None);
+        assert_eq!(result.market_cap, Some(100e9));
+        assert_eq!(result.fmp_method, Some("quote_fallback"));
+        assert_eq!(
+            result.discrepancies,
+            "FMP has no market cap for the date (quote_fallback skipped); \
+             shares outstanding missing from FMP"
+        );
+    }
+}
@@ -0,0 +1,264 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use cron::Schedule;
+use fs4::fs_std::FileExt;
+use sqlx::sqlite::SqlitePool;
+use std::fs::{self, File};
+use std::path::Path;
+use std::str::FromStr;
+
+use crate::api;
+use crate::config::{self, JobConfig, ScheduleConfig, ScheduledTask};
+use crate::marketcaps;
+use crate::monthly_historical_marketcaps;
+use crate::output::{status, ExportFormat};
+use crate::symbol_changes;
+use crate::trading_calendar::TradingCalendar;
+
+/// A configured job with its parsed cron schedule
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub config: JobConfig,
+    schedule: Schedule,
+}
+
+impl Job {
+    pub fn next_after(&self, after: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule.after(after).next()
+    }
+}
+
+/// Parse the cron expressions of the configured jobs
+pub fn parse_jobs(config: &ScheduleConfig) -> Result<Vec<Job>> {
+    config
+        .jobs
+        .iter()
+        .map(|job| {
+            let schedule = Schedule::from_str(&job.cron).map_err(|e| {
+                anyhow::anyhow!(
+                    "Invalid cron expression '{}' for {}: {}",
+                    job.cron,
+                    job.task,
+                    e
+                )
+            })?;
+            Ok(Job {
+                config: job.clone(),
+                schedule,
+            })
+        })
+        .collect()
+}
+
+/// The next time any job is due and the jobs due then
+pub fn next_due<'a>(
+    jobs: &'a [Job],
+    after: &DateTime<Utc>,
+) -> Option<(DateTime<Utc>, Vec<&'a Job>)> {
+    let next = jobs.iter().filter_map(|job| job.next_after(after)).min()?;
+    let due = jobs
+        .iter()
+        .filter(|job| job.next_after(after) == Some(next))
+        .collect();
+    Some((next, due))
+}
+
+/// Exclusive lock on the lock file, released when dropped or when the process exits
+#[derive(Debug)]
+pub struct RunLock {
+    _file: File,
+}
+
+impl RunLock {
+    /// Take the lock, failing if another run holds it
+    pub fn acquire(path: &str) -> Result<Self> {
+        if let Some(dir) = Path::new(path)
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(path)
+            .with_context(|| format!("Failed to open lock file {}", path))?;
+        if !file.try_lock_exclusive()? {
+            anyhow::bail!("Another run is in progress (lock file {} is held)", path);
+        }
+        Ok(Self { _file: file })
+    }
+}
+
+/// Last day of the month before `date`
+fn previous_month_end(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap().pred_opt().unwrap()
+}
+
+/// Run one task now
+pub async fn run_task(pool: &SqlitePool, task: ScheduledTask, today: NaiveDate) -> Result<()> {
+    match task {
+        ScheduledTask::DailySnapshot => marketcaps::marketcaps(pool, ExportFormat::Csv)
+            .await
+            .map(|_| ()),
+        ScheduledTask::SymbolChanges => {
+            let api_key = std::env::var("FINANCIALMODELINGPREP_API_KEY")
+                .expect("FINANCIALMODELINGPREP_API_KEY must be set");
+            let fmp_client = api::FMPClient::new(api_key);
+            symbol_changes::fetch_and_store_symbol_changes(pool, &fmp_client).await?;
+            let config_path = config::get_config_path();
+            let report =
+                symbol_changes::check_ticker_updates(pool, &config_path.to_string_lossy()).await?;
+            symbol_changes::print_symbol_change_report(&report);
+            Ok(())
+        }
+        ScheduledTask::MonthEndBackfill => {
+            // Stored at 23:59 like fetch-monthly-historical-market-caps, so the backfill
+            // fills the same month-end snapshot instead of adding one at midnight
+            let month_end = previous_month_end(today);
+            monthly_historical_marketcaps::fetch_month_end_marketcaps(
+                pool,
+                month_end.year(),
+                month_end.month(),
+            )
+            .await
+            .map(|_| ())
+        }
+    }
+}
+
+/// Run a task while holding the lock
+pub async fn run_locked(
+    pool: &SqlitePool,
+    lock_file: &str,
+    task: ScheduledTask,
+    today: NaiveDate,
+) -> Result<()> {
+    let _lock = RunLock::acquire(lock_file)?;
+    run_task(pool, task, today).await
+}
+
+/// Print the configured jobs with their next run time
+pub fn print_jobs(jobs: &[Job], now: &DateTime<Utc>) {
+    status!(
+        "{:<20} {:<22} {:<22} Exchanges",
+        "Task",
+        "Cron (UTC)",
+        "Next run"
+    );
+    for job in jobs {
+        status!(
+            "{:<20} {:<22} {:<22} {}",
+            job.config.task.to_string(),
+            job.config.cron,
+            job.next_after(now)
+                .map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|| "never".to_string()),
+            job.config.exchanges.join(", ")
+        );
+    }
+}
+
+/// Run the configured jobs until interrupted
+pub async fn run_daemon(pool: &SqlitePool) -> Result<()> {
+    let config = config::load_config()?;
+    let schedule = config.schedule.unwrap_or_default();
+    let calendar = TradingCalendar::from_config(config.calendar.as_ref())?;
+    let jobs = parse_jobs(&schedule)?;
+    if jobs.is_empty() {
+        anyhow::bail!("No jobs configured in [schedule]");
+    }
+
+    status!("Scheduler started with {} jobs", jobs.len());
+    print_jobs(&jobs, &Utc::now());
+
+    loop {
+        let now = Utc::now();
+        let Some((next, due)) = next_due(&jobs, &now) else {
+            status!("No more scheduled runs");
+            return Ok(());
+        };
+
+        let wait = (next - now).to_std().unwrap_or_default();
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = tokio::signal::ctrl_c() => {
+                status!("Scheduler stopped");
+                return Ok(());
+            }
+        }
+
+        let today = next.date_naive();
+        for job in due {
+            let task = job.config.task;
+            if !calendar.any_open(&job.config.exchanges, today) {
+                status!(
+                    "⏭️  Skipping {} on {}: {} closed",
+                    task,
+                    today,
+                    job.config.exchanges.join(", ")
+                );
+                continue;
+            }
+
+            status!(
+                "▶️  Running {} at {}",
+                task,
+                next.format("%Y-%m-%d %H:%M:%S")
+            );
+            match run_locked(pool, &schedule.lock_file, task, today).await {
+                Ok(()) => status!("✅ {} finished", task),
+                Err(e) => eprintln!("❌ {} failed: {:#}", task, e),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_default_jobs_and_lock() {
+        let jobs = parse_jobs(&ScheduleConfig::default()).unwrap();
+
+        // On Friday the daily snapshot is next, then the backfill on Monday 1 September
+        let friday = Utc.with_ymd_and_hms(2025, 8, 29, 12, 0, 0).unwrap();
+        let (next, due) = next_due(&jobs, &friday).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 8, 29, 22, 30, 0).unwrap());
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].config.task, ScheduledTask::DailySnapshot);
+
+        let (next, due) = next_due(&jobs, &next).unwrap();
+        assert_eq!(next, Utc.with_ymd_and_hms(2025, 9, 1, 4, 0, 0).unwrap());
+        assert_eq!(due[0].config.task, ScheduledTask::MonthEndBackfill);
+        assert_eq!(
+            previous_month_end(next.date_naive()),
+            NaiveDate::from_ymd_opt(2025, 8, 31).unwrap()
+        );
+
+        let invalid = ScheduleConfig {
+            jobs: vec![JobConfig {
+                task: ScheduledTask::DailySnapshot,
+                cron: "every day".to_string(),
+                exchanges: vec![],
+            }],
+            ..Default::default()
+        };
+        assert!(parse_jobs(&invalid).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("top200.lock");
+        let path = path.to_str().unwrap();
+        let lock = RunLock::acquire(path).unwrap();
+        assert!(RunLock::acquire(path).is_err());
+        drop(lock);
+        assert!(RunLock::acquire(path).is_ok());
+    }
+}
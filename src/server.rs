@@ -0,0 +1,688 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, NaiveDate};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePool;
+use std::sync::Arc;
+
+use crate::companies;
+use crate::compare_marketcaps::{
+    apply_overrides, build_comparisons, canonicalize_tickers, MarketCapComparison,
+};
+use crate::config::{self, ConsolidationConfig};
+use crate::consolidation;
+use crate::corporate_events;
+use crate::currencies;
+use crate::export_schema::MarketCapRecord;
+use crate::output::status;
+use crate::overrides;
+use crate::theme::Presentation;
+use crate::visualizations;
+
+#[derive(Clone)]
+struct AppState {
+    pool: SqlitePool,
+    presentation: Arc<Presentation>,
+    /// Companies ranked as one row, from config.toml
+    consolidations: Arc<Vec<ConsolidationConfig>>,
+}
+
+/// Error returned to API clients as `{"error": "..."}`
+#[derive(Debug)]
+enum ApiError {
+    BadRequest(String),
+    NotFound(String),
+    Internal(anyhow::Error),
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let (status, message) = match self {
+            ApiError::BadRequest(message) => (StatusCode::BAD_REQUEST, message),
+            ApiError::NotFound(message) => (StatusCode::NOT_FOUND, message),
+            ApiError::Internal(err) => {
+                eprintln!("❌ API error: {:#}", err);
+                (StatusCode::INTERNAL_SERVER_ERROR, err.to_string())
+            }
+        };
+        (status, Json(serde_json::json!({ "error": message }))).into_response()
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        ApiError::Internal(err)
+    }
+}
+
+impl From<sqlx::Error> for ApiError {
+    fn from(err: sqlx::Error) -> Self {
+        ApiError::Internal(err.into())
+    }
+}
+
+type ApiResult<T> = std::result::Result<T, ApiError>;
+
+#[derive(Debug, Serialize)]
+struct SnapshotSummary {
+    date: String,
+    timestamp: i64,
+    companies: i64,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct CompanyRow {
+    #[sqlx(skip)]
+    rank: usize,
+    ticker: String,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    exchange: Option<String>,
+    price: Option<f64>,
+    source_provider: Option<String>,
+    market_cap_method: Option<String>,
+    overridden: Option<String>,
+    /// Tickers merged into this row by consolidation, e.g. `HM-B.ST + HM-A.ST`
+    #[sqlx(skip)]
+    listings: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Snapshot {
+    date: String,
+    timestamp: i64,
+    companies: Vec<CompanyRow>,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct HistoryPoint {
+    #[sqlx(skip)]
+    date: String,
+    timestamp: i64,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    price: Option<f64>,
+}
+
+#[derive(Debug, Serialize)]
+struct CompanyHistory {
+    ticker: String,
+    history: Vec<HistoryPoint>,
+}
+
+#[derive(Debug, Serialize)]
+struct Comparison {
+    from: String,
+    to: String,
+    companies: Vec<MarketCapComparison>,
+}
+
+#[derive(Debug, Serialize)]
+struct Currency {
+    code: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize, sqlx::FromRow)]
+struct FxRate {
+    symbol: String,
+    ask: f64,
+    bid: f64,
+    timestamp: i64,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompareParams {
+    from: String,
+    to: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct FxParams {
+    date: Option<String>,
+}
+
+/// Format a unix timestamp as a YYYY-MM-DD date (UTC)
+fn timestamp_to_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Parse a YYYY-MM-DD date into the [start, end) unix timestamp range of that day (UTC)
+fn day_bounds(date: &str) -> ApiResult<(i64, i64)> {
+    let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+        ApiError::BadRequest(format!("Invalid date '{}', expected YYYY-MM-DD", date))
+    })?;
+    let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+    Ok((start, start + 86_400))
+}
+
+/// Rows of the latest snapshot taken on the given date, leaving out companies with a
+/// delisting or acquisition on or before that date
+async fn load_records(pool: &SqlitePool, date: &str) -> ApiResult<(i64, Vec<MarketCapRecord>)> {
+    let (start, end) = day_bounds(date)?;
+
+    let (timestamp,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MAX(timestamp) FROM market_caps WHERE timestamp >= ? AND timestamp < ?",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
+    .await?;
+    let timestamp =
+        timestamp.ok_or_else(|| ApiError::NotFound(format!("No snapshot for {}", date)))?;
+
+    let companies = sqlx::query_as::<_, CompanyRow>(
+        r#"
+        SELECT ticker, name,
+            CAST(market_cap_original AS REAL) AS market_cap_original,
+            original_currency,
+            CAST(market_cap_eur AS REAL) AS market_cap_eur,
+            CAST(market_cap_usd AS REAL) AS market_cap_usd,
+            exchange,
+            CAST(price AS REAL) AS price,
+            source_provider,
+            market_cap_method,
+            overridden
+        FROM market_caps
+        WHERE timestamp = ?
+        ORDER BY market_cap_eur DESC
+        "#,
+    )
+    .bind(timestamp)
+    .fetch_all(pool)
+    .await?;
+
+    let excluded = corporate_events::excluded_at(pool, timestamp).await?;
+    let records = companies
+        .into_iter()
+        .filter(|c| !excluded.contains(&c.ticker))
+        .map(|c| MarketCapRecord {
+            ticker: c.ticker,
+            name: c.name,
+            market_cap_original: c.market_cap_original,
+            original_currency: c.original_currency,
+            market_cap_eur: c.market_cap_eur,
+            market_cap_usd: c.market_cap_usd,
+            exchange: c.exchange,
+            price: c.price,
+            source: c.source_provider,
+            method: c.market_cap_method,
+            overridden: c.overridden,
+            ..Default::default()
+        })
+        .collect();
+
+    Ok((timestamp, records))
+}
+
+/// Rank a snapshot the way the exports do: apply the manual overrides in effect at
+/// `timestamp`, including ones set after it was stored, merge the listings of
+/// consolidated companies and rank by EUR market cap
+async fn rank_records(
+    pool: &SqlitePool,
+    consolidations: &[ConsolidationConfig],
+    timestamp: i64,
+    records: Vec<MarketCapRecord>,
+) -> ApiResult<Vec<MarketCapRecord>> {
+    let rate_map = currencies::get_rate_map_from_db(pool).await?;
+    let records = apply_overrides(
+        records,
+        &overrides::active_at(pool, timestamp).await?,
+        &rate_map,
+    );
+
+    let mut records: Vec<MarketCapRecord> = consolidation::consolidate(records, consolidations)?
+        .into_iter()
+        .map(|(mut record, listings)| {
+            record.listings = (!listings.is_empty()).then(|| listings.join(" + "));
+            record
+        })
+        .collect();
+    records.sort_by(|a, b| {
+        b.market_cap_eur
+            .unwrap_or(0.0)
+            .partial_cmp(&a.market_cap_eur.unwrap_or(0.0))
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+    for (i, record) in records.iter_mut().enumerate() {
+        record.rank = Some(i + 1);
+    }
+
+    Ok(records)
+}
+
+/// Load the latest snapshot taken on the given date, ranked by EUR market cap
+async fn load_snapshot(
+    pool: &SqlitePool,
+    consolidations: &[ConsolidationConfig],
+    date: &str,
+) -> ApiResult<Snapshot> {
+    let (timestamp, records) = load_records(pool, date).await?;
+    let companies = rank_records(pool, consolidations, timestamp, records)
+        .await?
+        .into_iter()
+        .map(|r| CompanyRow {
+            rank: r.rank.unwrap_or_default(),
+            ticker: r.ticker,
+            name: r.name,
+            market_cap_original: r.market_cap_original,
+            original_currency: r.original_currency,
+            market_cap_eur: r.market_cap_eur,
+            market_cap_usd: r.market_cap_usd,
+            exchange: r.exchange,
+            price: r.price,
+            source_provider: r.source,
+            market_cap_method: r.method,
+            overridden: r.overridden,
+            listings: r.listings,
+        })
+        .collect();
+
+    Ok(Snapshot {
+        date: timestamp_to_date(timestamp),
+        timestamp,
+        companies,
+    })
+}
+
+/// Load both snapshots and compare them the same way `compare-market-caps` does:
+/// retired tickers are matched to their successors before ranking
+async fn load_comparisons(
+    pool: &SqlitePool,
+    consolidations: &[ConsolidationConfig],
+    from_date: &str,
+    to_date: &str,
+) -> ApiResult<Vec<MarketCapComparison>> {
+    let retired = companies::retired_ticker_map(pool).await?;
+    let mut ranked = Vec::new();
+    for date in [from_date, to_date] {
+        let (timestamp, records) = load_records(pool, date).await?;
+        let records = canonicalize_tickers(records, &retired);
+        ranked.push(rank_records(pool, consolidations, timestamp, records).await?);
+    }
+
+    Ok(build_comparisons(&ranked[0], &ranked[1]))
+}
+
+async fn list_snapshots(State(state): State<AppState>) -> ApiResult<Json<Vec<SnapshotSummary>>> {
+    let rows = sqlx::query_as::<_, (i64, i64)>(
+        "SELECT timestamp, COUNT(*) FROM market_caps GROUP BY timestamp ORDER BY timestamp DESC",
+    )
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(
+        rows.into_iter()
+            .map(|(timestamp, companies)| SnapshotSummary {
+                date: timestamp_to_date(timestamp),
+                timestamp,
+                companies,
+            })
+            .collect(),
+    ))
+}
+
+async fn get_snapshot(
+    State(state): State<AppState>,
+    Path(date): Path<String>,
+) -> ApiResult<Json<Snapshot>> {
+    Ok(Json(
+        load_snapshot(&state.pool, &state.consolidations, &date).await?,
+    ))
+}
+
+async fn company_history(
+    State(state): State<AppState>,
+    Path(ticker): Path<String>,
+) -> ApiResult<Json<CompanyHistory>> {
+    // Follow the company across symbol changes
+    let ticker = companies::resolve_ticker(&state.pool, &ticker).await?;
+
+    // Rows stored under retired tickers count too, unless the current ticker has a row
+    // for the same snapshot
+    let mut history = sqlx::query_as::<_, HistoryPoint>(
+        r#"
+        WITH tickers AS (
+            SELECT ? AS ticker
+            UNION
+            SELECT alias.ticker
+            FROM ticker_aliases alias
+            JOIN ticker_aliases current ON current.company_id = alias.company_id
+            WHERE current.ticker = ?
+        )
+        SELECT m.timestamp, m.name,
+            CAST(m.market_cap_original AS REAL) AS market_cap_original,
+            m.original_currency,
+            CAST(m.market_cap_eur AS REAL) AS market_cap_eur,
+            CAST(m.market_cap_usd AS REAL) AS market_cap_usd,
+            CAST(m.price AS REAL) AS price
+        FROM market_caps m
+        WHERE m.ticker IN (SELECT ticker FROM tickers)
+            AND (m.ticker = ? OR NOT EXISTS (
+                SELECT 1 FROM market_caps c WHERE c.ticker = ? AND c.timestamp = m.timestamp
+            ))
+        ORDER BY m.timestamp
+        "#,
+    )
+    .bind(&ticker)
+    .bind(&ticker)
+    .bind(&ticker)
+    .bind(&ticker)
+    .fetch_all(&state.pool)
+    .await?;
+
+    if history.is_empty() {
+        return Err(ApiError::NotFound(format!("No history for {}", ticker)));
+    }
+    for point in history.iter_mut() {
+        point.date = timestamp_to_date(point.timestamp);
+    }
+
+    Ok(Json(CompanyHistory { ticker, history }))
+}
+
+async fn compare(
+    State(state): State<AppState>,
+    Query(params): Query<CompareParams>,
+) -> ApiResult<Json<Comparison>> {
+    let companies =
+        load_comparisons(&state.pool, &state.consolidations, &params.from, &params.to).await?;
+
+    Ok(Json(Comparison {
+        from: params.from,
+        to: params.to,
+        companies,
+    }))
+}
+
+async fn list_currencies(State(state): State<AppState>) -> ApiResult<Json<Vec<Currency>>> {
+    let currencies = currencies::list_currencies(&state.pool).await?;
+
+    Ok(Json(
+        currencies
+            .into_iter()
+            .map(|(code, name)| Currency { code, name })
+            .collect(),
+    ))
+}
+
+/// Latest rate per symbol as of the end of the given date (or overall if no date is given)
+async fn fx_rates(
+    State(state): State<AppState>,
+    Query(params): Query<FxParams>,
+) -> ApiResult<Json<Vec<FxRate>>> {
+    let until = match params.date.as_deref() {
+        Some(date) => day_bounds(date)?.1,
+        None => i64::MAX,
+    };
+
+    let rates = sqlx::query_as::<_, FxRate>(
+        r#"
+        SELECT f.symbol, f.ask, f.bid, f.timestamp
+        FROM forex_rates f
+        JOIN (
+            SELECT symbol, MAX(timestamp) AS timestamp
+            FROM forex_rates
+            WHERE timestamp < ?
+            GROUP BY symbol
+        ) latest ON f.symbol = latest.symbol AND f.timestamp = latest.timestamp
+        ORDER BY f.symbol
+        "#,
+    )
+    .bind(until)
+    .fetch_all(&state.pool)
+    .await?;
+
+    Ok(Json(rates))
+}
+
+async fn list_charts() -> Json<Vec<&'static str>> {
+    Json(visualizations::chart_kinds().collect())
+}
+
+async fn render_chart(
+    State(state): State<AppState>,
+    Path(kind): Path<String>,
+    Query(params): Query<CompareParams>,
+) -> ApiResult<Response> {
+    if !visualizations::chart_kinds().any(|k| k == kind) {
+        return Err(ApiError::NotFound(format!("Unknown chart kind: {}", kind)));
+    }
+
+    let comparisons =
+        load_comparisons(&state.pool, &state.consolidations, &params.from, &params.to).await?;
+    let svg = visualizations::render_chart_svg(
+        &kind,
+        &comparisons,
+        &params.from,
+        &params.to,
+        &state.presentation,
+    )?;
+
+    Ok(([(header::CONTENT_TYPE, "image/svg+xml")], svg).into_response())
+}
+
+fn router(
+    pool: SqlitePool,
+    presentation: Presentation,
+    consolidations: Vec<ConsolidationConfig>,
+) -> Router {
+    Router::new()
+        .route("/snapshots", get(list_snapshots))
+        .route("/snapshots/:date", get(get_snapshot))
+        .route("/companies/:ticker/history", get(company_history))
+        .route("/compare", get(compare))
+        .route("/currencies", get(list_currencies))
+        .route("/fx", get(fx_rates))
+        .route("/charts", get(list_charts))
+        .route("/charts/:kind", get(render_chart))
+        .with_state(AppState {
+            pool,
+            presentation: Arc::new(presentation),
+            consolidations: Arc::new(consolidations),
+        })
+}
+
+/// Serve the HTTP JSON API on the given address until interrupted
+pub async fn serve(pool: SqlitePool, addr: &str, presentation: Presentation) -> Result<()> {
+    let consolidations = config::load_config()?.consolidations;
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    status!("✅ Serving API on http://{}", listener.local_addr()?);
+
+    axum::serve(listener, router(pool, presentation, consolidations))
+        .with_graceful_shutdown(async {
+            tokio::signal::ctrl_c().await.ok();
+        })
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::{to_bytes, Body};
+    use axum::http::Request;
+    use sqlx::sqlite::SqlitePoolOptions;
+    use tower::ServiceExt;
+
+    async fn test_pool() -> SqlitePool {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        // Two snapshots: 2025-01-02 and 2025-02-03
+        for (ticker, name, eur, usd, timestamp) in [
+            ("AAA", "Alpha", 200.0, 210.0, 1_735_819_200_i64),
+            ("BBB", "Beta", 100.0, 105.0, 1_735_819_200),
+            ("AAA", "Alpha", 150.0, 160.0, 1_738_584_000),
+            ("BBB", "Beta", 300.0, 315.0, 1_738_584_000),
+        ] {
+            sqlx::query(
+                "INSERT INTO market_caps (ticker, name, market_cap_eur, market_cap_usd, timestamp) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(ticker)
+            .bind(name)
+            .bind(eur)
+            .bind(usd)
+            .bind(timestamp)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        pool
+    }
+
+    async fn get(pool: SqlitePool, uri: &str) -> (StatusCode, Vec<u8>) {
+        let response = router(pool, Presentation::default(), Vec::new())
+            .oneshot(Request::get(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, body.to_vec())
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_is_ranked_by_eur() {
+        let (status, body) = get(test_pool().await, "/snapshots/2025-02-03").await;
+        assert_eq!(status, StatusCode::OK);
+
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["companies"][0]["ticker"], "BBB");
+        assert_eq!(json["companies"][0]["rank"], 1);
+        assert_eq!(json["companies"][1]["rank"], 2);
+    }
+
+    #[tokio::test]
+    async fn test_compare_and_errors() {
+        let pool = test_pool().await;
+
+        let (status, body) = get(pool.clone(), "/compare?from=2025-01-02&to=2025-02-03").await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(json["companies"][0]["ticker"], "BBB");
+        assert_eq!(json["companies"][0]["rank_change"], 1);
+
+        let (status, _) = get(pool.clone(), "/snapshots/2025-13-01").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let (status, _) = get(pool, "/companies/ZZZ/history").await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_chart_endpoint_returns_svg() {
+        let (status, body) = get(
+            test_pool().await,
+            "/charts/market_treemap?from=2025-01-02&to=2025-02-03",
+        )
+        .await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(String::from_utf8(body).unwrap().contains("<svg"));
+    }
+
+    #[tokio::test]
+    async fn test_compare_consolidates_and_follows_renames() {
+        let pool = test_pool().await;
+
+        // BBB traded as OLD on the first date; CCC is a second share class of AAA
+        sqlx::query(
+            "UPDATE market_caps SET ticker = 'OLD' WHERE ticker = 'BBB' AND timestamp = 1735819200",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO market_caps (ticker, name, market_cap_eur, market_cap_usd, timestamp) VALUES ('CCC', 'Alpha B', 500.0, 525.0, 1738584000)")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO companies (id, name) VALUES (100, 'Beta')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO ticker_aliases (ticker, company_id, valid_until) VALUES ('OLD', 100, '2025-01-15'), ('BBB', 100, NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let consolidations = vec![ConsolidationConfig {
+            primary: "AAA".to_string(),
+            name: None,
+            share_classes: vec!["CCC".to_string()],
+            duplicate_listings: Vec::new(),
+        }];
+        let comparisons = load_comparisons(&pool, &consolidations, "2025-01-02", "2025-02-03")
+            .await
+            .unwrap();
+
+        let tickers: Vec<&str> = comparisons.iter().map(|c| c.ticker.as_str()).collect();
+        assert_eq!(tickers.len(), 2);
+        assert!(tickers.contains(&"AAA") && tickers.contains(&"BBB"));
+        let alpha = comparisons.iter().find(|c| c.ticker == "AAA").unwrap();
+        assert_eq!(alpha.market_cap_to, Some(685.0));
+        let beta = comparisons.iter().find(|c| c.ticker == "BBB").unwrap();
+        assert_eq!(beta.rank_from, Some(2));
+
+        let snapshot = load_snapshot(&pool, &consolidations, "2025-02-03")
+            .await
+            .unwrap();
+        assert_eq!(snapshot.companies[0].ticker, "AAA");
+        assert_eq!(snapshot.companies[0].listings.as_deref(), Some("AAA + CCC"));
+    }
+
+    #[tokio::test]
+    async fn test_history_includes_retired_tickers() {
+        let pool = test_pool().await;
+
+        // BBB was called OLD, whose rows stay under the old ticker
+        sqlx::query(
+            "UPDATE market_caps SET ticker = 'OLD' WHERE ticker = 'BBB' AND timestamp = 1735819200",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        sqlx::query("INSERT INTO companies (id, name) VALUES (100, 'Beta')")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query(
+            "INSERT INTO ticker_aliases (ticker, company_id, valid_until) VALUES ('OLD', 100, '2025-01-15'), ('BBB', 100, NULL)",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        for uri in ["/companies/BBB/history", "/companies/OLD/history"] {
+            let (status, body) = get(pool.clone(), uri).await;
+            assert_eq!(status, StatusCode::OK);
+            let json: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(json["ticker"], "BBB");
+            assert_eq!(json["history"].as_array().unwrap().len(), 2);
+        }
+    }
+}
@@ -4,16 +4,26 @@
 
 use crate::api;
 use crate::config;
-use crate::currencies::{convert_currency, get_rate_map_from_db};
+use crate::currencies::get_rate_map_from_db;
+use crate::export_schema::{self, MarketCapRecord};
+use crate::historical_marketcaps::store_historical_market_cap;
+use crate::output::{status, Failure, FetchSummary, Outcome};
+use crate::overrides::{self, Figures};
+use crate::provenance::ProvenanceRow;
+use crate::trading_calendar::TradingCalendar;
+use crate::validation;
 use anyhow::Result;
 use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
-use csv::Writer;
 use indicatif::{ProgressBar, ProgressStyle};
 use sqlx::sqlite::SqlitePool;
 use std::sync::Arc;
 
-pub async fn fetch_specific_date_marketcaps(pool: &SqlitePool, date_str: &str) -> Result<()> {
+pub async fn fetch_specific_date_marketcaps(
+    pool: &SqlitePool,
+    date_str: &str,
+) -> Result<Outcome<FetchSummary>> {
     let config = config::load_config()?;
+    let calendar = TradingCalendar::from_config(config.calendar.as_ref())?;
     let tickers = [config.non_us_tickers, config.us_tickers].concat();
 
     // Parse the date string
@@ -28,12 +38,13 @@ pub async fn fetch_specific_date_marketcaps(pool: &SqlitePool, date_str: &str) -
         .expect("FINANCIALMODELINGPREP_API_KEY must be set");
     let fmp_client = Arc::new(api::FMPClient::new(api_key));
 
-    println!("Fetching market caps for date: {}", date);
+    status!("Fetching market caps for date: {}", date);
+    calendar.print_shifted(&tickers, date);
 
     // Get exchange rates
-    println!("Fetching exchange rates from database...");
+    status!("Fetching exchange rates from database...");
     let rate_map = get_rate_map_from_db(pool).await?;
-    println!("✅ Exchange rates fetched from database");
+    status!("✅ Exchange rates fetched from database");
 
     let total_tickers = tickers.len();
     let progress = ProgressBar::new(total_tickers as u64);
@@ -51,51 +62,14 @@ pub async fn fetch_specific_date_marketcaps(pool: &SqlitePool, date_str: &str) -
         progress.set_message(format!("Processing {}", ticker));
 
         match fmp_client
-            .get_historical_market_cap(ticker, &datetime_utc)
+            .get_historical_market_cap(ticker, &calendar.request_datetime(ticker, &naive_dt))
             .await
         {
             Ok(market_cap) => {
-                // Convert currencies
-                let market_cap_eur = convert_currency(
-                    market_cap.market_cap_original,
-                    &market_cap.original_currency,
-                    "EUR",
-                    &rate_map,
-                );
-
-                let market_cap_usd = convert_currency(
-                    market_cap.market_cap_original,
-                    &market_cap.original_currency,
-                    "USD",
-                    &rate_map,
-                );
-
                 // Store the Unix timestamp of the historical date
                 let timestamp = naive_dt.and_utc().timestamp();
-
-                // Insert into database
-                sqlx::query!(
-                    r#"
-                    INSERT OR REPLACE INTO market_caps (
-                        ticker, name, market_cap_original, original_currency,
-                        market_cap_eur, market_cap_usd, exchange, price,
-                        active, timestamp
-                    )
-                    VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
-                    "#,
-                    ticker,
-                    market_cap.name,
-                    market_cap.market_cap_original,
-                    market_cap.original_currency,
-                    market_cap_eur,
-                    market_cap_usd,
-                    market_cap.exchange,
-                    market_cap.price,
-                    true,
-                    timestamp,
-                )
-                .execute(pool)
-                .await?;
+                store_historical_market_cap(pool, ticker, &market_cap, &rate_map, timestamp)
+                    .await?;
 
                 successful_tickers.push(ticker.clone());
             }
@@ -104,7 +78,7 @@ pub async fn fetch_specific_date_marketcaps(pool: &SqlitePool, date_str: &str) -
                     "❌ Failed to fetch market cap for {} on {}: {}",
                     ticker, date, e
                 );
-                failed_tickers.push((ticker.clone(), e.to_string()));
+                failed_tickers.push(Failure::new(ticker, e));
             }
         }
         progress.inc(1);
@@ -112,34 +86,72 @@ pub async fn fetch_specific_date_marketcaps(pool: &SqlitePool, date_str: &str) -
     progress.finish_with_message("Processing complete");
 
     // Print summary
-    println!(
+    status!(
         "\n✅ Successfully fetched market caps for {} tickers",
         successful_tickers.len()
     );
 
     if !failed_tickers.is_empty() {
-        println!("\n❌ Failed to fetch {} tickers:", failed_tickers.len());
-        for (ticker, error) in &failed_tickers {
-            println!("  {} - {}", ticker, error);
+        status!("\n❌ Failed to fetch {} tickers:", failed_tickers.len());
+        for failure in &failed_tickers {
+            status!("  {} - {}", failure.ticker, failure.error);
         }
     }
 
+    // Check the snapshot before exporting it
+    let issues = validation::run_validation(pool, &[datetime_utc.timestamp()]).await?;
+    validation::ensure_export_allowed(&issues)?;
+
     // Export to CSV
-    export_specific_date_marketcaps(pool, date).await?;
+    let files = export_specific_date_marketcaps(pool, date)
+        .await?
+        .into_iter()
+        .collect();
+
+    Ok(Outcome {
+        data: FetchSummary {
+            dates: vec![date.to_string()],
+            stored: successful_tickers.len(),
+            issues,
+            files,
+        },
+        failures: failed_tickers,
+    })
+}
 
-    Ok(())
+#[derive(sqlx::FromRow)]
+struct SpecificDateRow {
+    ticker: String,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    exchange: Option<String>,
+    active: Option<bool>,
+    price: Option<f64>,
+    description: Option<String>,
+    homepage_url: Option<String>,
+    employees: Option<i64>,
+    overridden: Option<String>,
+    #[sqlx(flatten)]
+    provenance: ProvenanceRow,
 }
 
-async fn export_specific_date_marketcaps(pool: &SqlitePool, date: NaiveDate) -> Result<()> {
+/// Export the snapshot of a date to CSV; returns the file written, if there was data
+async fn export_specific_date_marketcaps(
+    pool: &SqlitePool,
+    date: NaiveDate,
+) -> Result<Option<String>> {
     let naive_dt = NaiveDateTime::new(date, NaiveTime::default());
     let timestamp = naive_dt.and_utc().timestamp();
 
     // Fetch market caps for the specific date
-    let records = sqlx::query!(
+    let records = sqlx::query_as::<_, SpecificDateRow>(
         r#"
         SELECT
-            m.ticker as "ticker!",
-            m.name as "name!",
+            m.ticker,
+            m.name,
             CAST(m.market_cap_original AS REAL) as market_cap_original,
             m.original_currency,
             CAST(m.market_cap_eur AS REAL) as market_cap_eur,
@@ -149,22 +161,57 @@ async fn export_specific_date_marketcaps(pool: &SqlitePool, date: NaiveDate) ->
             CAST(m.price AS REAL) as price,
             td.description,
             td.homepage_url,
-            td.employees
+            td.employees,
+            m.overridden,
+            m.source_provider,
+            m.source_endpoint,
+            m.market_cap_method,
+            m.fetched_at,
+            m.fx_rate_eur,
+            m.fx_rate_usd,
+            m.fx_rate_timestamp
         FROM market_caps m
         LEFT JOIN ticker_details td ON m.ticker = td.ticker
         WHERE m.timestamp = ?
         ORDER BY m.market_cap_eur DESC
         "#,
-        timestamp
     )
+    .bind(timestamp)
     .fetch_all(pool)
     .await?;
 
     if records.is_empty() {
-        println!("No market cap data found for date: {}", date);
-        return Ok(());
+        status!("No market cap data found for date: {}", date);
+        return Ok(None);
     }
 
+    // Apply overrides set after the data was fetched and re-rank
+    let active_overrides = overrides::active_at(pool, timestamp).await?;
+    let rate_map = get_rate_map_from_db(pool).await?;
+    let mut rows: Vec<(SpecificDateRow, Figures, Option<String>)> = records
+        .into_iter()
+        .filter(|r| !active_overrides.get(&r.ticker).is_some_and(|o| o.exclude))
+        .map(|r| {
+            let mut figures = Figures {
+                name: r.name.clone(),
+                market_cap_original: r.market_cap_original.unwrap_or(0.0),
+                original_currency: r.original_currency.clone().unwrap_or_default(),
+                market_cap_eur: r.market_cap_eur.unwrap_or(0.0),
+                market_cap_usd: r.market_cap_usd.unwrap_or(0.0),
+            };
+            let applied = active_overrides
+                .get(&r.ticker)
+                .and_then(|o| o.apply(&mut figures, &rate_map));
+            let overridden = overrides::merge_markers(r.overridden.as_deref(), applied.as_deref());
+            (r, figures, overridden)
+        })
+        .collect();
+    rows.sort_by(|a, b| {
+        b.1.market_cap_eur
+            .partial_cmp(&a.1.market_cap_eur)
+            .unwrap_or(std::cmp::Ordering::Equal)
+    });
+
     // Create output directory if it doesn't exist
     std::fs::create_dir_all("output")?;
 
@@ -173,54 +220,31 @@ async fn export_specific_date_marketcaps(pool: &SqlitePool, date: NaiveDate) ->
     let date_str = date.format("%Y-%m-%d");
     let filename = format!("output/marketcaps_{}_{}.csv", date_str, timestamp_str);
 
-    let file = std::fs::File::create(&filename)?;
-    let mut writer = Writer::from_writer(file);
-
-    // Write headers
-    writer.write_record(&[
-        "Rank",
-        "Ticker",
-        "Name",
-        "Market Cap (Original)",
-        "Original Currency",
-        "Market Cap (EUR)",
-        "Market Cap (USD)",
-        "Price",
-        "Exchange",
-        "Active",
-        "Description",
-        "Homepage URL",
-        "Employees",
-        "Date",
-    ])?;
-
-    // Write data with rank
-    for (index, record) in records.iter().enumerate() {
-        writer.write_record(&[
-            (index + 1).to_string(),
-            record.ticker.clone(),
-            record.name.clone(),
-            record.market_cap_original.unwrap_or(0.0).to_string(),
-            record.original_currency.clone().unwrap_or_default(),
-            record.market_cap_eur.unwrap_or(0.0).to_string(),
-            record.market_cap_usd.unwrap_or(0.0).to_string(),
-            record.price.unwrap_or(0.0).to_string(),
-            record.exchange.clone().unwrap_or_default(),
-            if record.active.unwrap_or(true) {
-                "true".to_string()
-            } else {
-                "false".to_string()
-            },
-            record.description.clone().unwrap_or_default(),
-            record.homepage_url.clone().unwrap_or_default(),
-            record.employees.map(|e| e.to_string()).unwrap_or_default(),
-            date_str.to_string(),
-        ])?;
-    }
+    let records: Vec<MarketCapRecord> = rows
+        .iter()
+        .enumerate()
+        .map(|(index, (record, figures, overridden))| {
+            MarketCapRecord {
+                market_cap_original: Some(figures.market_cap_original),
+                original_currency: Some(figures.original_currency.clone()),
+                market_cap_eur: Some(figures.market_cap_eur),
+                market_cap_usd: Some(figures.market_cap_usd),
+                price: record.price,
+                exchange: record.exchange.clone(),
+                active: Some(record.active.unwrap_or(true)),
+                description: record.description.clone(),
+                homepage_url: record.homepage_url.clone(),
+                employees: record.employees,
+                overridden: overridden.clone(),
+                ..MarketCapRecord::new(index + 1, timestamp, &record.ticker, &figures.name)
+            }
+            .with_provenance(&record.provenance)
+        })
+        .collect();
+    export_schema::write_csv(std::fs::File::create(&filename)?, &records)?;
 
-    writer.flush()?;
-    println!("✅ Market caps for {} exported to {}", date, filename);
-    println!("   Total companies: {}", records.len());
+    status!("✅ Market caps for {} exported to {}", date, filename);
+    status!("   Total companies: {}", rows.len());
 
-    Ok(())
+    Ok(Some(filename))
 }
@@ -8,11 +8,14 @@ use serde::{Deserialize, Serialize};
 use sqlx::sqlite::SqlitePool;
 use std::collections::HashSet;
 use std::fs;
-use toml::Value;
 
 use crate::api::FMPClient;
+use crate::companies;
+use crate::config;
+use crate::config_editor::ConfigEditor;
+use crate::output::status;
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, sqlx::FromRow)]
 pub struct StoredSymbolChange {
     pub id: Option<i64>,
     pub old_symbol: String,
@@ -28,7 +31,8 @@ pub struct SymbolChangeReport {
     pub pending_changes: Vec<StoredSymbolChange>,
     pub applicable_changes: Vec<StoredSymbolChange>,
     pub non_applicable_changes: Vec<StoredSymbolChange>,
-    pub conflicts: Vec<String>,
+    /// Changes whose old and new symbol are both in our config
+    pub conflicts: Vec<StoredSymbolChange>,
 }
 
 /// Fetch symbol changes from FMP API and store in database
@@ -36,7 +40,7 @@ pub async fn fetch_and_store_symbol_changes(
     pool: &SqlitePool,
     fmp_client: &FMPClient,
 ) -> Result<usize> {
-    println!("Fetching symbol changes from FMP API...");
+    status!("Fetching symbol changes from FMP API...");
     let changes = fmp_client.fetch_symbol_changes().await?;
 
     let mut stored_count = 0;
@@ -63,27 +67,26 @@ pub async fn fetch_and_store_symbol_changes(
         }
     }
 
-    println!("✅ Stored {} new symbol changes", stored_count);
+    status!("✅ Stored {} new symbol changes", stored_count);
     Ok(stored_count)
 }
 
 /// Get all pending (unapplied) symbol changes from database
 pub async fn get_pending_changes(pool: &SqlitePool) -> Result<Vec<StoredSymbolChange>> {
-    let changes = sqlx::query_as!(
-        StoredSymbolChange,
+    let changes = sqlx::query_as::<_, StoredSymbolChange>(
         r#"
         SELECT 
-            id as "id?",
+            id,
             old_symbol,
             new_symbol,
             change_date,
             company_name,
             reason,
-            applied as "applied!"
+            COALESCE(applied, 0) AS applied
         FROM symbol_changes
-        WHERE applied = 0
+        WHERE applied = 0 AND COALESCE(ignored, 0) = 0
         ORDER BY change_date DESC, old_symbol
-        "#
+        "#,
     )
     .fetch_all(pool)
     .await?;
@@ -99,27 +102,12 @@ pub async fn check_ticker_updates(
     let pending_changes = get_pending_changes(pool).await?;
 
     // Read current config
-    let config_content = fs::read_to_string(config_path).context("Failed to read config.toml")?;
-    let config: Value = toml::from_str(&config_content).context("Failed to parse config.toml")?;
-
-    // Extract all current tickers
-    let mut current_tickers = HashSet::new();
-
-    if let Some(us_tickers) = config.get("us_tickers").and_then(|v| v.as_array()) {
-        for ticker in us_tickers {
-            if let Some(ticker_str) = ticker.as_str() {
-                current_tickers.insert(ticker_str.to_string());
-            }
-        }
-    }
-
-    if let Some(non_us_tickers) = config.get("non_us_tickers").and_then(|v| v.as_array()) {
-        for ticker in non_us_tickers {
-            if let Some(ticker_str) = ticker.as_str() {
-                current_tickers.insert(ticker_str.to_string());
-            }
-        }
-    }
+    let config = config::load_config_from(config_path)?;
+    let current_tickers: HashSet<String> = config
+        .us_tickers
+        .into_iter()
+        .chain(config.non_us_tickers)
+        .collect();
 
     // Categorize changes
     let mut applicable_changes = Vec::new();
@@ -129,15 +117,7 @@ pub async fn check_ticker_updates(
     for change in &pending_changes {
         if current_tickers.contains(&change.old_symbol) {
             if current_tickers.contains(&change.new_symbol) {
-                conflicts.push(format!(
-                    "Both {} and {} exist in config (change date: {})",
-                    change.old_symbol,
-                    change.new_symbol,
-                    change
-                        .change_date
-                        .as_ref()
-                        .unwrap_or(&"unknown".to_string())
-                ));
+                conflicts.push(change.clone());
             } else {
                 applicable_changes.push(change.clone());
             }
@@ -154,6 +134,58 @@ pub async fn check_ticker_updates(
     })
 }
 
+/// Copy the config file next to itself before changing it
+fn backup_config(config_path: &str) -> Result<()> {
+    let backup_path = format!(
+        "{}.backup.{}",
+        config_path,
+        Utc::now().format("%Y%m%d_%H%M%S")
+    );
+    fs::copy(config_path, &backup_path).context("Failed to create config backup")?;
+    status!("✅ Created backup at: {}", backup_path);
+    Ok(())
+}
+
+/// Mark a change as applied and link the old and new symbol to one company, together
+/// so a failed link leaves the change pending
+async fn mark_applied(pool: &SqlitePool, change: &StoredSymbolChange) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    sqlx::query!(
+        "UPDATE symbol_changes SET applied = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+        change.id
+    )
+    .execute(&mut *tx)
+    .await?;
+    companies::apply_symbol_change(
+        &mut tx,
+        &change.old_symbol,
+        &change.new_symbol,
+        change.change_date.as_deref(),
+    )
+    .await?;
+    tx.commit().await?;
+
+    status!(
+        "✅ Linked {} to {}; its stored history is read under {}",
+        change.old_symbol,
+        change.new_symbol,
+        change.new_symbol
+    );
+
+    Ok(())
+}
+
+/// Persist a rejected change so it is no longer reported as pending
+async fn mark_ignored(pool: &SqlitePool, change: &StoredSymbolChange) -> Result<()> {
+    sqlx::query(
+        "UPDATE symbol_changes SET ignored = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
+    )
+    .bind(change.id)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
 /// Apply ticker updates to the configuration file
 pub async fn apply_ticker_updates(
     pool: &SqlitePool,
@@ -162,96 +194,221 @@ pub async fn apply_ticker_updates(
     dry_run: bool,
 ) -> Result<()> {
     if changes_to_apply.is_empty() {
-        println!("No changes to apply.");
+        status!("No changes to apply.");
         return Ok(());
     }
 
     // Read current config
-    let config_content = fs::read_to_string(config_path).context("Failed to read config.toml")?;
+    let mut editor = ConfigEditor::open(config_path)?;
 
     if !dry_run {
-        // Create backup
-        let backup_path = format!(
-            "{}.backup.{}",
-            config_path,
-            Utc::now().format("%Y%m%d_%H%M%S")
-        );
-        fs::copy(config_path, &backup_path).context("Failed to create config backup")?;
-        println!("✅ Created backup at: {}", backup_path);
+        backup_config(config_path)?;
     }
 
-    let mut updated_content = config_content.clone();
-
+    let mut renamed = Vec::new();
     for change in &changes_to_apply {
-        println!(
+        status!(
             "Applying change: {} -> {}",
-            change.old_symbol, change.new_symbol
+            change.old_symbol,
+            change.new_symbol
         );
 
-        // Replace the ticker in the config content
-        // Handle both quoted and potential comment scenarios
-        let old_pattern = format!("\"{}\"", change.old_symbol);
-        let new_replacement = format!(
-            "\"{}\" # Changed from {} on {}",
-            change.new_symbol,
+        let change_date = change
+            .change_date
+            .clone()
+            .unwrap_or_else(|| Utc::now().format("%Y-%m-%d").to_string());
+        let note = format!("changed from {} on {}", change.old_symbol, change_date);
+
+        match editor.rename_ticker(&change.old_symbol, &change.new_symbol, Some(&note)) {
+            Ok(_) => renamed.push(change),
+            Err(e) => status!("⚠️  Warning: Skipping {}: {}", change.old_symbol, e),
+        }
+    }
+
+    if dry_run {
+        status!("\n=== DRY RUN - Changes that would be made: ===");
+        status!("{}", editor);
+        status!("=== END DRY RUN ===");
+        return Ok(());
+    }
+
+    // Write updated config
+    editor.save()?;
+    status!("✅ Updated config.toml with {} changes", renamed.len());
+
+    for change in renamed {
+        mark_applied(pool, change).await?;
+    }
+
+    Ok(())
+}
+
+/// Decision taken for a symbol change during review
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReviewDecision {
+    /// Apply the change; for a conflict the old ticker is dropped in favour of the new one
+    Accept,
+    /// Never apply the change; it is stored as ignored
+    Reject,
+    /// Leave the change pending for a later review
+    Defer,
+}
+
+fn prompt_decision(accept_label: &str) -> Result<ReviewDecision> {
+    let choice = dialoguer::Select::new()
+        .with_prompt("Decision")
+        .items(&[
+            accept_label,
+            "Reject - ignore this change from now on",
+            "Defer - decide later",
+        ])
+        .default(2)
+        .interact()?;
+
+    Ok(match choice {
+        0 => ReviewDecision::Accept,
+        1 => ReviewDecision::Reject,
+        _ => ReviewDecision::Defer,
+    })
+}
+
+/// Walk through applicable changes and conflicts one by one, asking what to do with each
+pub async fn review_symbol_changes(
+    pool: &SqlitePool,
+    config_path: &str,
+    report: &SymbolChangeReport,
+) -> Result<()> {
+    let editor = ConfigEditor::open(config_path)?;
+    let total = report.applicable_changes.len() + report.conflicts.len();
+    let mut decisions = Vec::new();
+
+    let changes = report
+        .applicable_changes
+        .iter()
+        .map(|c| (c, false))
+        .chain(report.conflicts.iter().map(|c| (c, true)));
+    for (i, (change, conflict)) in changes.enumerate() {
+        status!(
+            "\n[{}/{}] {} -> {}{}",
+            i + 1,
+            total,
             change.old_symbol,
-            change
-                .change_date
-                .as_ref()
-                .unwrap_or(&Utc::now().format("%Y-%m-%d").to_string())
+            change.new_symbol,
+            if conflict { " ⚠️  conflict" } else { "" }
         );
-
-        if updated_content.contains(&old_pattern) {
-            updated_content = updated_content.replace(&old_pattern, &new_replacement);
-
-            if !dry_run {
-                // Mark as applied in database
-                sqlx::query!(
-                    "UPDATE symbol_changes SET applied = 1, updated_at = CURRENT_TIMESTAMP WHERE id = ?",
-                    change.id
-                )
-                .execute(pool)
-                .await?;
+        status!(
+            "  Company:     {}",
+            change.company_name.as_deref().unwrap_or("Unknown")
+        );
+        status!(
+            "  Change date: {}",
+            change.change_date.as_deref().unwrap_or("unknown")
+        );
+        for symbol in [&change.old_symbol, &change.new_symbol] {
+            if let Some(list) = editor.find(symbol) {
+                status!(
+                    "  In config:   \"{}\" in {}{}",
+                    symbol,
+                    list,
+                    editor
+                        .comment_for(symbol)
+                        .map(|c| format!(" # {}", c))
+                        .unwrap_or_default()
+                );
             }
-        } else {
-            println!(
-                "⚠️  Warning: Could not find {} in config",
-                change.old_symbol
+        }
+
+        let decision = if conflict {
+            status!(
+                "  Both symbols are configured; accepting removes {} and merges its history into {}",
+                change.old_symbol, change.new_symbol
             );
+            prompt_decision("Accept - drop the old ticker and keep the new one")?
+        } else {
+            prompt_decision("Accept - rename the ticker in config.toml")?
+        };
+        decisions.push((change.clone(), decision));
+    }
+
+    apply_review_decisions(pool, config_path, decisions).await
+}
+
+/// Carry out the decisions of a review
+pub async fn apply_review_decisions(
+    pool: &SqlitePool,
+    config_path: &str,
+    decisions: Vec<(StoredSymbolChange, ReviewDecision)>,
+) -> Result<()> {
+    let editor = ConfigEditor::open(config_path)?;
+    let mut renames = Vec::new();
+    let mut merges = Vec::new();
+    let (mut rejected, mut deferred) = (0, 0);
+
+    for (change, decision) in decisions {
+        match decision {
+            ReviewDecision::Accept if editor.find(&change.new_symbol).is_some() => {
+                merges.push(change)
+            }
+            ReviewDecision::Accept => renames.push(change),
+            ReviewDecision::Reject => {
+                mark_ignored(pool, &change).await?;
+                rejected += 1;
+            }
+            ReviewDecision::Defer => deferred += 1,
         }
     }
 
-    if dry_run {
-        println!("\n=== DRY RUN - Changes that would be made: ===");
-        println!("{}", updated_content);
-        println!("=== END DRY RUN ===");
-    } else {
-        // Write updated config
-        fs::write(config_path, updated_content).context("Failed to write updated config")?;
-        println!(
-            "✅ Updated config.toml with {} changes",
-            changes_to_apply.len()
-        );
+    let accepted = merges.len() + renames.len();
+    let renamed_any = !renames.is_empty();
+    if renamed_any {
+        apply_ticker_updates(pool, config_path, renames, false).await?;
+    }
+
+    // Conflicts: the new ticker is already configured, so the old one is dropped
+    if !merges.is_empty() {
+        if !renamed_any {
+            backup_config(config_path)?;
+        }
+        let mut editor = ConfigEditor::open(config_path)?;
+        for change in &merges {
+            editor.remove_ticker(&change.old_symbol)?;
+        }
+        editor.save()?;
+        for change in &merges {
+            status!(
+                "✅ Removed {} from config in favour of {}",
+                change.old_symbol,
+                change.new_symbol
+            );
+            mark_applied(pool, change).await?;
+        }
     }
 
+    status!(
+        "\nReview complete: {} accepted, {} rejected, {} deferred",
+        accepted,
+        rejected,
+        deferred
+    );
+
     Ok(())
 }
 
 /// Generate a detailed report of symbol changes
 pub fn print_symbol_change_report(report: &SymbolChangeReport) {
-    println!("\n=== Symbol Change Report ===");
-    println!("Total pending changes: {}", report.pending_changes.len());
-    println!(
+    status!("\n=== Symbol Change Report ===");
+    status!("Total pending changes: {}", report.pending_changes.len());
+    status!(
         "Applicable to our config: {}",
         report.applicable_changes.len()
     );
-    println!("Not applicable: {}", report.non_applicable_changes.len());
-    println!("Conflicts: {}", report.conflicts.len());
+    status!("Not applicable: {}", report.non_applicable_changes.len());
+    status!("Conflicts: {}", report.conflicts.len());
 
     if !report.applicable_changes.is_empty() {
-        println!("\n📝 Applicable Changes:");
+        status!("\n📝 Applicable Changes:");
         for change in &report.applicable_changes {
-            println!(
+            status!(
                 "  {} -> {} ({})",
                 change.old_symbol,
                 change.new_symbol,
@@ -264,16 +421,21 @@ pub fn print_symbol_change_report(report: &SymbolChangeReport) {
     }
 
     if !report.conflicts.is_empty() {
-        println!("\n⚠️  Conflicts:");
+        status!("\n⚠️  Conflicts:");
         for conflict in &report.conflicts {
-            println!("  {}", conflict);
+            status!(
+                "  Both {} and {} exist in config (change date: {})",
+                conflict.old_symbol,
+                conflict.new_symbol,
+                conflict.change_date.as_deref().unwrap_or("unknown")
+            );
         }
     }
 
     if !report.non_applicable_changes.is_empty() && report.non_applicable_changes.len() <= 10 {
-        println!("\n📋 Non-applicable changes (not in our config):");
+        status!("\n📋 Non-applicable changes (not in our config):");
         for change in &report.non_applicable_changes {
-            println!(
+            status!(
                 "  {} -> {} ({})",
                 change.old_symbol,
                 change.new_symbol,
@@ -284,9 +446,85 @@ pub fn print_symbol_change_report(report: &SymbolChangeReport) {
             );
         }
     } else if !report.non_applicable_changes.is_empty() {
-        println!(
+        status!(
             "\n📋 {} non-applicable changes (not in our config)",
             report.non_applicable_changes.len()
         );
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sqlx::sqlite::SqlitePoolOptions;
+
+    #[tokio::test]
+    async fn test_review_decisions() {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let config_path = dir.path().join("config.toml");
+        fs::write(
+            &config_path,
+            "non_us_tickers = []\nus_tickers = [\n    \"AAA\", # Alpha\n    \"BBB\", # Beta\n    \"CCC\", # Gamma\n    \"DDD\", # Gamma new\n]\n",
+        )
+        .unwrap();
+        let config_path = config_path.to_str().unwrap();
+
+        for (old, new) in [("AAA", "AAA2"), ("BBB", "BBB2"), ("CCC", "DDD")] {
+            sqlx::query(
+                "INSERT INTO symbol_changes (old_symbol, new_symbol, change_date) VALUES (?, ?, '2025-01-01')",
+            )
+            .bind(old)
+            .bind(new)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+
+        let report = check_ticker_updates(&pool, config_path).await.unwrap();
+        assert_eq!(report.applicable_changes.len(), 2);
+        assert_eq!(report.conflicts.len(), 1);
+
+        let mut decisions: Vec<_> = report
+            .applicable_changes
+            .iter()
+            .chain(&report.conflicts)
+            .map(|change| {
+                let decision = match change.old_symbol.as_str() {
+                    "BBB" => ReviewDecision::Reject,
+                    _ => ReviewDecision::Accept,
+                };
+                (change.clone(), decision)
+            })
+            .collect();
+        decisions.sort_by(|a, b| a.0.old_symbol.cmp(&b.0.old_symbol));
+        apply_review_decisions(&pool, config_path, decisions)
+            .await
+            .unwrap();
+
+        let config = config::load_config_from(config_path).unwrap();
+        assert_eq!(config.us_tickers, vec!["AAA2", "BBB", "DDD"]);
+
+        let flags = sqlx::query_as::<_, (String, i64, i64)>(
+            "SELECT old_symbol, applied, ignored FROM symbol_changes ORDER BY old_symbol",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        assert_eq!(
+            flags,
+            vec![
+                ("AAA".to_string(), 1, 0),
+                ("BBB".to_string(), 0, 1),
+                ("CCC".to_string(), 1, 0),
+            ]
+        );
+        assert!(get_pending_changes(&pool).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,561 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::{Context, Result};
+use image::imageops::FilterType;
+use image::RgbaImage;
+use plotters::style::{FontDesc, IntoFont, RGBColor};
+use serde::Deserialize;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+/// A colour given as `#RRGGBB` in the theme file
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct HexColor(pub RGBColor);
+
+impl TryFrom<String> for HexColor {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        let hex = value.trim_start_matches('#');
+        // Checked before slicing, which would panic inside a multibyte character
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            anyhow::bail!("Invalid colour '{}', expected #RRGGBB", value);
+        }
+        let channel = |i: usize| {
+            u8::from_str_radix(&hex[i..i + 2], 16)
+                .with_context(|| format!("Invalid colour '{}', expected #RRGGBB", value))
+        };
+        Ok(HexColor(RGBColor(channel(0)?, channel(2)?, channel(4)?)))
+    }
+}
+
+/// Largest height a logo is drawn at, in pixels; larger images are scaled down
+const LOGO_MAX_HEIGHT: u32 = 48;
+
+/// An image drawn in the corner of every chart, given as a PNG or JPEG path in the
+/// theme file
+#[derive(Debug, Clone, Deserialize)]
+#[serde(try_from = "PathBuf")]
+pub struct Logo {
+    pub image: Arc<RgbaImage>,
+}
+
+impl TryFrom<PathBuf> for Logo {
+    type Error = anyhow::Error;
+
+    fn try_from(path: PathBuf) -> Result<Self> {
+        let mut image =
+            image::open(&path).with_context(|| format!("Failed to read logo {:?}", path))?;
+        if image.height() > LOGO_MAX_HEIGHT {
+            image = image.resize(u32::MAX, LOGO_MAX_HEIGHT, FilterType::Triangle);
+        }
+        Ok(Logo {
+            image: Arc::new(image.to_rgba8()),
+        })
+    }
+}
+
+impl Logo {
+    /// RGB pixels with transparent areas blended into `background`
+    pub fn rgb_on(&self, background: RGBColor) -> Vec<u8> {
+        let RGBColor(r, g, b) = background;
+        self.image
+            .pixels()
+            .flat_map(|pixel| {
+                let [pr, pg, pb, alpha] = pixel.0;
+                let alpha = alpha as f64 / 255.0;
+                let blend =
+                    |fg: u8, bg: u8| (fg as f64 * alpha + bg as f64 * (1.0 - alpha)).round() as u8;
+                [blend(pr, r), blend(pg, g), blend(pb, b)]
+            })
+            .collect()
+    }
+}
+
+/// Colours, fonts and watermark used by all charts
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// Categorical colours for series such as the top 10 companies
+    pub palette: Vec<HexColor>,
+    pub positive: HexColor,
+    pub negative: HexColor,
+    pub rank_up: HexColor,
+    pub rank_down: HexColor,
+    pub neutral: HexColor,
+    pub muted: HexColor,
+    pub panel: HexColor,
+    pub text: HexColor,
+    pub background: HexColor,
+    pub font_family: String,
+    pub title_size: u32,
+    /// Multiplier applied to every other font size
+    pub font_scale: f64,
+    /// Text drawn faintly in the bottom right corner of each chart
+    pub watermark: Option<String>,
+    /// Image drawn in the bottom right corner of each chart, with the watermark text
+    /// to its left
+    pub logo: Option<Logo>,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            palette: vec![
+                HexColor(RGBColor(59, 130, 246)),  // blue
+                HexColor(RGBColor(16, 185, 129)),  // emerald
+                HexColor(RGBColor(245, 158, 11)),  // amber
+                HexColor(RGBColor(244, 63, 94)),   // rose
+                HexColor(RGBColor(139, 92, 246)),  // purple
+                HexColor(RGBColor(236, 72, 153)),  // pink
+                HexColor(RGBColor(20, 184, 166)),  // teal
+                HexColor(RGBColor(249, 115, 22)),  // orange
+                HexColor(RGBColor(132, 204, 22)),  // lime
+                HexColor(RGBColor(100, 116, 139)), // slate
+            ],
+            positive: HexColor(RGBColor(16, 185, 129)),
+            negative: HexColor(RGBColor(244, 63, 94)),
+            rank_up: HexColor(RGBColor(20, 184, 166)),
+            rank_down: HexColor(RGBColor(251, 113, 133)),
+            neutral: HexColor(RGBColor(148, 163, 184)),
+            muted: HexColor(RGBColor(100, 116, 139)),
+            panel: HexColor(RGBColor(243, 244, 246)),
+            text: HexColor(RGBColor(0, 0, 0)),
+            background: HexColor(RGBColor(255, 255, 255)),
+            font_family: "sans-serif".to_string(),
+            title_size: 32,
+            font_scale: 1.0,
+            watermark: None,
+            logo: None,
+        }
+    }
+}
+
+impl Theme {
+    /// Colour for the n-th series, cycling through the palette
+    pub fn series_color(&self, index: usize) -> RGBColor {
+        if self.palette.is_empty() {
+            return self.muted.0;
+        }
+        self.palette[index % self.palette.len()].0
+    }
+
+    /// Font in the theme family, with `size` scaled by `font_scale`
+    pub fn font(&self, size: u32) -> FontDesc<'_> {
+        let scaled = (size as f64 * self.font_scale).round().max(1.0);
+        (self.font_family.as_str(), scaled).into_font()
+    }
+
+    /// Font used for chart titles
+    pub fn title_font(&self) -> FontDesc<'_> {
+        (self.font_family.as_str(), self.title_size as f64).into_font()
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    #[default]
+    En,
+    Nl,
+}
+
+/// Translated text used in charts and reports
+#[derive(Debug)]
+pub struct Labels {
+    pub to: &'static str,
+    pub others: &'static str,
+    pub percentage_change_axis: &'static str,
+    pub gainers_losers_title: &'static str,
+    pub distribution_title: &'static str,
+    pub treemap_title: &'static str,
+    pub change_since: &'static str,
+    pub total_market_cap: &'static str,
+    pub rank_movements_title: &'static str,
+    pub biggest_rank_improvements: &'static str,
+    pub biggest_rank_declines: &'static str,
+    pub market_summary_title: &'static str,
+    pub total_market_cap_change: &'static str,
+    pub companies_analyzed: &'static str,
+    pub movement_distribution: &'static str,
+    pub gainers: &'static str,
+    pub losers: &'static str,
+    pub unchanged: &'static str,
+    pub key_statistics: &'static str,
+    pub bar_race_title: &'static str,
+    pub average_change: &'static str,
+    pub top_gainer: &'static str,
+    pub top_loser: &'static str,
+    pub generated_on: &'static str,
+    pub comparison_title: &'static str,
+    pub overview_statistics: &'static str,
+    pub total_market_cap_on: &'static str,
+    pub total_change: &'static str,
+    pub top_gainers_pct: &'static str,
+    pub top_losers_pct: &'static str,
+    pub top_absolute_gain: &'static str,
+    pub top_absolute_loss: &'static str,
+    pub increase: &'static str,
+    pub decrease: &'static str,
+    pub gain: &'static str,
+    pub loss: &'static str,
+    pub positions: &'static str,
+    pub concentration_analysis: &'static str,
+    pub companies_increased: &'static str,
+    pub companies_decreased: &'static str,
+    pub new_companies: &'static str,
+    pub companies_removed: &'static str,
+    pub million_suffix: &'static str,
+    pub billion_suffix: &'static str,
+    pub trillion_suffix: &'static str,
+}
+
+const ENGLISH: Labels = Labels {
+    to: "to",
+    others: "Others",
+    percentage_change_axis: "Percentage Change (%)",
+    gainers_losers_title: "Top Gainers and Losers",
+    distribution_title: "Market Cap Distribution",
+    treemap_title: "Market Cap Treemap",
+    change_since: "change since",
+    total_market_cap: "Total Market Cap",
+    rank_movements_title: "Rank Movements",
+    biggest_rank_improvements: "Biggest Rank Improvements",
+    biggest_rank_declines: "Biggest Rank Declines",
+    market_summary_title: "Market Summary",
+    total_market_cap_change: "Total Market Cap Change",
+    companies_analyzed: "Companies Analyzed",
+    movement_distribution: "Market Movement Distribution",
+    gainers: "Gainers",
+    losers: "Losers",
+    unchanged: "Unchanged",
+    key_statistics: "Key Statistics",
+    bar_race_title: "Market Cap Ranking",
+    average_change: "Average Change",
+    top_gainer: "Top Gainer",
+    top_loser: "Top Loser",
+    generated_on: "Generated on",
+    comparison_title: "Market Cap Comparison",
+    overview_statistics: "Overview Statistics",
+    total_market_cap_on: "Total Market Cap on",
+    total_change: "Total Change",
+    top_gainers_pct: "Top 10 Gainers (by percentage)",
+    top_losers_pct: "Top 10 Losers (by percentage)",
+    top_absolute_gain: "Top 10 by Absolute Gain",
+    top_absolute_loss: "Top 10 by Absolute Loss",
+    increase: "increase",
+    decrease: "decrease",
+    gain: "gain",
+    loss: "loss",
+    positions: "positions",
+    concentration_analysis: "Market Concentration Analysis",
+    companies_increased: "Companies with increased market cap",
+    companies_decreased: "Companies with decreased market cap",
+    new_companies: "New companies in list",
+    companies_removed: "Companies no longer in list",
+    million_suffix: "M",
+    billion_suffix: "B",
+    trillion_suffix: "T",
+};
+
+const DUTCH: Labels = Labels {
+    to: "tot",
+    others: "Overige",
+    percentage_change_axis: "Procentuele verandering (%)",
+    gainers_losers_title: "Grootste stijgers en dalers",
+    distribution_title: "Verdeling beurswaarde",
+    treemap_title: "Beurswaarde treemap",
+    change_since: "verandering sinds",
+    total_market_cap: "Totale beurswaarde",
+    rank_movements_title: "Verschuivingen in ranglijst",
+    biggest_rank_improvements: "Grootste stijgers in ranglijst",
+    biggest_rank_declines: "Grootste dalers in ranglijst",
+    market_summary_title: "Marktoverzicht",
+    total_market_cap_change: "Verandering totale beurswaarde",
+    companies_analyzed: "Geanalyseerde bedrijven",
+    movement_distribution: "Verdeling van koersbewegingen",
+    gainers: "Stijgers",
+    losers: "Dalers",
+    unchanged: "Onveranderd",
+    key_statistics: "Kerncijfers",
+    bar_race_title: "Ranglijst beurswaarde",
+    average_change: "Gemiddelde verandering",
+    top_gainer: "Grootste stijger",
+    top_loser: "Grootste daler",
+    generated_on: "Gegenereerd op",
+    comparison_title: "Vergelijking beurswaarde",
+    overview_statistics: "Overzicht",
+    total_market_cap_on: "Totale beurswaarde op",
+    total_change: "Totale verandering",
+    top_gainers_pct: "Top 10 stijgers (procentueel)",
+    top_losers_pct: "Top 10 dalers (procentueel)",
+    top_absolute_gain: "Top 10 absolute stijging",
+    top_absolute_loss: "Top 10 absolute daling",
+    increase: "stijging",
+    decrease: "daling",
+    gain: "winst",
+    loss: "verlies",
+    positions: "posities",
+    concentration_analysis: "Marktconcentratie",
+    companies_increased: "Bedrijven met gestegen beurswaarde",
+    companies_decreased: "Bedrijven met gedaalde beurswaarde",
+    new_companies: "Nieuwe bedrijven in de lijst",
+    companies_removed: "Bedrijven niet meer in de lijst",
+    million_suffix: " mln",
+    billion_suffix: " mld",
+    trillion_suffix: " bln",
+};
+
+/// Magnitude used when formatting large amounts
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Magnitude {
+    Millions,
+    Billions,
+    Trillions,
+}
+
+/// Number format and language for charts and reports
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Locale {
+    pub language: Language,
+    pub decimal_separator: char,
+    /// May be empty to disable digit grouping
+    pub thousands_separator: String,
+}
+
+impl Default for Locale {
+    fn default() -> Self {
+        Self {
+            language: Language::En,
+            decimal_separator: '.',
+            thousands_separator: ",".to_string(),
+        }
+    }
+}
+
+impl Locale {
+    pub fn labels(&self) -> &'static Labels {
+        match self.language {
+            Language::En => &ENGLISH,
+            Language::Nl => &DUTCH,
+        }
+    }
+
+    /// Format a number with the configured separators
+    pub fn format_number(&self, value: f64, decimals: usize) -> String {
+        let formatted = format!("{:.*}", decimals, value.abs());
+        let (integer, fraction) = match formatted.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (formatted.as_str(), None),
+        };
+
+        let mut grouped = String::new();
+        for (i, digit) in integer.chars().enumerate() {
+            if i > 0 && (integer.len() - i) % 3 == 0 {
+                grouped.push_str(&self.thousands_separator);
+            }
+            grouped.push(digit);
+        }
+
+        let mut result = String::new();
+        // Avoid printing "-0.00" for values that round to zero
+        if value < 0.0 && formatted.chars().any(|c| c.is_ascii_digit() && c != '0') {
+            result.push('-');
+        }
+        result.push_str(&grouped);
+        if let Some(fraction) = fraction {
+            result.push(self.decimal_separator);
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// Format a percentage, e.g. `12.34%`
+    pub fn format_percent(&self, value: f64, decimals: usize) -> String {
+        format!("{}%", self.format_number(value, decimals))
+    }
+
+    /// Format an amount in an ISO currency in the given magnitude, e.g. `$1.23B`. The
+    /// symbol always follows the currency of the figures, not the locale.
+    pub fn format_money(
+        &self,
+        value: f64,
+        currency: &str,
+        magnitude: Magnitude,
+        decimals: usize,
+    ) -> String {
+        let labels = self.labels();
+        let (divisor, suffix) = match magnitude {
+            Magnitude::Millions => (1_000_000.0, labels.million_suffix),
+            Magnitude::Billions => (1_000_000_000.0, labels.billion_suffix),
+            Magnitude::Trillions => (1_000_000_000_000.0, labels.trillion_suffix),
+        };
+        let symbol = currency_symbol(currency);
+        let number = self.format_number(value / divisor, decimals);
+        match number.strip_prefix('-') {
+            Some(unsigned) => format!("-{}{}{}", symbol, unsigned, suffix),
+            None => format!("{}{}{}", symbol, number, suffix),
+        }
+    }
+}
+
+/// Symbol of an ISO currency code, or the code followed by a space if it has none
+fn currency_symbol(currency: &str) -> String {
+    match currency {
+        "USD" => "$".to_string(),
+        "EUR" => "€".to_string(),
+        "GBP" => "£".to_string(),
+        "JPY" => "¥".to_string(),
+        other => format!("{} ", other),
+    }
+}
+
+/// Contents of the theme file
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct Presentation {
+    pub theme: Theme,
+    pub locale: Locale,
+}
+
+fn get_theme_path() -> PathBuf {
+    let mut path = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    path.push("theme.toml");
+    path
+}
+
+/// Load the theme file at `path`, or `theme.toml` if it exists, otherwise the defaults
+pub fn load_presentation(path: Option<&str>) -> Result<Presentation> {
+    let theme_path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let default_path = get_theme_path();
+            if !default_path.exists() {
+                return Ok(Presentation::default());
+            }
+            default_path
+        }
+    };
+
+    let content = fs::read_to_string(&theme_path)
+        .with_context(|| format!("Failed to read theme file {:?}", theme_path))?;
+    let presentation: Presentation = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse theme file {:?}", theme_path))?;
+    Ok(presentation)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_number() {
+        let en = Locale::default();
+        assert_eq!(en.format_number(1234567.891, 2), "1,234,567.89");
+        assert_eq!(en.format_number(-1234.5, 1), "-1,234.5");
+        assert_eq!(en.format_number(999.0, 0), "999");
+        assert_eq!(en.format_number(-0.001, 2), "0.00");
+
+        let nl = Locale {
+            language: Language::Nl,
+            decimal_separator: ',',
+            thousands_separator: ".".to_string(),
+        };
+        assert_eq!(nl.format_number(1234567.891, 2), "1.234.567,89");
+        assert_eq!(nl.format_percent(-12.345, 2), "-12,35%");
+    }
+
+    #[test]
+    fn test_format_money() {
+        let en = Locale::default();
+        assert_eq!(
+            en.format_money(1_230_000_000.0, "USD", Magnitude::Billions, 2),
+            "$1.23B"
+        );
+        assert_eq!(
+            en.format_money(-5_500_000.0, "USD", Magnitude::Millions, 1),
+            "-$5.5M"
+        );
+
+        let nl = Locale {
+            language: Language::Nl,
+            decimal_separator: ',',
+            thousands_separator: ".".to_string(),
+        };
+        // The locale formats the number; the symbol stays that of the figures
+        assert_eq!(
+            nl.format_money(1_230_000_000.0, "USD", Magnitude::Billions, 2),
+            "$1,23 mld"
+        );
+        assert_eq!(
+            nl.format_money(1_230_000_000.0, "EUR", Magnitude::Billions, 2),
+            "€1,23 mld"
+        );
+        assert_eq!(
+            en.format_money(2_000_000.0, "SEK", Magnitude::Millions, 0),
+            "SEK 2M"
+        );
+    }
+
+    #[test]
+    fn test_presentation_from_toml() {
+        let presentation: Presentation = toml::from_str(
+            r##"
+            [theme]
+            positive = "#00FF00"
+            font_family = "Helvetica"
+            watermark = "FashionUnited"
+
+            [locale]
+            language = "nl"
+            decimal_separator = ","
+            thousands_separator = "."
+            "##,
+        )
+        .unwrap();
+
+        assert_eq!(presentation.theme.positive, HexColor(RGBColor(0, 255, 0)));
+        assert_eq!(presentation.theme.font_family, "Helvetica");
+        assert_eq!(presentation.theme.title_size, 32);
+        assert_eq!(presentation.locale.language, Language::Nl);
+        assert_eq!(presentation.locale.decimal_separator, ',');
+        assert_eq!(presentation.locale.labels().others, "Overige");
+    }
+
+    #[test]
+    fn test_invalid_hex_color() {
+        assert!(HexColor::try_from("#12345".to_string()).is_err());
+        assert!(HexColor::try_from("#GG0000".to_string()).is_err());
+        assert!(HexColor::try_from("#ééé".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_logo_is_scaled_and_blended() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logo.png");
+        // Left half opaque red, right half transparent
+        let image = RgbaImage::from_fn(192, 96, |x, _| {
+            if x < 96 {
+                image::Rgba([255, 0, 0, 255])
+            } else {
+                image::Rgba([0, 0, 0, 0])
+            }
+        });
+        image.save(&path).unwrap();
+
+        let logo = Logo::try_from(path).unwrap();
+        assert_eq!(logo.image.dimensions(), (96, LOGO_MAX_HEIGHT));
+
+        let pixels = logo.rgb_on(RGBColor(255, 255, 255));
+        assert_eq!(pixels.len(), 96 * 48 * 3);
+        assert_eq!(&pixels[..3], &[255, 0, 0]);
+        assert_eq!(&pixels[pixels.len() - 3..], &[255, 255, 255]);
+
+        assert!(Logo::try_from(dir.path().join("missing.png")).is_err());
+    }
+}
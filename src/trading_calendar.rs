@@ -0,0 +1,787 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, Utc, Weekday};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::config::CalendarConfig;
+use crate::output::status;
+
+/// Exchange of tickers without a known suffix, i.e. US listings
+pub const DEFAULT_EXCHANGE: &str = "NYSE";
+
+/// Country of tickers without a known suffix, i.e. US listings
+pub const DEFAULT_COUNTRY: &str = "US";
+
+/// How far back `last_trading_day` looks before giving up
+const MAX_LOOKBACK_DAYS: u32 = 14;
+
+/// Holidays observed by many exchanges, built into the calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Closure {
+    NewYear,
+    /// 2 and 3 January, closed in Tokyo
+    NewYearHolidays,
+    /// Third Monday of January
+    MartinLutherKingDay,
+    /// Third Monday of February
+    PresidentsDay,
+    /// Third Monday of February in most Canadian provinces
+    FamilyDay,
+    GoodFriday,
+    EasterMonday,
+    /// 1 May
+    LabourDay,
+    /// First Monday of May in the UK
+    EarlyMayBankHoliday,
+    AscensionDay,
+    WhitMonday,
+    /// Monday before 25 May
+    VictoriaDay,
+    /// Last Monday of May
+    MemorialDay,
+    /// Last Monday of May in the UK
+    SpringBankHoliday,
+    /// 19 June, observed by the NYSE since 2022
+    Juneteenth,
+    /// 1 July
+    CanadaDay,
+    /// 4 July
+    IndependenceDay,
+    /// First Monday of August in Canada
+    CivicHoliday,
+    /// Last Monday of August in the UK
+    SummerBankHoliday,
+    /// First Monday of September in the US and Canada
+    LaborDay,
+    /// Second Monday of October
+    CanadianThanksgiving,
+    /// Fourth Thursday of November
+    Thanksgiving,
+    ChristmasEve,
+    Christmas,
+    BoxingDay,
+    NewYearsEve,
+}
+
+use Closure::*;
+
+/// How an exchange makes up for a holiday that falls on its weekend
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Observance {
+    /// The holiday is not made up
+    None,
+    /// NYSE rule: a Saturday holiday is observed on Friday and a Sunday holiday on
+    /// Monday, except New Year's Day on a Saturday, which is not observed
+    NearestWeekday,
+    /// Substitute day: the next weekday that is not already a holiday, so Christmas
+    /// on a Saturday closes Monday and Boxing Day on the Sunday closes Tuesday
+    NextWeekday,
+}
+
+const NYSE: &[Closure] = &[
+    NewYear,
+    MartinLutherKingDay,
+    PresidentsDay,
+    GoodFriday,
+    MemorialDay,
+    Juneteenth,
+    IndependenceDay,
+    LaborDay,
+    Thanksgiving,
+    Christmas,
+];
+const UK: &[Closure] = &[
+    NewYear,
+    GoodFriday,
+    EasterMonday,
+    EarlyMayBankHoliday,
+    SpringBankHoliday,
+    SummerBankHoliday,
+    Christmas,
+    BoxingDay,
+];
+const CANADA: &[Closure] = &[
+    NewYear,
+    FamilyDay,
+    GoodFriday,
+    VictoriaDay,
+    CanadaDay,
+    CivicHoliday,
+    LaborDay,
+    CanadianThanksgiving,
+    Christmas,
+    BoxingDay,
+];
+const WESTERN: &[Closure] = &[NewYear, GoodFriday, Christmas, BoxingDay];
+/// Continental exchanges that also close on Easter Monday and 1 May
+const EUROPEAN: &[Closure] = &[
+    NewYear,
+    GoodFriday,
+    EasterMonday,
+    LabourDay,
+    Christmas,
+    BoxingDay,
+];
+/// Exchanges that also close on Christmas Eve and New Year's Eve
+const EUROPEAN_YEAR_END: &[Closure] = &[
+    NewYear,
+    GoodFriday,
+    EasterMonday,
+    LabourDay,
+    ChristmasEve,
+    Christmas,
+    BoxingDay,
+    NewYearsEve,
+];
+/// Year-end closers that also close on Ascension Day and Whit Monday
+const ALPINE_NORDIC: &[Closure] = &[
+    NewYear,
+    GoodFriday,
+    EasterMonday,
+    LabourDay,
+    AscensionDay,
+    WhitMonday,
+    ChristmasEve,
+    Christmas,
+    BoxingDay,
+    NewYearsEve,
+];
+const SAT_SUN: [Weekday; 2] = [Weekday::Sat, Weekday::Sun];
+
+struct Exchange {
+    code: &'static str,
+    /// Ticker suffixes after the last dot with the country they are listed in, e.g.
+    /// ("PA", "FR") for MC.PA
+    suffixes: &'static [(&'static str, &'static str)],
+    weekend: [Weekday; 2],
+    closures: &'static [Closure],
+    observance: Observance,
+}
+
+const EXCHANGES: &[Exchange] = &[
+    Exchange {
+        code: "NYSE",
+        suffixes: &[],
+        weekend: SAT_SUN,
+        closures: NYSE,
+        observance: Observance::NearestWeekday,
+    },
+    Exchange {
+        code: "LSE",
+        suffixes: &[("L", "GB")],
+        weekend: SAT_SUN,
+        closures: UK,
+        observance: Observance::NextWeekday,
+    },
+    Exchange {
+        code: "EURONEXT",
+        suffixes: &[
+            ("PA", "FR"),
+            ("AS", "NL"),
+            ("BR", "BE"),
+            ("LS", "PT"),
+            ("IR", "IE"),
+        ],
+        weekend: SAT_SUN,
+        closures: EUROPEAN,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "BORSA_ITALIANA",
+        suffixes: &[("MI", "IT")],
+        weekend: SAT_SUN,
+        closures: EUROPEAN_YEAR_END,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "XETRA",
+        suffixes: &[("DE", "DE"), ("F", "DE")],
+        weekend: SAT_SUN,
+        closures: EUROPEAN_YEAR_END,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "SIX",
+        suffixes: &[("SW", "CH")],
+        weekend: SAT_SUN,
+        closures: ALPINE_NORDIC,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "NASDAQ_NORDIC",
+        suffixes: &[("ST", "SE"), ("HE", "FI"), ("CO", "DK")],
+        weekend: SAT_SUN,
+        closures: ALPINE_NORDIC,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "OSLO",
+        suffixes: &[("OL", "NO")],
+        weekend: SAT_SUN,
+        closures: ALPINE_NORDIC,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "BME",
+        suffixes: &[("MC", "ES")],
+        weekend: SAT_SUN,
+        closures: EUROPEAN,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "WSE",
+        suffixes: &[("WA", "PL")],
+        weekend: SAT_SUN,
+        closures: EUROPEAN,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "WIENER_BOERSE",
+        suffixes: &[("VI", "AT")],
+        weekend: SAT_SUN,
+        closures: EUROPEAN_YEAR_END,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "BIST",
+        suffixes: &[("IS", "TR")],
+        weekend: SAT_SUN,
+        closures: &[NewYear],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "TASE",
+        suffixes: &[("TA", "IL")],
+        weekend: SAT_SUN,
+        closures: &[],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "TADAWUL",
+        suffixes: &[("SR", "SA")],
+        weekend: [Weekday::Fri, Weekday::Sat],
+        closures: &[],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "JSE",
+        suffixes: &[("JO", "ZA")],
+        weekend: SAT_SUN,
+        closures: WESTERN,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "TSX",
+        suffixes: &[("TO", "CA"), ("V", "CA")],
+        weekend: SAT_SUN,
+        closures: CANADA,
+        observance: Observance::NextWeekday,
+    },
+    Exchange {
+        code: "B3",
+        suffixes: &[("SA", "BR")],
+        weekend: SAT_SUN,
+        closures: &[NewYear, GoodFriday, ChristmasEve, Christmas, NewYearsEve],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "TSE",
+        suffixes: &[("T", "JP")],
+        weekend: SAT_SUN,
+        closures: &[NewYear, NewYearHolidays, NewYearsEve],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "HKEX",
+        suffixes: &[("HK", "HK")],
+        weekend: SAT_SUN,
+        closures: WESTERN,
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "SSE",
+        suffixes: &[("SS", "CN")],
+        weekend: SAT_SUN,
+        closures: &[NewYear],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "SZSE",
+        suffixes: &[("SZ", "CN")],
+        weekend: SAT_SUN,
+        closures: &[NewYear],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "KRX",
+        suffixes: &[("KS", "KR"), ("KQ", "KR")],
+        weekend: SAT_SUN,
+        closures: &[NewYear, Christmas, NewYearsEve],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "TWSE",
+        suffixes: &[("TW", "TW")],
+        weekend: SAT_SUN,
+        closures: &[NewYear],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "NSE",
+        suffixes: &[("NS", "IN"), ("BO", "IN")],
+        weekend: SAT_SUN,
+        closures: &[GoodFriday, Christmas],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "PSX",
+        suffixes: &[("PK", "PK")],
+        weekend: SAT_SUN,
+        closures: &[Christmas],
+        observance: Observance::None,
+    },
+    Exchange {
+        code: "ASX",
+        suffixes: &[("AX", "AU")],
+        weekend: SAT_SUN,
+        closures: WESTERN,
+        observance: Observance::NextWeekday,
+    },
+];
+
+fn exchange_info(code: &str) -> Option<&'static Exchange> {
+    EXCHANGES.iter().find(|e| e.code.eq_ignore_ascii_case(code))
+}
+
+/// Exchange and listing country of a ticker suffix
+fn listing_for_ticker(ticker: &str) -> Option<(&'static Exchange, &'static str)> {
+    let (_, suffix) = ticker.rsplit_once('.')?;
+    EXCHANGES.iter().find_map(|e| {
+        e.suffixes
+            .iter()
+            .find(|(s, _)| s.eq_ignore_ascii_case(suffix))
+            .map(|(_, country)| (e, *country))
+    })
+}
+
+/// Exchange a ticker trades on, from its suffix (MC.PA → EURONEXT); tickers without a
+/// known suffix are US listings
+pub fn exchange_for_ticker(ticker: &str) -> &'static str {
+    listing_for_ticker(ticker)
+        .map(|(e, _)| e.code)
+        .unwrap_or(DEFAULT_EXCHANGE)
+}
+
+/// Country a ticker is listed in, from its suffix (MC.PA → FR); tickers without a
+/// known suffix are US listings
+pub fn country_for_ticker(ticker: &str) -> &'static str {
+    listing_for_ticker(ticker)
+        .map(|(_, country)| country)
+        .unwrap_or(DEFAULT_COUNTRY)
+}
+
+/// Easter Sunday of a year (anonymous Gregorian algorithm)
+fn easter_sunday(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).expect("valid Easter date")
+}
+
+fn ymd(year: i32, month: u32, day: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(year, month, day)
+}
+
+/// The n-th (1-based) weekday of a month
+fn nth_weekday(year: i32, month: u32, weekday: Weekday, n: u8) -> Option<NaiveDate> {
+    NaiveDate::from_weekday_of_month_opt(year, month, weekday, n)
+}
+
+/// The last weekday of a month
+fn last_weekday(year: i32, month: u32, weekday: Weekday) -> Option<NaiveDate> {
+    nth_weekday(year, month, weekday, 5).or_else(|| nth_weekday(year, month, weekday, 4))
+}
+
+/// The weekday on or before a date
+fn weekday_on_or_before(date: NaiveDate, weekday: Weekday) -> NaiveDate {
+    let back = (7 + date.weekday().num_days_from_monday() - weekday.num_days_from_monday()) % 7;
+    date - Duration::days(back as i64)
+}
+
+impl Closure {
+    /// Dates of the holiday in a year, before any weekend observance
+    fn dates(self, year: i32) -> Vec<NaiveDate> {
+        let easter = easter_sunday(year);
+        let date = match self {
+            NewYear => ymd(year, 1, 1),
+            NewYearHolidays => return [2, 3].iter().filter_map(|&d| ymd(year, 1, d)).collect(),
+            MartinLutherKingDay => nth_weekday(year, 1, Weekday::Mon, 3),
+            PresidentsDay | FamilyDay => nth_weekday(year, 2, Weekday::Mon, 3),
+            GoodFriday => Some(easter - Duration::days(2)),
+            EasterMonday => Some(easter + Duration::days(1)),
+            LabourDay => ymd(year, 5, 1),
+            // Moved for the VE Day anniversary in 2020
+            EarlyMayBankHoliday if year == 2020 => ymd(2020, 5, 8),
+            EarlyMayBankHoliday => nth_weekday(year, 5, Weekday::Mon, 1),
+            AscensionDay => Some(easter + Duration::days(39)),
+            WhitMonday => Some(easter + Duration::days(50)),
+            VictoriaDay => ymd(year, 5, 24).map(|d| weekday_on_or_before(d, Weekday::Mon)),
+            MemorialDay => last_weekday(year, 5, Weekday::Mon),
+            // Moved for the Golden, Diamond and Platinum Jubilees
+            SpringBankHoliday if matches!(year, 2002 | 2012) => ymd(year, 6, 4),
+            SpringBankHoliday if year == 2022 => ymd(2022, 6, 2),
+            SpringBankHoliday => last_weekday(year, 5, Weekday::Mon),
+            Juneteenth if year < 2022 => None,
+            Juneteenth => ymd(year, 6, 19),
+            CanadaDay => ymd(year, 7, 1),
+            IndependenceDay => ymd(year, 7, 4),
+            CivicHoliday => nth_weekday(year, 8, Weekday::Mon, 1),
+            SummerBankHoliday => last_weekday(year, 8, Weekday::Mon),
+            LaborDay => nth_weekday(year, 9, Weekday::Mon, 1),
+            CanadianThanksgiving => nth_weekday(year, 10, Weekday::Mon, 2),
+            Thanksgiving => nth_weekday(year, 11, Weekday::Thu, 4),
+            ChristmasEve => ymd(year, 12, 24),
+            Christmas => ymd(year, 12, 25),
+            BoxingDay => ymd(year, 12, 26),
+            NewYearsEve => ymd(year, 12, 31),
+        };
+        date.into_iter().collect()
+    }
+}
+
+impl Exchange {
+    fn is_weekend(&self, date: NaiveDate) -> bool {
+        self.weekend.contains(&date.weekday())
+    }
+
+    /// Days of a year the exchange is closed for its built-in holidays, after moving
+    /// holidays that fall on its weekend according to its observance rule
+    fn closed_days(&self, year: i32) -> HashSet<NaiveDate> {
+        let mut holidays: Vec<(Closure, NaiveDate)> = self
+            .closures
+            .iter()
+            .flat_map(|&c| c.dates(year).into_iter().map(move |d| (c, d)))
+            .collect();
+        holidays.sort_by_key(|(_, d)| *d);
+
+        let mut closed = HashSet::new();
+        for (closure, date) in holidays {
+            let observed = match self.observance {
+                _ if !self.is_weekend(date) && !closed.contains(&date) => Some(date),
+                Observance::None => None,
+                Observance::NearestWeekday => match date.weekday() {
+                    Weekday::Sat if closure == NewYear => None,
+                    Weekday::Sat => date.pred_opt(),
+                    Weekday::Sun => date.succ_opt(),
+                    _ => None,
+                },
+                Observance::NextWeekday => {
+                    let mut day = date;
+                    while self.is_weekend(day) || closed.contains(&day) {
+                        day = day.succ_opt().unwrap_or(day);
+                    }
+                    Some(day)
+                }
+            };
+            closed.extend(observed);
+        }
+        closed
+    }
+}
+
+/// Trading days per exchange: days outside the exchange's weekend that are neither
+/// built-in holidays (public holidays with fixed or rule-based dates, made up on a
+/// weekday where the exchange does so) nor holidays configured for the exchange
+#[derive(Debug, Clone, Default)]
+pub struct TradingCalendar {
+    holidays: HashMap<String, HashSet<NaiveDate>>,
+}
+
+impl TradingCalendar {
+    /// Build the calendar from the `[calendar]` table in config.toml
+    pub fn from_config(config: Option<&CalendarConfig>) -> Result<Self> {
+        let mut holidays: HashMap<String, HashSet<NaiveDate>> = HashMap::new();
+        for (exchange, dates) in config.map(|c| &c.holidays).into_iter().flatten() {
+            let days = holidays.entry(exchange.to_uppercase()).or_default();
+            for date in dates {
+                let day = NaiveDate::parse_from_str(date, "%Y-%m-%d").map_err(|_| {
+                    anyhow::anyhow!(
+                        "Invalid holiday '{}' for {}, expected YYYY-MM-DD",
+                        date,
+                        exchange
+                    )
+                })?;
+                days.insert(day);
+            }
+        }
+        Ok(Self { holidays })
+    }
+
+    /// Whether the exchange trades on the given date
+    pub fn is_trading_day(&self, exchange: &str, date: NaiveDate) -> bool {
+        let info = exchange_info(exchange);
+        let weekend = info.map(|e| e.weekend).unwrap_or(SAT_SUN);
+        if weekend.contains(&date.weekday()) {
+            return false;
+        }
+        if info.is_some_and(|e| e.closed_days(date.year()).contains(&date)) {
+            return false;
+        }
+        !self
+            .holidays
+            .get(&exchange.to_uppercase())
+            .is_some_and(|days| days.contains(&date))
+    }
+
+    /// The given date if the exchange trades on it, otherwise the trading day before it
+    pub fn last_trading_day(&self, exchange: &str, date: NaiveDate) -> NaiveDate {
+        let mut day = date;
+        for _ in 0..MAX_LOOKBACK_DAYS {
+            if self.is_trading_day(exchange, day) {
+                return day;
+            }
+            match day.pred_opt() {
+                Some(previous) => day = previous,
+                None => break,
+            }
+        }
+        date
+    }
+
+    /// Last trading day on or before `date` for the exchange of a ticker
+    pub fn last_trading_day_for_ticker(&self, ticker: &str, date: NaiveDate) -> NaiveDate {
+        self.last_trading_day(exchange_for_ticker(ticker), date)
+    }
+
+    /// Date and time to request for a ticker: `datetime` moved back to the last trading
+    /// day of the ticker's exchange, keeping the time of day
+    pub fn request_datetime(&self, ticker: &str, datetime: &NaiveDateTime) -> DateTime<Utc> {
+        let day = self.last_trading_day_for_ticker(ticker, datetime.date());
+        NaiveDateTime::new(day, datetime.time()).and_utc()
+    }
+
+    /// Print the exchanges of the tickers that are closed on `date`
+    pub fn print_shifted(&self, tickers: &[String], date: NaiveDate) {
+        for (exchange, day) in self.shifted_exchanges(tickers, date) {
+            status!("  {} is closed on {}, using {}", exchange, date, day);
+        }
+    }
+
+    /// Exchanges of the tickers whose last trading day differs from `date`
+    pub fn shifted_exchanges(
+        &self,
+        tickers: &[String],
+        date: NaiveDate,
+    ) -> BTreeMap<&'static str, NaiveDate> {
+        tickers
+            .iter()
+            .map(|ticker| exchange_for_ticker(ticker))
+            .map(|exchange| (exchange, self.last_trading_day(exchange, date)))
+            .filter(|(_, day)| *day != date)
+            .collect()
+    }
+
+    /// Whether at least one of the exchanges trades on the given date; true when no
+    /// exchanges are given
+    pub fn any_open(&self, exchanges: &[String], date: NaiveDate) -> bool {
+        exchanges.is_empty() || exchanges.iter().any(|e| self.is_trading_day(e, date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_trading_days() {
+        let config = CalendarConfig {
+            holidays: BTreeMap::from([
+                ("nyse".to_string(), vec!["2025-07-04".to_string()]),
+                ("EURONEXT".to_string(), vec!["2025-12-25".to_string()]),
+            ]),
+        };
+        let calendar = TradingCalendar::from_config(Some(&config)).unwrap();
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert!(!calendar.is_trading_day("NYSE", date("2025-07-04")));
+        assert!(calendar.is_trading_day("EURONEXT", date("2025-07-04")));
+        assert!(!calendar.is_trading_day("NYSE", date("2025-07-05")));
+
+        let exchanges = vec!["NYSE".to_string(), "EURONEXT".to_string()];
+        assert!(calendar.any_open(&exchanges, date("2025-07-04")));
+        assert!(!calendar.any_open(&exchanges[..1], date("2025-07-04")));
+        assert!(calendar.any_open(&[], date("2025-07-05")));
+
+        let invalid = CalendarConfig {
+            holidays: BTreeMap::from([("NYSE".to_string(), vec!["July 4".to_string()])]),
+        };
+        assert!(TradingCalendar::from_config(Some(&invalid)).is_err());
+    }
+
+    #[test]
+    fn test_last_trading_day_per_exchange() {
+        let calendar = TradingCalendar::default();
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+
+        assert_eq!(exchange_for_ticker("MC.PA"), "EURONEXT");
+        assert_eq!(exchange_for_ticker("9983.T"), "TSE");
+        assert_eq!(exchange_for_ticker("BRK.B"), "NYSE");
+        assert_eq!(exchange_for_ticker("NKE"), "NYSE");
+
+        assert_eq!(easter_sunday(2024), date("2024-03-31"));
+        assert_eq!(easter_sunday(2025), date("2025-04-20"));
+
+        // Sunday 2024-03-31 follows Good Friday, so Thursday is the last trading day
+        // in Paris while Tokyo traded on Friday
+        assert_eq!(
+            calendar.last_trading_day_for_ticker("MC.PA", date("2024-03-31")),
+            date("2024-03-28")
+        );
+        assert_eq!(
+            calendar.last_trading_day_for_ticker("9983.T", date("2024-03-31")),
+            date("2024-03-29")
+        );
+        // Boxing Day closes London but not New York
+        assert_eq!(
+            calendar.last_trading_day_for_ticker("NXT.L", date("2021-12-26")),
+            date("2021-12-24")
+        );
+        // Riyadh's weekend is Friday and Saturday
+        assert_eq!(
+            calendar.last_trading_day_for_ticker("4190.SR", date("2025-08-30")),
+            date("2025-08-28")
+        );
+
+        let tickers = vec!["NKE".to_string(), "MC.PA".to_string(), "9983.T".to_string()];
+        let shifted = calendar.shifted_exchanges(&tickers, date("2024-03-29"));
+        assert_eq!(
+            shifted,
+            BTreeMap::from([
+                ("EURONEXT", date("2024-03-28")),
+                ("NYSE", date("2024-03-28"))
+            ])
+        );
+    }
+
+    #[test]
+    fn test_year_end_closures() {
+        let calendar = TradingCalendar::default();
+        let datetime = |s| NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap();
+
+        // Exchanges closed on New Year's Eve fall back to the 30th
+        let closed = [
+            "XETRA",
+            "SIX",
+            "NASDAQ_NORDIC",
+            "OSLO",
+            "WIENER_BOERSE",
+            "BORSA_ITALIANA",
+            "B3",
+            "TSE",
+            "KRX",
+        ];
+        let year_end = datetime("2025-12-31 23:59");
+        for exchange in EXCHANGES {
+            let ticker = match exchange.suffixes.first() {
+                Some((suffix, _)) => format!("TEST.{}", suffix),
+                None => "TEST".to_string(),
+            };
+            let expected = if closed.contains(&exchange.code) {
+                datetime("2025-12-30 23:59")
+            } else {
+                year_end
+            };
+            assert_eq!(
+                calendar.request_datetime(&ticker, &year_end),
+                expected.and_utc(),
+                "{}",
+                exchange.code
+            );
+        }
+        assert_eq!(exchange_for_ticker("ISP.MI"), "BORSA_ITALIANA");
+
+        // Tokyo stays closed until 4 January
+        assert_eq!(
+            calendar.request_datetime("9983.T", &datetime("2025-01-03 12:00")),
+            datetime("2024-12-30 12:00").and_utc()
+        );
+    }
+
+    #[test]
+    fn test_observed_holidays() {
+        let calendar = TradingCalendar::default();
+        let date = |s| NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap();
+        let closed = |exchange, day| !calendar.is_trading_day(exchange, date(day));
+
+        // Memorial Day and the Spring Bank Holiday share the last Monday of May
+        assert!(closed("NYSE", "2021-05-31"));
+        assert!(closed("LSE", "2021-05-31"));
+        assert!(!closed("EURONEXT", "2021-05-31"));
+
+        // Christmas on a Saturday: New York closes the Friday before, London the
+        // Monday and Tuesday after for Christmas and Boxing Day
+        assert!(closed("NYSE", "2021-12-24"));
+        assert!(!closed("NYSE", "2021-12-27"));
+        assert!(!closed("LSE", "2021-12-24"));
+        assert!(closed("LSE", "2021-12-27"));
+        assert!(closed("LSE", "2021-12-28"));
+
+        // Boxing Day on a Saturday is made up on Monday
+        assert!(closed("LSE", "2020-12-28"));
+        assert!(!closed("EURONEXT", "2020-12-28"));
+
+        // New Year's Day on a Saturday is not observed on the Friday before in New York
+        assert!(!closed("NYSE", "2021-12-31"));
+        assert!(closed("LSE", "2022-01-03"));
+        assert!(closed("NYSE", "2023-01-02"));
+
+        // Moveable US holidays and Juneteenth from 2022
+        for day in [
+            "2025-01-20",
+            "2025-02-17",
+            "2025-07-04",
+            "2025-09-01",
+            "2025-11-27",
+            "2022-06-20",
+        ] {
+            assert!(closed("NYSE", day), "{}", day);
+        }
+        assert!(!closed("NYSE", "2021-06-18"));
+
+        // UK bank holidays, including the moved 2020 and 2022 ones
+        for day in [
+            "2025-04-21",
+            "2025-05-05",
+            "2025-08-25",
+            "2020-05-08",
+            "2022-06-02",
+        ] {
+            assert!(closed("LSE", day), "{}", day);
+        }
+        assert!(!closed("LSE", "2020-05-04"));
+        assert!(!closed("LSE", "2022-05-30"));
+
+        // Whit Monday in Zurich, Victoria Day in Toronto
+        assert!(closed("SIX", "2025-06-09"));
+        assert!(closed("TSX", "2025-05-19"));
+
+        assert_eq!(country_for_ticker("MC.PA"), "FR");
+        assert_eq!(country_for_ticker("ASML.AS"), "NL");
+        assert_eq!(country_for_ticker("NKE"), DEFAULT_COUNTRY);
+    }
+}
@@ -0,0 +1,513 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use chrono::{DateTime, Local};
+use serde::Serialize;
+use sqlx::sqlite::SqlitePool;
+use std::collections::HashMap;
+use std::fmt::{self, Write as _};
+
+use crate::api::MarketCapMethod;
+use crate::config::{self, ValidationConfig};
+use crate::corporate_events;
+use crate::currencies::get_rate_map_from_db;
+use crate::output::status;
+use crate::overrides::{self, Figures};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => f.write_str("warning"),
+            Severity::Error => f.write_str("error"),
+        }
+    }
+}
+
+/// A check that failed for one ticker in a snapshot
+#[derive(Debug, Clone, Serialize)]
+pub struct Issue {
+    pub date: String,
+    pub ticker: String,
+    pub check: &'static str,
+    pub severity: Severity,
+    pub details: String,
+}
+
+#[derive(Debug, Clone, sqlx::FromRow)]
+struct SnapshotRow {
+    ticker: String,
+    name: String,
+    market_cap_original: Option<f64>,
+    original_currency: Option<String>,
+    market_cap_eur: Option<f64>,
+    market_cap_usd: Option<f64>,
+    price: Option<f64>,
+    market_cap_method: Option<String>,
+}
+
+/// Load a snapshot as it is exported: excluded tickers are left out and active
+/// overrides are applied, so pinned figures are not reported as bad data
+async fn load_rows(pool: &SqlitePool, timestamp: i64) -> Result<Vec<SnapshotRow>> {
+    let rows = sqlx::query_as::<_, SnapshotRow>(
+        r#"
+        SELECT ticker, name,
+            CAST(market_cap_original AS REAL) AS market_cap_original,
+            original_currency,
+            CAST(market_cap_eur AS REAL) AS market_cap_eur,
+            CAST(market_cap_usd AS REAL) AS market_cap_usd,
+            CAST(price AS REAL) AS price,
+            market_cap_method
+        FROM market_caps
+        WHERE timestamp = ?
+        "#,
+    )
+    .bind(timestamp)
+    .fetch_all(pool)
+    .await?;
+
+    let excluded = corporate_events::excluded_at(pool, timestamp).await?;
+    let active_overrides = overrides::active_at(pool, timestamp).await?;
+    let rate_map = get_rate_map_from_db(pool).await?;
+
+    Ok(rows
+        .into_iter()
+        .filter(|r| !excluded.contains(&r.ticker))
+        .filter(|r| !active_overrides.get(&r.ticker).is_some_and(|o| o.exclude))
+        .map(|mut row| {
+            let Some(o) = active_overrides.get(&row.ticker) else {
+                return row;
+            };
+            let mut figures = Figures {
+                name: row.name.clone(),
+                market_cap_original: row.market_cap_original.unwrap_or(0.0),
+                original_currency: row.original_currency.clone().unwrap_or_default(),
+                market_cap_eur: row.market_cap_eur.unwrap_or(0.0),
+                market_cap_usd: row.market_cap_usd.unwrap_or(0.0),
+            };
+            if o.apply(&mut figures, &rate_map).is_some() {
+                row.name = figures.name;
+                row.market_cap_original = Some(figures.market_cap_original);
+                row.original_currency = Some(figures.original_currency);
+                row.market_cap_eur = Some(figures.market_cap_eur);
+                row.market_cap_usd = Some(figures.market_cap_usd);
+            }
+            row
+        })
+        .collect())
+}
+
+fn timestamp_to_date(timestamp: i64) -> String {
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|dt| dt.format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+/// Whether a converted amount is just the original amount, which `convert_currency`
+/// returns when it has no rate for the currency
+fn unconverted(row: &SnapshotRow, target: &str, converted: Option<f64>) -> bool {
+    let currency = row.original_currency.as_deref().unwrap_or("");
+    let original = row.market_cap_original.unwrap_or(0.0);
+    !currency.is_empty()
+        && currency != target
+        && original > 0.0
+        && converted.is_some_and(|c| (c - original).abs() < 0.5)
+}
+
+/// Check one snapshot on its own and against the previous one
+fn check_rows(
+    date: &str,
+    rows: &[SnapshotRow],
+    previous: &[SnapshotRow],
+    previous_date: &str,
+    config: &ValidationConfig,
+) -> Vec<Issue> {
+    let previous: HashMap<&str, &SnapshotRow> =
+        previous.iter().map(|r| (r.ticker.as_str(), r)).collect();
+    let mut issues = Vec::new();
+    let mut flag = |row: &SnapshotRow, check, severity, details: String| {
+        issues.push(Issue {
+            date: date.to_string(),
+            ticker: row.ticker.clone(),
+            check,
+            severity,
+            details,
+        })
+    };
+
+    for row in rows {
+        let original = row.market_cap_original.unwrap_or(0.0);
+        if original <= 0.0 {
+            flag(
+                row,
+                "non_positive",
+                Severity::Error,
+                format!("Market cap is {}", original),
+            );
+            continue;
+        }
+
+        for (target, converted) in [("EUR", row.market_cap_eur), ("USD", row.market_cap_usd)] {
+            if unconverted(row, target, converted) {
+                flag(
+                    row,
+                    "unconverted",
+                    Severity::Error,
+                    format!(
+                        "{} value equals the {} amount; no exchange rate was applied",
+                        target,
+                        row.original_currency.as_deref().unwrap_or("")
+                    ),
+                );
+            }
+        }
+
+        if row.market_cap_method.as_deref() == Some(MarketCapMethod::QuoteFallback.as_str()) {
+            flag(
+                row,
+                "quote_fallback",
+                Severity::Warning,
+                "No historical figure was found; the current quote was stored instead".to_string(),
+            );
+        }
+
+        let Some(prev) = previous.get(row.ticker.as_str()) else {
+            continue;
+        };
+
+        if let (Some(now), Some(before)) = (row.market_cap_usd, prev.market_cap_usd) {
+            if now > 0.0 && before > 0.0 {
+                let factor = if now > before {
+                    now / before
+                } else {
+                    before / now
+                };
+                if factor >= config.max_change_factor {
+                    flag(
+                        row,
+                        "outlier",
+                        Severity::Error,
+                        format!(
+                            "USD market cap moved {:.1}x since {} ({:.0} -> {:.0})",
+                            factor, previous_date, before, now
+                        ),
+                    );
+                }
+            }
+        }
+
+        if row.original_currency != prev.original_currency {
+            flag(
+                row,
+                "currency_changed",
+                Severity::Warning,
+                format!(
+                    "Currency changed from {} to {} since {}",
+                    prev.original_currency.as_deref().unwrap_or("none"),
+                    row.original_currency.as_deref().unwrap_or("none"),
+                    previous_date
+                ),
+            );
+        }
+
+        if row.name != prev.name {
+            flag(
+                row,
+                "name_changed",
+                Severity::Warning,
+                format!(
+                    "Name changed from \"{}\" to \"{}\" since {}",
+                    prev.name, row.name, previous_date
+                ),
+            );
+        }
+
+        if let (Some(now), Some(before)) = (row.price, prev.price) {
+            if now > 0.0 && now == before && date != previous_date {
+                flag(
+                    row,
+                    "stale_price",
+                    Severity::Warning,
+                    format!("Price {} is unchanged since {}", now, previous_date),
+                );
+            }
+        }
+    }
+
+    issues
+}
+
+/// Validate the snapshot stored at a timestamp against the snapshot before it
+pub async fn validate_snapshot(
+    pool: &SqlitePool,
+    timestamp: i64,
+    config: &ValidationConfig,
+) -> Result<Vec<Issue>> {
+    let rows = load_rows(pool, timestamp).await?;
+    let (previous_timestamp,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MAX(timestamp) FROM market_caps WHERE timestamp < ?",
+    )
+    .bind(timestamp)
+    .fetch_one(pool)
+    .await?;
+    let (previous, previous_date) = match previous_timestamp {
+        Some(ts) => (load_rows(pool, ts).await?, timestamp_to_date(ts)),
+        None => (Vec::new(), String::new()),
+    };
+
+    Ok(check_rows(
+        &timestamp_to_date(timestamp),
+        &rows,
+        &previous,
+        &previous_date,
+        config,
+    ))
+}
+
+/// Render issues as a Markdown report
+fn render_report(issues: &[Issue]) -> String {
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    let mut report = String::new();
+    writeln!(report, "# Data Quality Report").unwrap();
+    writeln!(report).unwrap();
+    writeln!(
+        report,
+        "Generated {}: {} errors, {} warnings.",
+        Local::now().format("%Y-%m-%d %H:%M:%S"),
+        errors,
+        issues.len() - errors
+    )
+    .unwrap();
+
+    if !issues.is_empty() {
+        writeln!(report).unwrap();
+        writeln!(report, "| Date | Ticker | Severity | Check | Details |").unwrap();
+        writeln!(report, "|------|--------|----------|-------|---------|").unwrap();
+        for issue in issues {
+            writeln!(
+                report,
+                "| {} | {} | {} | {} | {} |",
+                issue.date,
+                issue.ticker,
+                issue.severity,
+                issue.check,
+                issue.details.replace('|', "\\|")
+            )
+            .unwrap();
+        }
+    }
+
+    report
+}
+
+/// Validate snapshots, print a summary and write the report to `output/`.
+///
+/// Returns the issues found; use [`ensure_export_allowed`] to honour `block_export`.
+pub async fn run_validation(pool: &SqlitePool, timestamps: &[i64]) -> Result<Vec<Issue>> {
+    let config = config::load_config()?.validation.unwrap_or_default();
+
+    let mut issues = Vec::new();
+    for &timestamp in timestamps {
+        issues.extend(validate_snapshot(pool, timestamp, &config).await?);
+    }
+    issues.sort_by(|a, b| (&a.date, b.severity, &a.ticker).cmp(&(&b.date, a.severity, &b.ticker)));
+
+    std::fs::create_dir_all("output")?;
+    let filename = format!(
+        "output/data_quality_{}.md",
+        Local::now().format("%Y%m%d_%H%M%S")
+    );
+    std::fs::write(&filename, render_report(&issues))?;
+
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    if issues.is_empty() {
+        status!(
+            "✅ Data quality checks passed, report written to {}",
+            filename
+        );
+    } else {
+        status!(
+            "⚠️  Data quality checks found {} errors and {} warnings, see {}",
+            errors,
+            issues.len() - errors,
+            filename
+        );
+    }
+
+    Ok(issues)
+}
+
+/// Timestamp of the latest snapshot, or of the latest one taken on a YYYY-MM-DD date
+pub async fn snapshot_timestamp(pool: &SqlitePool, date: Option<&str>) -> Result<i64> {
+    let (start, end) = match date {
+        Some(date) => {
+            let day = chrono::NaiveDate::parse_from_str(date, "%Y-%m-%d")
+                .map_err(|_| anyhow::anyhow!("Invalid date '{}', expected YYYY-MM-DD", date))?;
+            let start = day.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp();
+            (start, start + 86_400)
+        }
+        None => (i64::MIN, i64::MAX),
+    };
+
+    let (timestamp,) = sqlx::query_as::<_, (Option<i64>,)>(
+        "SELECT MAX(timestamp) FROM market_caps WHERE timestamp >= ? AND timestamp < ?",
+    )
+    .bind(start)
+    .bind(end)
+    .fetch_one(pool)
+    .await?;
+
+    timestamp.ok_or_else(|| match date {
+        Some(date) => anyhow::anyhow!("No snapshot found for {}", date),
+        None => anyhow::anyhow!("No snapshots found"),
+    })
+}
+
+/// Fail when validation found errors and `block_export` is enabled
+pub fn ensure_export_allowed(issues: &[Issue]) -> Result<()> {
+    let config = config::load_config()?.validation.unwrap_or_default();
+    let errors = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    if config.block_export && errors > 0 {
+        anyhow::bail!(
+            "Export blocked: data quality checks found {} errors (set block_export = false in [validation] to export anyway)",
+            errors
+        );
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn row(ticker: &str, name: &str, currency: &str, original: f64, usd: f64) -> SnapshotRow {
+        SnapshotRow {
+            ticker: ticker.to_string(),
+            name: name.to_string(),
+            market_cap_original: Some(original),
+            original_currency: Some(currency.to_string()),
+            market_cap_eur: Some(usd * 0.9),
+            market_cap_usd: Some(usd),
+            price: Some(10.0),
+            market_cap_method: Some("profile".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_checks_against_previous_snapshot() {
+        let previous = vec![
+            row("NKE", "Nike", "USD", 100e9, 100e9),
+            row("JUMP", "Jumper", "USD", 1e9, 1e9),
+            row("RENAMED", "Old Name", "EUR", 2e9, 2.2e9),
+            row("MOVED", "Moved", "EUR", 2e9, 2.2e9),
+        ];
+        let mut current = vec![
+            row("NKE", "Nike", "USD", 101e9, 101e9),
+            row("JUMP", "Jumper", "USD", 12e9, 12e9),
+            row("RENAMED", "New Name", "EUR", 2e9, 2.2e9),
+            row("MOVED", "Moved", "GBp", 2e9, 2.2e9),
+            row("ZERO", "Zero", "USD", 0.0, 0.0),
+            row("RAW", "Raw", "SEK", 50e9, 50e9),
+        ];
+        current[0].price = Some(11.0);
+        current[2].price = Some(12.0);
+        current[2].market_cap_method = Some("quote_fallback".to_string());
+
+        let config = ValidationConfig::default();
+        let issues = check_rows("2025-02-01", &current, &previous, "2025-01-31", &config);
+        let found: Vec<(&str, &str)> = issues
+            .iter()
+            .map(|i| (i.ticker.as_str(), i.check))
+            .collect();
+
+        assert_eq!(
+            found,
+            vec![
+                ("JUMP", "outlier"),
+                ("JUMP", "stale_price"),
+                ("RENAMED", "quote_fallback"),
+                ("RENAMED", "name_changed"),
+                ("MOVED", "currency_changed"),
+                ("MOVED", "stale_price"),
+                ("ZERO", "non_positive"),
+                ("RAW", "unconverted"),
+            ]
+        );
+        assert!(render_report(&issues).contains("| 2025-02-01 | JUMP | error | outlier |"));
+    }
+
+    #[tokio::test]
+    async fn test_overrides_and_exclusions_apply_before_checks() {
+        let pool = sqlx::sqlite::SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+
+        crate::currencies::insert_forex_rate(&pool, "EUR/USD", 1.08, 1.08, 1738368000)
+            .await
+            .unwrap();
+
+        // 2025-02-01: both rows are broken as stored
+        let timestamp = 1738368000;
+        for ticker in ["PINNED", "GONE"] {
+            sqlx::query(
+                "INSERT INTO market_caps (ticker, name, market_cap_original, original_currency, market_cap_eur, market_cap_usd, timestamp) VALUES (?, ?, 0, 'USD', 0, 0, ?)",
+            )
+            .bind(ticker)
+            .bind(ticker)
+            .bind(timestamp)
+            .execute(&pool)
+            .await
+            .unwrap();
+        }
+        overrides::set_override(
+            &pool,
+            "PINNED",
+            &overrides::OverrideValues {
+                market_cap: Some(5e9),
+                currency: None,
+                name: None,
+                exclude: false,
+            },
+            "2025-01-01",
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+        corporate_events::record_event(
+            &pool,
+            "GONE",
+            corporate_events::EventType::Delisted,
+            "2025-01-15",
+            None,
+            "manual",
+        )
+        .await
+        .unwrap();
+
+        let issues = validate_snapshot(&pool, timestamp, &ValidationConfig::default())
+            .await
+            .unwrap();
+        assert!(issues.is_empty(), "{:?}", issues);
+    }
+}
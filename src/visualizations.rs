@@ -5,10 +5,56 @@
 use anyhow::{Context, Result};
 use csv::Reader;
 use plotters::prelude::*;
+use plotters::style::text_anchor::{HPos, Pos, VPos};
 use serde::Deserialize;
 use std::fs::File;
 use std::path::Path;
 
+use crate::compare_marketcaps::{write_comparison_csv, MarketCapComparison, COMPARISON_CURRENCY};
+use crate::output::status;
+use crate::theme::{Locale, Magnitude, Presentation, Theme};
+
+/// Canvas size shared by all comparison charts
+const CHART_SIZE: (u32, u32) = (1200, 800);
+
+/// Drawing area of any backend: SVG for files and the API, bitmaps for email
+type ChartArea<DB> = DrawingArea<DB, plotters::coord::Shift>;
+type ChartFn<DB> = fn(&ChartArea<DB>, &[ComparisonRecord], &str, &str, &Presentation) -> Result<()>;
+
+/// Chart kinds by name, with a human-readable label and the function drawing them
+fn charts<DB: DrawingBackend>() -> [(&'static str, &'static str, ChartFn<DB>); 5]
+where
+    DB::ErrorType: 'static,
+{
+    [
+        (
+            "gainers_losers",
+            "gainers/losers chart",
+            draw_gainers_losers_chart,
+        ),
+        (
+            "market_distribution",
+            "market distribution chart",
+            draw_market_distribution_chart,
+        ),
+        (
+            "market_treemap",
+            "market cap treemap",
+            draw_market_cap_treemap,
+        ),
+        (
+            "rank_movements",
+            "rank movements chart",
+            draw_rank_movement_chart,
+        ),
+        (
+            "summary_dashboard",
+            "summary dashboard",
+            draw_summary_dashboard,
+        ),
+    ]
+}
+
 #[derive(Debug, Deserialize)]
 struct ComparisonRecord {
     #[serde(rename = "Ticker")]
@@ -35,33 +81,6 @@ struct ComparisonRecord {
     _market_share_to: Option<String>,
 }
 
-// Professional color palette
-const COLOR_EMERALD: RGBColor = RGBColor(16, 185, 129);
-const COLOR_ROSE: RGBColor = RGBColor(244, 63, 94);
-const COLOR_BLUE: RGBColor = RGBColor(59, 130, 246);
-const COLOR_AMBER: RGBColor = RGBColor(245, 158, 11);
-const COLOR_TEAL: RGBColor = RGBColor(20, 184, 166);
-const COLOR_CORAL: RGBColor = RGBColor(251, 113, 133);
-const COLOR_PURPLE: RGBColor = RGBColor(139, 92, 246);
-const COLOR_PINK: RGBColor = RGBColor(236, 72, 153);
-const COLOR_LIME: RGBColor = RGBColor(132, 204, 22);
-const COLOR_ORANGE: RGBColor = RGBColor(249, 115, 22);
-const COLOR_SLATE: RGBColor = RGBColor(100, 116, 139);
-const COLOR_GRAY_LIGHT: RGBColor = RGBColor(243, 244, 246);
-
-const CHART_COLORS: [RGBColor; 10] = [
-    COLOR_BLUE,
-    COLOR_EMERALD,
-    COLOR_AMBER,
-    COLOR_ROSE,
-    COLOR_PURPLE,
-    COLOR_PINK,
-    COLOR_TEAL,
-    COLOR_ORANGE,
-    COLOR_LIME,
-    COLOR_SLATE,
-];
-
 /// Find the comparison CSV file for the given dates
 fn find_comparison_csv(from_date: &str, to_date: &str) -> Result<String> {
     let output_dir = Path::new("output");
@@ -97,7 +116,12 @@ fn read_comparison_data(csv_path: &str) -> Result<Vec<ComparisonRecord>> {
     let file =
         File::open(csv_path).with_context(|| format!("Failed to open CSV file: {}", csv_path))?;
 
-    let mut reader = Reader::from_reader(file);
+    read_comparison_records(file)
+}
+
+/// Read comparison records from any CSV source
+fn read_comparison_records<R: std::io::Read>(source: R) -> Result<Vec<ComparisonRecord>> {
+    let mut reader = Reader::from_reader(source);
     let mut records = Vec::new();
 
     for result in reader.deserialize() {
@@ -119,11 +143,20 @@ fn parse_usd_amount(s: &Option<String>) -> Option<f64> {
 }
 
 /// Create top gainers and losers bar chart
-fn create_gainers_losers_chart(
+fn draw_gainers_losers_chart<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     records: &[ComparisonRecord],
     from_date: &str,
     to_date: &str,
-) -> Result<()> {
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
     // Filter and sort for top gainers
     let mut gainers: Vec<_> = records
         .iter()
@@ -155,17 +188,15 @@ fn create_gainers_losers_chart(
     losers.truncate(10);
 
     // Create the chart
-    let filename = format!(
-        "output/comparison_{}_to_{}_gainers_losers.svg",
-        from_date, to_date
-    );
-    let root = SVGBackend::new(&filename, (1200, 800)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background.0)?;
 
-    let mut chart = ChartBuilder::on(&root)
+    let mut chart = ChartBuilder::on(root)
         .caption(
-            format!("Top Gainers and Losers: {} to {}", from_date, to_date),
-            ("sans-serif", 32).into_font().color(&BLACK),
+            format!(
+                "{}: {} {} {}",
+                labels.gainers_losers_title, from_date, labels.to, to_date
+            ),
+            theme.title_font().color(&theme.text.0),
         )
         .margin(20)
         .x_label_area_size(150)
@@ -174,22 +205,18 @@ fn create_gainers_losers_chart(
 
     chart
         .configure_mesh()
-        .x_desc("Percentage Change (%)")
+        .x_desc(labels.percentage_change_axis)
         .y_desc("")
-        .x_label_formatter(&|x| format!("{:.0}%", x))
+        .x_label_formatter(&|x| locale.format_percent(*x, 0))
         .y_label_formatter(&|_| "".to_string())
-        .axis_desc_style(("sans-serif", 16))
+        .axis_desc_style(theme.font(16))
         .draw()?;
 
     // Draw gainers (green gradient)
     for (i, (name, pct)) in gainers.iter().enumerate() {
         let y = 19 - i;
         let y_coord = y as i32;
-        let color = RGBColor(
-            16 + (i * 10) as u8,
-            185 - (i * 5) as u8,
-            129 - (i * 5) as u8,
-        );
+        let color = blend_colors(theme.positive.0, theme.background.0, i as f64 * 0.05);
 
         chart.draw_series(std::iter::once(Rectangle::new(
             [(0.0, y), (*pct, y.saturating_sub(1))],
@@ -205,14 +232,14 @@ fn create_gainers_losers_chart(
 
         root.draw_text(
             &label_name,
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (50, 80 + y_coord * 35),
         )?;
 
         // Add value label
         root.draw_text(
-            &format!("+{:.1}%", pct),
-            &TextStyle::from(("sans-serif", 12).into_font()).color(&COLOR_EMERALD),
+            &format!("+{}", locale.format_percent(*pct, 1)),
+            &TextStyle::from(theme.font(12)).color(&theme.positive.0),
             (1050, 80 + y_coord * 35),
         )?;
     }
@@ -221,7 +248,7 @@ fn create_gainers_losers_chart(
     for (i, (name, pct)) in losers.iter().enumerate() {
         let y = 9 - i;
         let y_coord = y as i32;
-        let color = RGBColor(244 - (i * 5) as u8, 63 + (i * 5) as u8, 94 + (i * 5) as u8);
+        let color = blend_colors(theme.negative.0, theme.background.0, i as f64 * 0.05);
 
         chart.draw_series(std::iter::once(Rectangle::new(
             [(0.0, y), (*pct, y.saturating_sub(1))],
@@ -237,14 +264,14 @@ fn create_gainers_losers_chart(
 
         root.draw_text(
             &label_name,
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (50, 440 + (9 - y_coord) * 35),
         )?;
 
         // Add value label
         root.draw_text(
-            &format!("{:.1}%", pct),
-            &TextStyle::from(("sans-serif", 12).into_font()).color(&COLOR_ROSE),
+            &locale.format_percent(*pct, 1),
+            &TextStyle::from(theme.font(12)).color(&theme.negative.0),
             (1050, 440 + (9 - y_coord) * 35),
         )?;
     }
@@ -252,21 +279,29 @@ fn create_gainers_losers_chart(
     // Add dividing line
     chart.draw_series(std::iter::once(PathElement::new(
         vec![(0.0, 10), (0.0, 10)],
-        BLACK.stroke_width(2),
+        theme.text.0.stroke_width(2),
     )))?;
 
-    root.present()?;
-    println!("✅ Generated gainers/losers chart: {}", filename);
+    draw_watermark(root, theme)?;
 
     Ok(())
 }
 
 /// Create market cap distribution donut chart
-fn create_market_distribution_chart(
+fn draw_market_distribution_chart<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     records: &[ComparisonRecord],
-    from_date: &str,
+    _from_date: &str,
     to_date: &str,
-) -> Result<()> {
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
     // Get top 10 companies by market cap
     let mut companies: Vec<_> = records
         .iter()
@@ -283,17 +318,12 @@ fn create_market_distribution_chart(
     let others = total_market_cap - top_10_sum;
 
     // Create the chart
-    let filename = format!(
-        "output/comparison_{}_to_{}_market_distribution.svg",
-        from_date, to_date
-    );
-    let root = SVGBackend::new(&filename, (1200, 800)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background.0)?;
 
     // Title
     root.draw_text(
-        &format!("Market Cap Distribution: {}", to_date),
-        &TextStyle::from(("sans-serif", 32).into_font()).color(&BLACK),
+        &format!("{}: {}", labels.distribution_title, to_date),
+        &TextStyle::from(theme.title_font()).color(&theme.text.0),
         (400, 30),
     )?;
 
@@ -310,13 +340,13 @@ fn create_market_distribution_chart(
 
         // Draw segment
         draw_donut_segment(
-            &root,
+            root,
             center,
             outer_radius,
             inner_radius,
             start_angle,
             sweep_angle,
-            CHART_COLORS[i],
+            theme.series_color(i),
         )?;
 
         start_angle += sweep_angle;
@@ -328,13 +358,13 @@ fn create_market_distribution_chart(
         let sweep_angle = (percentage / 100.0) * 360.0;
 
         draw_donut_segment(
-            &root,
+            root,
             center,
             outer_radius,
             inner_radius,
             start_angle,
             sweep_angle,
-            COLOR_GRAY_LIGHT,
+            theme.panel.0,
         )?;
     }
 
@@ -348,7 +378,7 @@ fn create_market_distribution_chart(
         // Color box
         root.draw(&Rectangle::new(
             [(legend_x, y), (legend_x + 20, y + 20)],
-            CHART_COLORS[i].filled(),
+            theme.series_color(i).filled(),
         ))?;
 
         // Company name
@@ -360,15 +390,15 @@ fn create_market_distribution_chart(
 
         root.draw_text(
             &format!("{} ({})", display_name, ticker),
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (legend_x + 30, y + 5),
         )?;
 
         // Percentage
         let percentage = (market_cap / total_market_cap) * 100.0;
         root.draw_text(
-            &format!("{:.1}%", percentage),
-            &TextStyle::from(("sans-serif", 12).into_font()).color(&COLOR_SLATE),
+            &locale.format_percent(percentage, 1),
+            &TextStyle::from(theme.font(12)).color(&theme.muted.0),
             (legend_x + 30, y + 20),
         )?;
     }
@@ -378,51 +408,316 @@ fn create_market_distribution_chart(
         let y = legend_y_start + 10 * 35;
         root.draw(&Rectangle::new(
             [(legend_x, y), (legend_x + 20, y + 20)],
-            COLOR_GRAY_LIGHT.filled(),
+            theme.panel.0.filled(),
         ))?;
 
         root.draw_text(
-            "Others",
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            labels.others,
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (legend_x + 30, y + 5),
         )?;
 
         let percentage = (others / total_market_cap) * 100.0;
         root.draw_text(
-            &format!("{:.1}%", percentage),
-            &TextStyle::from(("sans-serif", 12).into_font()).color(&COLOR_SLATE),
+            &locale.format_percent(percentage, 1),
+            &TextStyle::from(theme.font(12)).color(&theme.muted.0),
             (legend_x + 30, y + 20),
         )?;
     }
 
     // Add center text with total
     root.draw_text(
-        "Total Market Cap",
-        &TextStyle::from(("sans-serif", 16).into_font()).color(&COLOR_SLATE),
+        labels.total_market_cap,
+        &TextStyle::from(theme.font(16)).color(&theme.muted.0),
         (center.0 - 60, center.1 - 10),
     )?;
     root.draw_text(
-        &format!("${:.1}T", total_market_cap / 1_000_000_000_000.0),
-        &TextStyle::from(("sans-serif", 24).into_font()).color(&BLACK),
+        &locale.format_money(
+            total_market_cap,
+            COMPARISON_CURRENCY,
+            Magnitude::Trillions,
+            1,
+        ),
+        &TextStyle::from(theme.font(24)).color(&theme.text.0),
         (center.0 - 40, center.1 + 10),
     )?;
 
-    root.present()?;
-    println!("✅ Generated market distribution chart: {}", filename);
+    draw_watermark(root, theme)?;
+
+    Ok(())
+}
+
+/// A rectangle produced by the treemap layout, in pixel coordinates
+#[derive(Debug, Clone, PartialEq)]
+struct TreemapTile {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+/// Worst aspect ratio of a row of areas laid out along a side of the given length
+fn worst_aspect_ratio(row: &[f64], side: f64) -> f64 {
+    let sum: f64 = row.iter().sum();
+    let max = row.iter().cloned().fold(f64::MIN, f64::max);
+    let min = row.iter().cloned().fold(f64::MAX, f64::min);
+    let side_sq = side * side;
+    let sum_sq = sum * sum;
+    (side_sq * max / sum_sq).max(sum_sq / (side_sq * min))
+}
+
+/// Squarified treemap layout (Bruls, Huizing & van Wijk).
+///
+/// Values are expected in descending order; one tile is returned per value, in the same order.
+fn squarify(values: &[f64], x: f64, y: f64, width: f64, height: f64) -> Vec<TreemapTile> {
+    let total: f64 = values.iter().sum();
+    if values.is_empty() || total <= 0.0 {
+        return Vec::new();
+    }
+
+    let scale = (width * height) / total;
+    let areas: Vec<f64> = values.iter().map(|v| v * scale).collect();
+
+    let mut tiles = Vec::with_capacity(areas.len());
+    let (mut x, mut y, mut width, mut height) = (x, y, width, height);
+    let mut start = 0;
+
+    while start < areas.len() {
+        let side = width.min(height);
+
+        // Grow the current row while it improves the worst aspect ratio
+        let mut end = start + 1;
+        while end < areas.len()
+            && worst_aspect_ratio(&areas[start..=end], side)
+                <= worst_aspect_ratio(&areas[start..end], side)
+        {
+            end += 1;
+        }
+
+        let row = &areas[start..end];
+        let row_sum: f64 = row.iter().sum();
+
+        if width >= height {
+            // Lay the row out as a column along the left edge
+            let column_width = row_sum / height;
+            let mut tile_y = y;
+            for area in row {
+                let tile_height = area / column_width;
+                tiles.push(TreemapTile {
+                    x,
+                    y: tile_y,
+                    width: column_width,
+                    height: tile_height,
+                });
+                tile_y += tile_height;
+            }
+            x += column_width;
+            width -= column_width;
+        } else {
+            // Lay the row out along the top edge
+            let row_height = row_sum / width;
+            let mut tile_x = x;
+            for area in row {
+                let tile_width = area / row_height;
+                tiles.push(TreemapTile {
+                    x: tile_x,
+                    y,
+                    width: tile_width,
+                    height: row_height,
+                });
+                tile_x += tile_width;
+            }
+            y += row_height;
+            height -= row_height;
+        }
+
+        start = end;
+    }
+
+    tiles
+}
+
+/// Blend two colours, `t` in 0.0..=1.0
+fn blend_colors(from: RGBColor, to: RGBColor, t: f64) -> RGBColor {
+    let t = t.clamp(0.0, 1.0);
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    RGBColor(mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+}
+
+/// Colour for a percentage change, saturating at +/-30%
+fn change_color(theme: &Theme, percentage_change: Option<f64>) -> RGBColor {
+    match percentage_change {
+        Some(pct) if pct >= 0.0 => blend_colors(theme.neutral.0, theme.positive.0, pct / 30.0),
+        Some(pct) => blend_colors(theme.neutral.0, theme.negative.0, -pct / 30.0),
+        None => theme.muted.0,
+    }
+}
+
+/// Format a USD amount using a B/T suffix
+fn format_money_compact(locale: &Locale, value: f64) -> String {
+    if value >= 1_000_000_000_000.0 {
+        locale.format_money(value, COMPARISON_CURRENCY, Magnitude::Trillions, 1)
+    } else {
+        locale.format_money(value, COMPARISON_CURRENCY, Magnitude::Billions, 1)
+    }
+}
+
+/// Format a percentage with an explicit sign, e.g. `+1.5%`
+fn format_signed_percent(locale: &Locale, value: f64, decimals: usize) -> String {
+    let formatted = locale.format_percent(value, decimals);
+    if formatted.starts_with('-') {
+        formatted
+    } else {
+        format!("+{}", formatted)
+    }
+}
+
+/// Create squarified treemap of market cap, coloured by percentage change.
+///
+/// One rectangle per company: the comparison data carries no segment (or sector)
+/// classification, so the rectangles are not grouped by segment.
+fn draw_market_cap_treemap<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
+    records: &[ComparisonRecord],
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
+    let mut companies: Vec<_> = records
+        .iter()
+        .filter_map(|r| {
+            let market_cap = parse_usd_amount(&r.market_cap_to)?;
+            if market_cap > 0.0 {
+                Some((
+                    r.ticker.clone(),
+                    market_cap,
+                    parse_percentage(&r.percentage_change),
+                ))
+            } else {
+                None
+            }
+        })
+        .collect();
+    companies.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
+
+    // Keep the top 50 as individual tiles and group the rest into "Others"
+    let others: f64 = companies.iter().skip(50).map(|c| c.1).sum();
+    companies.truncate(50);
+    if others > 0.0 {
+        companies.push((labels.others.to_string(), others, None));
+    }
+
+    root.fill(&theme.background.0)?;
+
+    // Title
+    root.draw_text(
+        &format!(
+            "{}: {} ({} {})",
+            labels.treemap_title, to_date, labels.change_since, from_date
+        ),
+        &TextStyle::from(theme.title_font()).color(&theme.text.0),
+        (300, 20),
+    )?;
+
+    let values: Vec<f64> = companies.iter().map(|c| c.1).collect();
+    let tiles = squarify(&values, 20.0, 70.0, 1160.0, 660.0);
+
+    for ((ticker, market_cap, pct), tile) in companies.iter().zip(tiles.iter()) {
+        let top_left = (tile.x.round() as i32, tile.y.round() as i32);
+        let bottom_right = (
+            (tile.x + tile.width).round() as i32,
+            (tile.y + tile.height).round() as i32,
+        );
+
+        let is_others = ticker == labels.others;
+        let color = if is_others {
+            theme.panel.0
+        } else {
+            change_color(theme, *pct)
+        };
+        root.draw(&Rectangle::new([top_left, bottom_right], color.filled()))?;
+        root.draw(&Rectangle::new(
+            [top_left, bottom_right],
+            theme.background.0.stroke_width(2),
+        ))?;
+
+        // Only label tiles large enough to fit the text
+        if tile.width < 50.0 || tile.height < 30.0 {
+            continue;
+        }
+
+        let label_color = if is_others {
+            theme.text.0
+        } else {
+            theme.background.0
+        };
+        let font_size = if tile.width > 150.0 && tile.height > 80.0 {
+            18
+        } else {
+            12
+        };
+
+        root.draw_text(
+            ticker,
+            &TextStyle::from(theme.font(font_size)).color(&label_color),
+            (top_left.0 + 6, top_left.1 + 6),
+        )?;
+        root.draw_text(
+            &format_money_compact(locale, *market_cap),
+            &TextStyle::from(theme.font(font_size - 2)).color(&label_color),
+            (top_left.0 + 6, top_left.1 + 8 + font_size as i32),
+        )?;
+        if let Some(pct) = pct {
+            if tile.height > 60.0 {
+                root.draw_text(
+                    &format_signed_percent(locale, *pct, 1),
+                    &TextStyle::from(theme.font(font_size - 2)).color(&label_color),
+                    (top_left.0 + 6, top_left.1 + 10 + 2 * font_size as i32),
+                )?;
+            }
+        }
+    }
+
+    // Colour scale legend
+    let legend_y = 745;
+    for (i, pct) in [-30.0, -15.0, 0.0, 15.0, 30.0].iter().enumerate() {
+        let x = 400 + (i as i32) * 80;
+        root.draw(&Rectangle::new(
+            [(x, legend_y), (x + 70, legend_y + 20)],
+            change_color(theme, Some(*pct)).filled(),
+        ))?;
+        root.draw_text(
+            &format_signed_percent(locale, *pct, 0),
+            &TextStyle::from(theme.font(12)).color(&theme.muted.0),
+            (x + 20, legend_y + 25),
+        )?;
+    }
+
+    draw_watermark(root, theme)?;
 
     Ok(())
 }
 
 /// Draw a donut segment
-fn draw_donut_segment(
-    root: &DrawingArea<SVGBackend, plotters::coord::Shift>,
+fn draw_donut_segment<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     center: (i32, i32),
     outer_radius: f64,
     inner_radius: f64,
     start_angle: f64,
     sweep_angle: f64,
     color: RGBColor,
-) -> Result<()> {
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     let num_points = 100;
     let mut points = Vec::new();
 
@@ -450,11 +745,20 @@ fn draw_donut_segment(
 }
 
 /// Create rank movement chart
-fn create_rank_movement_chart(
+fn draw_rank_movement_chart<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     records: &[ComparisonRecord],
     from_date: &str,
     to_date: &str,
-) -> Result<()> {
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
     // Parse rank changes
     let mut rank_changes: Vec<_> = records
         .iter()
@@ -481,7 +785,7 @@ fn create_rank_movement_chart(
         .collect();
 
     // Get top 10 improvements and declines
-    rank_changes.sort_by(|a, b| b.1.cmp(&a.1));
+    rank_changes.sort_by_key(|r| std::cmp::Reverse(r.1));
     let improvements = rank_changes
         .iter()
         .filter(|r| r.1 > 0)
@@ -489,7 +793,7 @@ fn create_rank_movement_chart(
         .cloned()
         .collect::<Vec<_>>();
 
-    rank_changes.sort_by(|a, b| a.1.cmp(&b.1));
+    rank_changes.sort_by_key(|a| a.1);
     let declines = rank_changes
         .iter()
         .filter(|r| r.1 < 0)
@@ -498,24 +802,22 @@ fn create_rank_movement_chart(
         .collect::<Vec<_>>();
 
     // Create the chart
-    let filename = format!(
-        "output/comparison_{}_to_{}_rank_movements.svg",
-        from_date, to_date
-    );
-    let root = SVGBackend::new(&filename, (1200, 800)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background.0)?;
 
     // Title
     root.draw_text(
-        &format!("Rank Movements: {} to {}", from_date, to_date),
-        &TextStyle::from(("sans-serif", 32).into_font()).color(&BLACK),
+        &format!(
+            "{}: {} {} {}",
+            labels.rank_movements_title, from_date, labels.to, to_date
+        ),
+        &TextStyle::from(theme.title_font()).color(&theme.text.0),
         (350, 30),
     )?;
 
     // Draw improvements
     root.draw_text(
-        "Biggest Rank Improvements",
-        &TextStyle::from(("sans-serif", 20).into_font()).color(&COLOR_TEAL),
+        labels.biggest_rank_improvements,
+        &TextStyle::from(theme.font(20)).color(&theme.rank_up.0),
         (150, 100),
     )?;
 
@@ -526,7 +828,7 @@ fn create_rank_movement_chart(
         // Draw bar
         root.draw(&Rectangle::new(
             [(200, y as i32), (200 + bar_width, (y + 20) as i32)],
-            COLOR_TEAL.filled(),
+            theme.rank_up.0.filled(),
         ))?;
 
         // Company name
@@ -538,7 +840,7 @@ fn create_rank_movement_chart(
 
         root.draw_text(
             &display_name,
-            &TextStyle::from(("sans-serif", 12).into_font()),
+            &TextStyle::from(theme.font(12)).color(&theme.text.0),
             (10, y as i32),
         )?;
 
@@ -550,15 +852,15 @@ fn create_rank_movement_chart(
                 from.as_ref().unwrap_or(&"NA".to_string()),
                 to.as_ref().unwrap_or(&"NA".to_string())
             ),
-            &TextStyle::from(("sans-serif", 11).into_font()).color(&COLOR_TEAL),
+            &TextStyle::from(theme.font(11)).color(&theme.rank_up.0),
             (210 + bar_width, y as i32 + 5),
         )?;
     }
 
     // Draw declines
     root.draw_text(
-        "Biggest Rank Declines",
-        &TextStyle::from(("sans-serif", 20).into_font()).color(&COLOR_CORAL),
+        labels.biggest_rank_declines,
+        &TextStyle::from(theme.font(20)).color(&theme.rank_down.0),
         (150, 450),
     )?;
 
@@ -569,7 +871,7 @@ fn create_rank_movement_chart(
         // Draw bar
         root.draw(&Rectangle::new(
             [(200, y as i32), (200 + bar_width, (y + 20) as i32)],
-            COLOR_CORAL.filled(),
+            theme.rank_down.0.filled(),
         ))?;
 
         // Company name
@@ -581,7 +883,7 @@ fn create_rank_movement_chart(
 
         root.draw_text(
             &display_name,
-            &TextStyle::from(("sans-serif", 12).into_font()),
+            &TextStyle::from(theme.font(12)).color(&theme.text.0),
             (10, y as i32),
         )?;
 
@@ -593,23 +895,31 @@ fn create_rank_movement_chart(
                 from.as_ref().unwrap_or(&"NA".to_string()),
                 to.as_ref().unwrap_or(&"NA".to_string())
             ),
-            &TextStyle::from(("sans-serif", 11).into_font()).color(&COLOR_CORAL),
+            &TextStyle::from(theme.font(11)).color(&theme.rank_down.0),
             (210 + bar_width, y as i32 + 5),
         )?;
     }
 
-    root.present()?;
-    println!("✅ Generated rank movements chart: {}", filename);
+    draw_watermark(root, theme)?;
 
     Ok(())
 }
 
 /// Create market summary dashboard
-fn create_summary_dashboard(
+fn draw_summary_dashboard<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     records: &[ComparisonRecord],
     from_date: &str,
     to_date: &str,
-) -> Result<()> {
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let theme = &presentation.theme;
+    let locale = &presentation.locale;
+    let labels = locale.labels();
+
     // Calculate metrics
     let total_from: f64 = records
         .iter()
@@ -641,73 +951,88 @@ fn create_summary_dashboard(
     let unchanged = records.len() - gainers - losers;
 
     // Create the dashboard
-    let filename = format!(
-        "output/comparison_{}_to_{}_summary_dashboard.svg",
-        from_date, to_date
-    );
-    let root = SVGBackend::new(&filename, (1200, 800)).into_drawing_area();
-    root.fill(&WHITE)?;
+    root.fill(&theme.background.0)?;
 
     // Title
     root.draw_text(
-        &format!("Market Summary: {} to {}", from_date, to_date),
-        &TextStyle::from(("sans-serif", 36).into_font()).color(&BLACK),
+        &format!(
+            "{}: {} {} {}",
+            labels.market_summary_title, from_date, labels.to, to_date
+        ),
+        &TextStyle::from(theme.title_font()).color(&theme.text.0),
         (300, 40),
     )?;
 
     // Main metric box
     let metric_color = if total_change >= 0.0 {
-        COLOR_EMERALD
+        theme.positive.0
     } else {
-        COLOR_ROSE
+        theme.negative.0
     };
     let arrow = if total_change >= 0.0 { "↑" } else { "↓" };
 
     // Background box
     root.draw(&Rectangle::new(
         [(100, 120), (500, 280)],
-        COLOR_GRAY_LIGHT.filled(),
+        theme.panel.0.filled(),
     ))?;
 
     root.draw_text(
-        "Total Market Cap Change",
-        &TextStyle::from(("sans-serif", 18).into_font()).color(&COLOR_SLATE),
+        labels.total_market_cap_change,
+        &TextStyle::from(theme.font(18)).color(&theme.muted.0),
         (220, 140),
     )?;
 
     root.draw_text(
-        &format!("{} ${:.2}B", arrow, total_change.abs() / 1_000_000_000.0),
-        &TextStyle::from(("sans-serif", 48).into_font()).color(&metric_color),
+        &format!(
+            "{} {}",
+            arrow,
+            locale.format_money(
+                total_change.abs(),
+                COMPARISON_CURRENCY,
+                Magnitude::Billions,
+                2
+            )
+        ),
+        &TextStyle::from(theme.font(48)).color(&metric_color),
         (180, 190),
     )?;
 
     root.draw_text(
-        &format!("{:.2}%", total_pct_change),
-        &TextStyle::from(("sans-serif", 32).into_font()).color(&metric_color),
+        &locale.format_percent(total_pct_change, 2),
+        &TextStyle::from(theme.font(32)).color(&metric_color),
         (250, 240),
     )?;
 
     // From and To values
     root.draw(&Rectangle::new(
         [(600, 120), (1100, 280)],
-        COLOR_GRAY_LIGHT.filled(),
+        theme.panel.0.filled(),
     ))?;
 
     root.draw_text(
-        &format!("{}: ${:.2}T", from_date, total_from / 1_000_000_000_000.0),
-        &TextStyle::from(("sans-serif", 20).into_font()),
+        &format!(
+            "{}: {}",
+            from_date,
+            locale.format_money(total_from, COMPARISON_CURRENCY, Magnitude::Trillions, 2)
+        ),
+        &TextStyle::from(theme.font(20)).color(&theme.text.0),
         (650, 160),
     )?;
 
     root.draw_text(
-        &format!("{}: ${:.2}T", to_date, total_to / 1_000_000_000_000.0),
-        &TextStyle::from(("sans-serif", 20).into_font()),
+        &format!(
+            "{}: {}",
+            to_date,
+            locale.format_money(total_to, COMPARISON_CURRENCY, Magnitude::Trillions, 2)
+        ),
+        &TextStyle::from(theme.font(20)).color(&theme.text.0),
         (650, 200),
     )?;
 
     root.draw_text(
-        &format!("Companies Analyzed: {}", records.len()),
-        &TextStyle::from(("sans-serif", 16).into_font()).color(&COLOR_SLATE),
+        &format!("{}: {}", labels.companies_analyzed, records.len()),
+        &TextStyle::from(theme.font(16)).color(&theme.muted.0),
         (650, 240),
     )?;
 
@@ -716,8 +1041,8 @@ fn create_summary_dashboard(
     let pie_radius = 120.0;
 
     root.draw_text(
-        "Market Movement Distribution",
-        &TextStyle::from(("sans-serif", 20).into_font()),
+        labels.movement_distribution,
+        &TextStyle::from(theme.font(20)).color(&theme.text.0),
         (180, 350),
     )?;
 
@@ -728,82 +1053,85 @@ fn create_summary_dashboard(
 
     // Draw pie segments
     draw_pie_segment(
-        &root,
+        root,
         pie_center,
         pie_radius,
         -90.0,
         gainers_angle,
-        COLOR_EMERALD,
+        theme.positive.0,
     )?;
     draw_pie_segment(
-        &root,
+        root,
         pie_center,
         pie_radius,
         -90.0 + gainers_angle,
         losers_angle,
-        COLOR_ROSE,
+        theme.negative.0,
     )?;
     draw_pie_segment(
-        &root,
+        root,
         pie_center,
         pie_radius,
         -90.0 + gainers_angle + losers_angle,
         360.0 - gainers_angle - losers_angle,
-        COLOR_SLATE,
+        theme.muted.0,
     )?;
 
     // Legend for pie chart
     root.draw(&Rectangle::new(
         [(500, 450), (520, 470)],
-        COLOR_EMERALD.filled(),
+        theme.positive.0.filled(),
     ))?;
     root.draw_text(
         &format!(
-            "Gainers: {} ({:.1}%)",
+            "{}: {} ({})",
+            labels.gainers,
             gainers,
-            (gainers as f64 / total_companies as f64) * 100.0
+            locale.format_percent((gainers as f64 / total_companies as f64) * 100.0, 1)
         ),
-        &TextStyle::from(("sans-serif", 14).into_font()),
+        &TextStyle::from(theme.font(14)).color(&theme.text.0),
         (530, 455),
     )?;
 
     root.draw(&Rectangle::new(
         [(500, 490), (520, 510)],
-        COLOR_ROSE.filled(),
+        theme.negative.0.filled(),
     ))?;
     root.draw_text(
         &format!(
-            "Losers: {} ({:.1}%)",
+            "{}: {} ({})",
+            labels.losers,
             losers,
-            (losers as f64 / total_companies as f64) * 100.0
+            locale.format_percent((losers as f64 / total_companies as f64) * 100.0, 1)
         ),
-        &TextStyle::from(("sans-serif", 14).into_font()),
+        &TextStyle::from(theme.font(14)).color(&theme.text.0),
         (530, 495),
     )?;
 
     root.draw(&Rectangle::new(
         [(500, 530), (520, 550)],
-        COLOR_SLATE.filled(),
+        theme.muted.0.filled(),
     ))?;
     root.draw_text(
         &format!(
-            "Unchanged: {} ({:.1}%)",
+            "{}: {} ({})",
+            labels.unchanged,
             unchanged,
-            (unchanged as f64 / total_companies as f64) * 100.0
+            locale.format_percent((unchanged as f64 / total_companies as f64) * 100.0, 1)
         ),
-        &TextStyle::from(("sans-serif", 14).into_font()),
+        &TextStyle::from(theme.font(14)).color(&theme.text.0),
         (530, 535),
     )?;
 
     // Key statistics box
     root.draw(&Rectangle::new(
         [(750, 400), (1100, 620)],
-        COLOR_GRAY_LIGHT.filled(),
+        theme.panel.0.filled(),
     ))?;
 
     root.draw_text(
-        "Key Statistics",
-        &TextStyle::from(("sans-serif", 20).into_font()),
+        labels.key_statistics,
+        &TextStyle::from(theme.font(20)).color(&theme.text.0),
         (850, 420),
     )?;
 
@@ -815,8 +1143,12 @@ fn create_summary_dashboard(
         / records.len() as f64;
 
     root.draw_text(
-        &format!("Average Change: {:.2}%", avg_change),
-        &TextStyle::from(("sans-serif", 14).into_font()),
+        &format!(
+            "{}: {}",
+            labels.average_change,
+            locale.format_percent(avg_change, 2)
+        ),
+        &TextStyle::from(theme.font(14)).color(&theme.text.0),
         (780, 460),
     )?;
 
@@ -842,16 +1174,19 @@ fn create_summary_dashboard(
             gainer.name.clone()
         };
         root.draw_text(
-            &format!("Top Gainer: {}", name),
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            &format!("{}: {}", labels.top_gainer, name),
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (780, 490),
         )?;
         root.draw_text(
             &format!(
-                "  +{:.1}%",
-                parse_percentage(&gainer.percentage_change).unwrap_or(0.0)
+                "  +{}",
+                locale.format_percent(
+                    parse_percentage(&gainer.percentage_change).unwrap_or(0.0),
+                    1
+                )
             ),
-            &TextStyle::from(("sans-serif", 14).into_font()).color(&COLOR_EMERALD),
+            &TextStyle::from(theme.font(14)).color(&theme.positive.0),
             (780, 510),
         )?;
     }
@@ -863,16 +1198,16 @@ fn create_summary_dashboard(
             loser.name.clone()
         };
         root.draw_text(
-            &format!("Top Loser: {}", name),
-            &TextStyle::from(("sans-serif", 14).into_font()),
+            &format!("{}: {}", labels.top_loser, name),
+            &TextStyle::from(theme.font(14)).color(&theme.text.0),
             (780, 540),
         )?;
         root.draw_text(
             &format!(
-                "  {:.1}%",
-                parse_percentage(&loser.percentage_change).unwrap_or(0.0)
+                "  {}",
+                locale.format_percent(parse_percentage(&loser.percentage_change).unwrap_or(0.0), 1)
             ),
-            &TextStyle::from(("sans-serif", 14).into_font()).color(&COLOR_ROSE),
+            &TextStyle::from(theme.font(14)).color(&theme.negative.0),
             (780, 560),
         )?;
     }
@@ -880,28 +1215,31 @@ fn create_summary_dashboard(
     // Footer
     root.draw_text(
         &format!(
-            "Generated on {}",
+            "{} {}",
+            labels.generated_on,
             chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
         ),
-        &TextStyle::from(("sans-serif", 10).into_font()).color(&COLOR_SLATE),
+        &TextStyle::from(theme.font(10)).color(&theme.muted.0),
         (450, 750),
     )?;
 
-    root.present()?;
-    println!("✅ Generated summary dashboard: {}", filename);
+    draw_watermark(root, theme)?;
 
     Ok(())
 }
 
 /// Draw a pie segment
-fn draw_pie_segment(
-    root: &DrawingArea<SVGBackend, plotters::coord::Shift>,
+fn draw_pie_segment<DB: DrawingBackend>(
+    root: &ChartArea<DB>,
     center: (i32, i32),
     radius: f64,
     start_angle: f64,
     sweep_angle: f64,
     color: RGBColor,
-) -> Result<()> {
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
     let num_points = 100;
     let mut points = Vec::new();
 
@@ -920,29 +1258,277 @@ fn draw_pie_segment(
     Ok(())
 }
 
-/// Main function to generate all charts
-pub async fn generate_all_charts(from_date: &str, to_date: &str) -> Result<()> {
-    println!(
-        "Generating visualization charts for {} to {}",
-        from_date, to_date
-    );
+/// Draw the theme logo and watermark, if any, in the bottom right corner
+pub fn draw_watermark<DB: DrawingBackend>(
+    root: &DrawingArea<DB, plotters::coord::Shift>,
+    theme: &Theme,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let (width, height) = root.dim_in_pixel();
+    let mut right = width as i32 - 15;
+
+    if let Some(logo) = &theme.logo {
+        let (logo_width, logo_height) = logo.image.dimensions();
+        let position = (
+            right - logo_width as i32,
+            height as i32 - 10 - logo_height as i32,
+        );
+        let element: BitMapElement<_> = BitMapElement::with_owned_buffer(
+            position,
+            (logo_width, logo_height),
+            logo.rgb_on(theme.background.0),
+        )
+        .context("Logo has no pixels")?;
+        root.draw(&element)?;
+        right -= logo_width as i32 + 10;
+    }
 
-    // Find and read the comparison CSV
+    if let Some(watermark) = &theme.watermark {
+        let color = theme.muted.0.mix(0.4);
+        let style = TextStyle::from(theme.font(14))
+            .color(&color)
+            .pos(Pos::new(HPos::Right, VPos::Bottom));
+        root.draw_text(watermark, &style, (right, height as i32 - 10))?;
+    }
+
+    Ok(())
+}
+
+/// Draw one chart kind on any backend
+fn draw_chart<DB: DrawingBackend>(
+    kind: &str,
+    root: &ChartArea<DB>,
+    records: &[ComparisonRecord],
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<()>
+where
+    DB::ErrorType: 'static,
+{
+    let (_, _, draw) = charts::<DB>()
+        .into_iter()
+        .find(|(name, _, _)| *name == kind)
+        .with_context(|| format!("Unknown chart kind: {}", kind))?;
+    draw(root, records, from_date, to_date, presentation)
+}
+
+/// Chart kinds with their human-readable labels
+fn chart_labels() -> impl Iterator<Item = (&'static str, &'static str)> {
+    charts::<SVGBackend<'static>>()
+        .into_iter()
+        .map(|(kind, label, _)| (kind, label))
+}
+
+/// Path of a comparison chart in `output/`, e.g. `comparison_2025-07-01_to_2025-08-01_market_treemap.png`
+pub fn chart_path(from_date: &str, to_date: &str, kind: &str, extension: &str) -> String {
+    format!(
+        "output/comparison_{}_to_{}_{}.{}",
+        from_date, to_date, kind, extension
+    )
+}
+
+/// Read the comparison CSV of two dates
+fn load_chart_records(from_date: &str, to_date: &str) -> Result<Vec<ComparisonRecord>> {
     let csv_path = find_comparison_csv(from_date, to_date)?;
-    println!("Reading data from: {}", csv_path);
+    status!("Reading data from: {}", csv_path);
 
     let records = read_comparison_data(&csv_path)?;
-    println!("Loaded {} companies for visualization", records.len());
+    status!("Loaded {} companies for visualization", records.len());
 
-    // Generate each chart type
-    println!("\nGenerating charts...");
+    Ok(records)
+}
 
-    create_gainers_losers_chart(&records, from_date, to_date)?;
-    create_market_distribution_chart(&records, from_date, to_date)?;
-    create_rank_movement_chart(&records, from_date, to_date)?;
-    create_summary_dashboard(&records, from_date, to_date)?;
+/// Main function to generate all charts
+pub async fn generate_all_charts(
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<()> {
+    status!(
+        "Generating visualization charts for {} to {}",
+        from_date,
+        to_date
+    );
 
-    println!("\n✅ All charts generated successfully!");
+    let records = load_chart_records(from_date, to_date)?;
+
+    // Generate each chart type
+    status!("\nGenerating charts...");
+
+    for (kind, label) in chart_labels() {
+        let filename = chart_path(from_date, to_date, kind, "svg");
+        let root = SVGBackend::new(&filename, CHART_SIZE).into_drawing_area();
+        draw_chart(kind, &root, &records, from_date, to_date, presentation)?;
+        root.present()?;
+        status!("✅ Generated {}: {}", label, filename);
+    }
+
+    status!("\n✅ All charts generated successfully!");
 
     Ok(())
 }
+
+/// Render all charts as PNG files, for email where SVG attachments are not accepted;
+/// returns the files written
+pub fn generate_png_charts(
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<Vec<String>> {
+    let records = load_chart_records(from_date, to_date)?;
+
+    chart_labels()
+        .map(|(kind, label)| {
+            let filename = chart_path(from_date, to_date, kind, "png");
+            {
+                let root = BitMapBackend::new(&filename, CHART_SIZE).into_drawing_area();
+                draw_chart(kind, &root, &records, from_date, to_date, presentation)?;
+                root.present()?;
+            }
+            status!("✅ Generated {}: {}", label, filename);
+            Ok(filename)
+        })
+        .collect()
+}
+
+/// Names of the chart kinds that can be rendered
+pub fn chart_kinds() -> impl Iterator<Item = &'static str> {
+    chart_labels().map(|(kind, _)| kind)
+}
+
+/// Render a single chart kind from in-memory comparison data and return the SVG document
+pub fn render_chart_svg(
+    kind: &str,
+    comparisons: &[MarketCapComparison],
+    from_date: &str,
+    to_date: &str,
+    presentation: &Presentation,
+) -> Result<String> {
+    // Round-trip through the comparison CSV format so charts see exactly what the CLI exports
+    let mut csv_buffer = Vec::new();
+    write_comparison_csv(&mut csv_buffer, comparisons)?;
+    let records = read_comparison_records(csv_buffer.as_slice())?;
+
+    let mut svg = String::new();
+    {
+        let root = SVGBackend::with_string(&mut svg, CHART_SIZE).into_drawing_area();
+        draw_chart(kind, &root, &records, from_date, to_date, presentation)?;
+        root.present()?;
+    }
+
+    Ok(svg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_squarify_covers_area() {
+        let values = [6.0, 6.0, 4.0, 3.0, 2.0, 2.0, 1.0];
+        let tiles = squarify(&values, 0.0, 0.0, 6.0, 4.0);
+
+        assert_eq!(tiles.len(), values.len());
+
+        // Each tile's area is proportional to its value
+        for (value, tile) in values.iter().zip(tiles.iter()) {
+            assert!((tile.width * tile.height - value).abs() < 1e-9);
+        }
+
+        // Tiles stay within the bounds
+        for tile in &tiles {
+            assert!(tile.x >= -1e-9 && tile.x + tile.width <= 6.0 + 1e-9);
+            assert!(tile.y >= -1e-9 && tile.y + tile.height <= 4.0 + 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_squarify_empty() {
+        assert!(squarify(&[], 0.0, 0.0, 10.0, 10.0).is_empty());
+        assert!(squarify(&[0.0], 0.0, 0.0, 10.0, 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_change_color() {
+        let theme = Theme::default();
+        assert_eq!(change_color(&theme, Some(0.0)), theme.neutral.0);
+        assert_eq!(change_color(&theme, Some(30.0)), theme.positive.0);
+        assert_eq!(change_color(&theme, Some(-100.0)), theme.negative.0);
+        assert_eq!(change_color(&theme, None), theme.muted.0);
+    }
+
+    fn sample_comparisons() -> Vec<MarketCapComparison> {
+        [("AAA", 200.0, 150.0), ("BBB", 100.0, 300.0)]
+            .into_iter()
+            .enumerate()
+            .map(|(i, (ticker, from, to))| MarketCapComparison {
+                ticker: ticker.to_string(),
+                name: ticker.to_string(),
+                market_cap_from: Some(from),
+                market_cap_to: Some(to),
+                absolute_change: Some(to - from),
+                percentage_change: Some((to - from) / from * 100.0),
+                rank_from: Some(i + 1),
+                rank_to: Some(2 - i),
+                rank_change: Some(if i == 0 { -1 } else { 1 }),
+                market_share_from: Some(from / 3.0),
+                market_share_to: Some(to / 4.5),
+                source_from: None,
+                source_to: None,
+                overridden_from: None,
+                overridden_to: None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_charts_render_to_bitmaps() {
+        let mut csv_buffer = Vec::new();
+        write_comparison_csv(&mut csv_buffer, &sample_comparisons()).unwrap();
+        let records = read_comparison_records(csv_buffer.as_slice()).unwrap();
+
+        let presentation = Presentation::default();
+        for kind in chart_kinds() {
+            let mut pixels = vec![0u8; (CHART_SIZE.0 * CHART_SIZE.1 * 3) as usize];
+            {
+                let root = BitMapBackend::with_buffer(&mut pixels, CHART_SIZE).into_drawing_area();
+                draw_chart(
+                    kind,
+                    &root,
+                    &records,
+                    "2025-01-02",
+                    "2025-02-03",
+                    &presentation,
+                )
+                .unwrap();
+                root.present().unwrap();
+            }
+            assert!(pixels.iter().any(|&p| p != 0), "{} drew nothing", kind);
+        }
+    }
+
+    #[test]
+    fn test_logo_is_embedded_in_svg() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("logo.png");
+        image::RgbaImage::from_pixel(20, 10, image::Rgba([255, 0, 0, 255]))
+            .save(&path)
+            .unwrap();
+
+        let mut presentation = Presentation::default();
+        presentation.theme.logo = Some(crate::theme::Logo::try_from(path).unwrap());
+        let svg = render_chart_svg(
+            "market_treemap",
+            &sample_comparisons(),
+            "2025-01-02",
+            "2025-02-03",
+            &presentation,
+        )
+        .unwrap();
+        // Embedded as one PNG image rather than drawn pixel by pixel
+        assert!(svg.contains("<image"));
+    }
+}
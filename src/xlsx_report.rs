@@ -0,0 +1,508 @@
+// SPDX-FileCopyrightText: 2025 Joost van der Laan <joost@fashionunited.com>
+//
+// SPDX-License-Identifier: AGPL-3.0-only
+
+use anyhow::Result;
+use rust_xlsxwriter::{
+    Chart, ChartType, ConditionalFormatCell, ConditionalFormatCellRule, Format, Workbook, Worksheet,
+};
+
+use crate::compare_marketcaps::{ComparisonSummary, MarketCapComparison};
+use crate::theme::Locale;
+
+/// A value in a worksheet cell; `Empty` leaves the cell blank
+#[derive(Debug, Clone, PartialEq)]
+pub enum Cell {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+    Empty,
+}
+
+impl From<Option<f64>> for Cell {
+    fn from(value: Option<f64>) -> Self {
+        value.map(Cell::Number).unwrap_or(Cell::Empty)
+    }
+}
+
+impl From<Option<&str>> for Cell {
+    fn from(value: Option<&str>) -> Self {
+        value
+            .map(|v| Cell::Text(v.to_string()))
+            .unwrap_or(Cell::Empty)
+    }
+}
+
+/// A company in a ranked list sheet
+#[derive(Debug, Clone)]
+pub struct RankedCompany {
+    pub ticker: String,
+    pub name: String,
+    pub market_cap_original: f64,
+    pub original_currency: String,
+    pub market_cap_eur: f64,
+    pub market_cap_usd: f64,
+    pub exchange: Option<String>,
+    pub active: Option<bool>,
+}
+
+const RANKING_HEADERS: [&str; 9] = [
+    "Rank",
+    "Ticker",
+    "Name",
+    "Market Cap (EUR)",
+    "Market Cap (USD)",
+    "Market Cap (Original)",
+    "Original Currency",
+    "Exchange",
+    "Active",
+];
+
+const COMPARISON_HEADERS: [&str; 15] = [
+    "Ticker",
+    "Name",
+    "Market Cap From (USD)",
+    "Market Cap To (USD)",
+    "Absolute Change (USD)",
+    "Percentage Change (%)",
+    "Rank From",
+    "Rank To",
+    "Rank Change",
+    "Market Share From (%)",
+    "Market Share To (%)",
+    "Source From",
+    "Source To",
+    "Overridden From",
+    "Overridden To",
+];
+
+fn money() -> Format {
+    Format::new().set_num_format("#,##0")
+}
+
+fn decimal() -> Format {
+    Format::new().set_num_format("0.00")
+}
+
+fn bold() -> Format {
+    Format::new().set_bold()
+}
+
+fn write_cell(
+    sheet: &mut Worksheet,
+    row: u32,
+    col: u16,
+    cell: &Cell,
+    format: &Format,
+) -> Result<()> {
+    match cell {
+        Cell::Text(text) => {
+            sheet.write_string_with_format(row, col, text, format)?;
+        }
+        Cell::Number(number) => {
+            sheet.write_number_with_format(row, col, *number, format)?;
+        }
+        Cell::Bool(value) => {
+            sheet.write_boolean_with_format(row, col, *value, format)?;
+        }
+        Cell::Empty => {}
+    }
+    Ok(())
+}
+
+/// Write a bold, frozen header row and the rows below it, formatting numbers with
+/// `number_format`
+fn write_table(
+    sheet: &mut Worksheet,
+    headers: &[&str],
+    rows: &[Vec<Cell>],
+    number_format: impl Fn(usize) -> Format,
+) -> Result<()> {
+    for (col, header) in headers.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *header, &bold())?;
+    }
+    sheet.set_freeze_panes(1, 0)?;
+
+    for (i, row) in rows.iter().enumerate() {
+        for (col, cell) in row.iter().enumerate() {
+            write_cell(sheet, i as u32 + 1, col as u16, cell, &number_format(col))?;
+        }
+    }
+    sheet.autofit();
+    Ok(())
+}
+
+/// Longest worksheet name Excel accepts
+const MAX_SHEET_NAME: usize = 31;
+
+/// Add a worksheet named after `name`, made valid for Excel: characters it does not
+/// allow become `_`, long names are cut to 31 characters, and a name already in the
+/// workbook gets a number, e.g. `Top 200 (2)`
+fn add_named_sheet<'a>(workbook: &'a mut Workbook, name: &str) -> Result<&'a mut Worksheet> {
+    let cleaned: String = name
+        .chars()
+        .map(|c| if "*?:[]\\/".contains(c) { '_' } else { c })
+        .collect();
+    let cleaned = cleaned.trim_matches(|c: char| c == '\'' || c.is_whitespace());
+    let cleaned = if cleaned.is_empty() { "Sheet" } else { cleaned };
+    let truncated = |max: usize| -> String {
+        let name: String = cleaned.chars().take(max).collect();
+        name.trim_end_matches(|c: char| c == '\'' || c.is_whitespace())
+            .to_string()
+    };
+
+    // Excel compares sheet names case-insensitively
+    let taken: Vec<String> = workbook
+        .worksheets()
+        .iter()
+        .map(|sheet| sheet.name().to_lowercase())
+        .collect();
+    let mut unique = truncated(MAX_SHEET_NAME);
+    let mut number = 2;
+    while taken.contains(&unique.to_lowercase()) {
+        let suffix = format!(" ({})", number);
+        unique = format!("{}{}", truncated(MAX_SHEET_NAME - suffix.len()), suffix);
+        number += 1;
+    }
+
+    let sheet = workbook.add_worksheet();
+    sheet.set_name(unique)?;
+    Ok(sheet)
+}
+
+/// Add a sheet with a plain table, e.g. the full market cap history
+pub fn add_table_sheet(
+    workbook: &mut Workbook,
+    name: &str,
+    headers: &[&str],
+    rows: &[Vec<Cell>],
+) -> Result<()> {
+    let sheet = add_named_sheet(workbook, name)?;
+    write_table(sheet, headers, rows, |_| Format::new())
+}
+
+/// Add a ranked list with a bar chart of its ten largest companies
+pub fn add_ranking_sheet(
+    workbook: &mut Workbook,
+    name: &str,
+    companies: &[RankedCompany],
+) -> Result<()> {
+    let rows: Vec<Vec<Cell>> = companies
+        .iter()
+        .enumerate()
+        .map(|(i, c)| {
+            vec![
+                Cell::Number((i + 1) as f64),
+                Cell::Text(c.ticker.clone()),
+                Cell::Text(c.name.clone()),
+                Cell::Number(c.market_cap_eur),
+                Cell::Number(c.market_cap_usd),
+                Cell::Number(c.market_cap_original),
+                Cell::Text(c.original_currency.clone()),
+                c.exchange.as_deref().into(),
+                c.active.map(Cell::Bool).unwrap_or(Cell::Empty),
+            ]
+        })
+        .collect();
+
+    let sheet = add_named_sheet(workbook, name)?;
+    write_table(sheet, &RANKING_HEADERS, &rows, |col| match col {
+        3..=5 => money(),
+        _ => Format::new(),
+    })?;
+
+    if !companies.is_empty() {
+        let last = companies.len().min(10) as u32;
+        let name = sheet.name();
+        let mut chart = Chart::new(ChartType::Bar);
+        chart.title().set_name("Top 10 by market cap (EUR)");
+        chart
+            .add_series()
+            .set_categories((name.as_str(), 1, 2, last, 2))
+            .set_values((name.as_str(), 1, 3, last, 3));
+        chart.legend().set_hidden();
+        chart.y_axis().set_reverse();
+        sheet.insert_chart(1, RANKING_HEADERS.len() as u16 + 1, &chart)?;
+    }
+    Ok(())
+}
+
+/// Add the comparison table, colouring gains green and losses red
+pub fn add_comparison_sheet(
+    workbook: &mut Workbook,
+    comparisons: &[MarketCapComparison],
+) -> Result<()> {
+    let rows: Vec<Vec<Cell>> = comparisons
+        .iter()
+        .map(|c| {
+            vec![
+                Cell::Text(c.ticker.clone()),
+                Cell::Text(c.name.clone()),
+                c.market_cap_from.into(),
+                c.market_cap_to.into(),
+                c.absolute_change.into(),
+                c.percentage_change.into(),
+                c.rank_from.map(|r| r as f64).into(),
+                c.rank_to.map(|r| r as f64).into(),
+                c.rank_change.map(f64::from).into(),
+                c.market_share_from.into(),
+                c.market_share_to.into(),
+                c.source_from.as_deref().into(),
+                c.source_to.as_deref().into(),
+                c.overridden_from.as_deref().into(),
+                c.overridden_to.as_deref().into(),
+            ]
+        })
+        .collect();
+
+    let sheet = add_named_sheet(workbook, "Comparison")?;
+    write_table(sheet, &COMPARISON_HEADERS, &rows, |col| match col {
+        2..=4 => money(),
+        5 | 9 | 10 => decimal(),
+        _ => Format::new(),
+    })?;
+
+    if !comparisons.is_empty() {
+        let gain = ConditionalFormatCell::new()
+            .set_rule(ConditionalFormatCellRule::GreaterThan(0))
+            .set_format(
+                Format::new()
+                    .set_font_color("006100")
+                    .set_background_color("C6EFCE"),
+            );
+        let loss = ConditionalFormatCell::new()
+            .set_rule(ConditionalFormatCellRule::LessThan(0))
+            .set_format(
+                Format::new()
+                    .set_font_color("9C0006")
+                    .set_background_color("FFC7CE"),
+            );
+        let last = comparisons.len() as u32;
+        // Absolute change, percentage change and rank change
+        for (first_col, last_col) in [(4, 5), (8, 8)] {
+            sheet.add_conditional_format(1, first_col, last, last_col, &gain)?;
+            sheet.add_conditional_format(1, first_col, last, last_col, &loss)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add the summary of `export_summary_report` with a chart of the top gainers
+pub fn add_summary_sheet(
+    workbook: &mut Workbook,
+    summary: &ComparisonSummary,
+    from_date: &str,
+    to_date: &str,
+    locale: &Locale,
+) -> Result<()> {
+    let labels = locale.labels();
+    let sheet = add_named_sheet(workbook, "Summary")?;
+
+    sheet.write_string_with_format(
+        0,
+        0,
+        format!(
+            "{}: {} {} {}",
+            labels.comparison_title, from_date, labels.to, to_date
+        ),
+        &Format::new().set_bold().set_font_size(14),
+    )?;
+
+    sheet.write_string_with_format(2, 0, labels.overview_statistics, &bold())?;
+    let overview = [
+        (
+            format!("{} {}", labels.total_market_cap_on, from_date),
+            summary.total_from,
+            None,
+        ),
+        (
+            format!("{} {}", labels.total_market_cap_on, to_date),
+            summary.total_to,
+            None,
+        ),
+        (
+            labels.total_change.to_string(),
+            summary.total_change,
+            Some(summary.total_pct_change),
+        ),
+    ];
+    let mut row = 3;
+    for (label, value, pct) in overview {
+        sheet.write_string(row, 0, label)?;
+        sheet.write_number_with_format(row, 1, value, &money())?;
+        if let Some(pct) = pct {
+            sheet.write_number_with_format(row, 2, pct, &decimal())?;
+        }
+        row += 1;
+    }
+
+    // Each list shows the name and ticker with two figures
+    type Figures = fn(&MarketCapComparison) -> [Cell; 2];
+    let change: Figures = |c| [c.percentage_change.into(), c.absolute_change.into()];
+    let ranks: Figures = |c| {
+        [
+            c.rank_change.map(f64::from).into(),
+            Cell::Text(format!(
+                "#{} → #{}",
+                c.rank_from.unwrap_or(0),
+                c.rank_to.unwrap_or(0)
+            )),
+        ]
+    };
+    let sections: [(&str, &[&MarketCapComparison], Figures); 6] = [
+        (labels.top_gainers_pct, &summary.top_gainers, change),
+        (labels.top_losers_pct, &summary.top_losers, change),
+        (
+            labels.top_absolute_gain,
+            &summary.top_absolute_gains,
+            change,
+        ),
+        (
+            labels.top_absolute_loss,
+            &summary.top_absolute_losses,
+            change,
+        ),
+        (
+            labels.biggest_rank_improvements,
+            &summary.rank_improvements,
+            ranks,
+        ),
+        (labels.biggest_rank_declines, &summary.rank_declines, ranks),
+    ];
+
+    let mut gainers_rows = None;
+    for (title, companies, figures) in sections {
+        row += 1;
+        sheet.write_string_with_format(row, 0, title, &bold())?;
+        let first = row + 1;
+        for (i, comp) in companies.iter().enumerate() {
+            row += 1;
+            sheet.write_number(row, 0, (i + 1) as f64)?;
+            sheet.write_string(row, 1, &comp.name)?;
+            sheet.write_string(row, 2, &comp.ticker)?;
+            let [a, b] = figures(comp);
+            write_cell(sheet, row, 3, &a, &decimal())?;
+            write_cell(sheet, row, 4, &b, &money())?;
+        }
+        if gainers_rows.is_none() && !companies.is_empty() {
+            gainers_rows = Some((first, row));
+        }
+        row += 1;
+    }
+
+    row += 1;
+    sheet.write_string_with_format(row, 0, labels.concentration_analysis, &bold())?;
+    for (label, count) in [
+        (labels.companies_increased, summary.companies_increased),
+        (labels.companies_decreased, summary.companies_decreased),
+        (labels.new_companies, summary.new_companies),
+        (labels.companies_removed, summary.companies_removed),
+    ] {
+        row += 1;
+        sheet.write_string(row, 0, label)?;
+        sheet.write_number(row, 1, count as f64)?;
+    }
+    sheet.autofit();
+
+    if let Some((first, last)) = gainers_rows.filter(|_| !summary.top_gainers.is_empty()) {
+        let mut chart = Chart::new(ChartType::Column);
+        chart.title().set_name(labels.top_gainers_pct);
+        chart
+            .add_series()
+            .set_categories(("Summary", first, 1, last, 1))
+            .set_values(("Summary", first, 3, last, 3));
+        chart.legend().set_hidden();
+        sheet.insert_chart(2, 6, &chart)?;
+    }
+    Ok(())
+}
+
+/// Save a workbook, creating `output/` if needed
+pub fn save(workbook: &mut Workbook, filename: &str) -> Result<()> {
+    std::fs::create_dir_all("output")?;
+    workbook.save(filename)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_workbook_sheets() {
+        let companies: Vec<RankedCompany> = ["MC.PA", "ITX.MC", "NKE"]
+            .iter()
+            .enumerate()
+            .map(|(i, ticker)| RankedCompany {
+                ticker: ticker.to_string(),
+                name: ticker.to_string(),
+                market_cap_original: 300e9 / (i + 1) as f64,
+                original_currency: "EUR".to_string(),
+                market_cap_eur: 300e9 / (i + 1) as f64,
+                market_cap_usd: 330e9 / (i + 1) as f64,
+                exchange: None,
+                active: Some(i != 1),
+            })
+            .collect();
+        let comparisons = vec![MarketCapComparison {
+            ticker: "NKE".to_string(),
+            name: "Nike".to_string(),
+            market_cap_from: Some(100e9),
+            market_cap_to: Some(90e9),
+            absolute_change: Some(-10e9),
+            percentage_change: Some(-10.0),
+            rank_from: Some(2),
+            rank_to: Some(3),
+            rank_change: Some(-1),
+            market_share_from: Some(20.0),
+            market_share_to: Some(18.0),
+            source_from: None,
+            source_to: None,
+            overridden_from: None,
+            overridden_to: None,
+        }];
+        let summary = ComparisonSummary::new(&comparisons);
+        assert_eq!(summary.top_absolute_losses.len(), 1);
+        assert!(summary.rank_improvements.is_empty());
+
+        let mut workbook = Workbook::new();
+        add_ranking_sheet(&mut workbook, "Top 200", &companies).unwrap();
+        add_comparison_sheet(&mut workbook, &comparisons).unwrap();
+        add_summary_sheet(
+            &mut workbook,
+            &summary,
+            "2025-07-01",
+            "2025-08-01",
+            &Locale::default(),
+        )
+        .unwrap();
+        add_table_sheet(
+            &mut workbook,
+            "History",
+            &["Ticker", "Active"],
+            &[vec![Cell::Text("NKE".to_string()), Cell::Bool(true)]],
+        )
+        .unwrap();
+
+        // List names that Excel would reject still get a sheet of their own
+        for name in ["Top 200", "top 200", "EU/US: [Top] 50 by market cap in EUR"] {
+            add_ranking_sheet(&mut workbook, name, &companies).unwrap();
+        }
+
+        let bytes = workbook.save_to_buffer().unwrap();
+        assert_eq!(&bytes[..2], b"PK");
+        let names: Vec<String> = workbook.worksheets().iter().map(|s| s.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "Top 200",
+                "Comparison",
+                "Summary",
+                "History",
+                "Top 200 (2)",
+                "top 200 (3)",
+                "EU_US_ _Top_ 50 by market cap i",
+            ]
+        );
+    }
+}